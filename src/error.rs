@@ -199,10 +199,10 @@ pub enum PoolingError {
     //60
     #[error("Deposit is now paused")]
     DepositPaused,
-    #[error("Borrow is now paused")]
-    BorrowPaused,
-    #[error("Liquidation is now paused")]
-    LiquidationPaused,
+    #[error("Redeem is now paused")]
+    RedeemPaused,
+    #[error("Draw is now paused")]
+    DrawPaused,
     #[error("Can not withdraw all deposit")]
     CanNotWithdrawAllDeposit,
     #[error("Can not reentry")]
@@ -215,6 +215,80 @@ pub enum PoolingError {
     HostFeeReceiversFull,
     #[error("Host fee receiver not fund")]
     HostFeeReceiverNotFund,
+    #[error("No ticket deposits to draw from")]
+    NoTicketDeposits,
+    #[error("Ticket account provided more than once")]
+    DuplicateTicket,
+    #[error("Prize period is not over yet")]
+    PrizePeriodNotOver,
+    #[error("A draw is already pending")]
+    DrawAlreadyPending,
+
+    //70
+    #[error("No draw is pending")]
+    DrawNotPending,
+    #[error("Draw reveal must happen after the commit slot")]
+    DrawRevealTooEarly,
+    #[error("Draw reveal does not match the commitment")]
+    DrawRevealMismatch,
+    #[error("Slot hash not found")]
+    SlotHashNotFound,
+
+    //75
+    #[error("Input randomness config is invalid")]
+    InvalidRandomnessConfig,
+    #[error("Randomness result is not usable for this draw")]
+    InvalidRandomnessResult,
+    #[error("No prize to claim")]
+    NoPrizeToClaim,
+    #[error("Init authority is not allowed to create a pool manager")]
+    InvalidInitAuthority,
+    #[error("Prize claim is now paused")]
+    ClaimPaused,
+
+    //80
+    #[error("Mining claim is now paused")]
+    MiningClaimPaused,
+    #[error("Pool manager is now paused")]
+    PoolManagerPaused,
+    #[error("Signer is not allowed to pause or unpause")]
+    InvalidPauseAuthority,
+    #[error("No whole mine token to claim")]
+    NoMineToClaim,
+    #[error("Mine supply is insufficient for the claim")]
+    InsufficientMineSupply,
+
+    //85
+    #[error("Exit fee exceeds the maximum exit fee accepted")]
+    ExitFeeTooHigh,
+    #[error("No protocol fees to withdraw")]
+    NoProtocolFees,
+    #[error("Yield source provided is invalid")]
+    InvalidYieldSource,
+    #[error("Yield source still holds pool liquidity")]
+    YieldSourceInUse,
+    #[error("Tickets indexed do not cover every ticket balance of the prize period")]
+    DrawTicketsIncomplete,
+
+    //90
+    #[error("Draw commitment has expired")]
+    DrawExpired,
+    #[error("Draw secret has already been revealed")]
+    DrawAlreadyRevealed,
+    #[error("Draw secret has not been revealed")]
+    DrawNotRevealed,
+    #[error("Draw must be settled after the reveal slot")]
+    DrawSettleTooEarly,
+    #[error("Randomness has already been requested for the next draw")]
+    RandomnessAlreadyRequested,
+
+    //95
+    #[error("Draw is awarding its winners")]
+    DrawAwardingWinners,
+    #[error("Draw is not awarding its winners")]
+    DrawNotAwarding,
+    #[error("Ticket holding the next pick of the draw is not provided")]
+    DrawWinnerNotProvided,
 
 }
 
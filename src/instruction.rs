@@ -2,7 +2,7 @@
 
 use crate::{
     error::PoolingError,
-    state::{PoolConfig},
+    state::{MineDistribution, PoolConfig, PrizeTier},
     unpack_util::{
         unpack_u8,
         unpack_u64,
@@ -15,6 +15,7 @@ use solana_program::{
     program_error::ProgramError,
     pubkey::{Pubkey},
 };
+use std::convert::TryFrom;
 // use crate::config::ConfigType;
 use crate::unpack_util::unpack_bool;
 
@@ -22,7 +23,8 @@ use crate::unpack_util::unpack_bool;
 #[derive(Clone, Debug, PartialEq)]
 pub enum PoolingInstruction {
     // 0
-    /// Initializes a new lending market.
+    /// Initializes a new lending market. Only the upgrade authority of the program can initialize
+    /// a pool manager, unless the program is built with the `permissionless-init` feature.
     ///
     /// Accounts expected by this instruction:
     ///   0. `[singer]` Init lending market authority
@@ -30,6 +32,10 @@ pub enum PoolingInstruction {
     ///   2. `[]` Rent sysvar.
     ///   3. `[]` Token program id.
     ///   4. `[]` Pyth oracle program id.
+    ///   5. `[]` Mine token mint.
+    ///   6. `[]` Mine token supply account.
+    ///   7. `[]` Randomness oracle program id.
+    ///   8. `[]` Program data account of the pooling program, owned by the upgradeable loader.
     InitPoolingManager {
         /// Owner authority which can add new reserves
         owner: Pubkey,
@@ -39,12 +45,13 @@ pub enum PoolingInstruction {
     },
 
     // 1
-    /// Sets the new owner of a lending market.
+    /// Proposes a new owner of a pool manager, who takes over with AcceptPoolingManagerOwner.
+    /// Proposing the default pubkey cancels a pending transfer.
     ///
     /// Accounts expected by this instruction:
     ///
     ///
-    ///   0. `[writable]` Lending market account.
+    ///   0. `[writable]` Pool manager account.
     ///   1. `[signer]` Current owner.
     SetPoolingManagerOwner {
         /// The new owner
@@ -78,13 +85,7 @@ pub enum PoolingInstruction {
     ///   13 `[]` Rent sysvar.
     ///   14 `[]` Token program id.
 
-    InitPool {
-        /// Reserve configuration values
-        config: PoolConfig,
-        total_mining_speed: u64,
-        kink_util_rate: u64,
-        use_pyth_oracle: bool,
-    },
+    InitPool(InitPoolArgs),
 
     // 3
     /// Accrue interest and update market price of liquidity on a reserve.
@@ -96,6 +97,10 @@ pub enum PoolingInstruction {
     ///   1. `[]` Reserve liquidity oracle account.
     ///             Must be the Pyth price account specified at InitReserve.
     ///   3. `[]` Clock sysvar.
+    ///
+    ///   Only when the pool has a yield source:
+    ///   4. `[]` Yield source lending reserve account.
+    ///   5. `[]` Pool yield source share supply SPL Token account.
     RefreshPool,
 
     // 4
@@ -142,7 +147,8 @@ pub enum PoolingInstruction {
     },
 
     // 6
-    /// Initializes a new lending market obligation.
+    /// Initializes a new lending market obligation. Deposits of a sponsor ticket earn for the
+    /// prize of their pools without taking part in draws.
     ///
     /// Accounts expected by this instruction:
     ///
@@ -152,7 +158,12 @@ pub enum PoolingInstruction {
     ///   3. `[]` Clock sysvar.
     ///   4. `[]` Rent sysvar.
     ///   5. `[]` Token program id.
-    InitTicket,
+    ///
+    /// Without instruction data, the ticket does not sponsor.
+    InitTicket {
+        /// Whether the ticket sponsors the prize instead of taking part in draws
+        sponsor: bool,
+    },
 
     // 7
     /// Refresh an obligation's accrued interest and collateral and liquidity prices. Requires
@@ -168,15 +179,441 @@ pub enum PoolingInstruction {
     RefreshTicket,
 
     // 8
-    /// Refresh an obligation's accrued interest and collateral and liquidity prices. Requires
-    /// refreshed reserves, as all obligation collateral deposit reserves in order, followed by all
-    /// liquidity borrow reserves in order.
+    /// Draw winners among the tickets indexed by IndexDrawTickets, weighted by their average
+    /// deposit over the prize period, seeded by the randomness oracle result bound to the pool by
+    /// RequestRandomness. The yield accrued since the last draw is credited to the winning
+    /// tickets, to be collected with ClaimPrize. Winners held by the tickets provided are awarded
+    /// right away, the others by AwardDrawWinners. Requires a refreshed pool.
     ///
     /// Accounts expected by this instruction:
     ///
-    ///   0. `[writable]` Ticket account.
-    ///   1. `[]` Clock sysvar.
+    ///   0. `[writable]` Pool account.
+    ///   1. `[]` Pool manager account.
+    ///   2. `[]` Clock sysvar.
+    ///   3. `[]` Randomness result account bound by RequestRandomness.
+    ///   .. `[writable]` Ticket accounts holding the first picks, as in AwardDrawWinners.
     LotteryDraw,
+
+    // 9
+    /// Start a draw of a pool whose prize period is over by committing to the hash of a secret.
+    /// The secret is revealed by CompleteDraw in a later slot, then the draw is run by SettleDraw.
+    /// The draw index must cover every ticket, see IndexDrawTickets.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pool account.
+    ///   1. `[]` Pool manager account.
+    ///   2. `[signer]` Pool manager owner.
+    ///   3. `[]` Clock sysvar.
+    StartDraw {
+        /// SHA-256 hash of the secret revealed by CompleteDraw
+        commitment: [u8; 32],
+    },
+
+    // 10
+    /// Reveal the secret committed by StartDraw before the draw expires. The draw is seeded by the
+    /// secret mixed with the hash of the reveal slot, unknown to anyone at reveal time, and is run
+    /// by SettleDraw in a later slot.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pool account.
+    ///   1. `[]` Pool manager account.
+    ///   2. `[signer]` Pool manager owner.
+    ///   3. `[]` Clock sysvar.
+    CompleteDraw {
+        /// Secret whose hash was committed by StartDraw
+        secret: [u8; 32],
+    },
+
+    // 11
+    /// Claim the prize won by a ticket in a pool, either transferred to the destination liquidity
+    /// account or compounded into the ticket deposit. Requires a refreshed pool.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Ticket account.
+    ///   1. `[writable]` Pool account.
+    ///   2. `[]` Pool manager account.
+    ///   3. `[]` Derived pool manager authority.
+    ///   4. `[signer]` Ticket owner.
+    ///   5. `[writable]` Destination liquidity token account, unused when compounding.
+    ///   6. `[writable]` Pool liquidity supply SPL Token account.
+    ///   7. `[writable]` Pool collateral SPL Token mint.
+    ///   8. `[writable]` Pool collateral supply SPL Token account.
+    ///   9. `[]` Clock sysvar.
+    ///   10. `[]` Token program id.
+    ClaimPrize {
+        /// Deposit the prize back into the pool instead of transferring it
+        compound: bool,
+    },
+
+    // 12
+    /// Deposit liquidity into a pool and credit the collateral to a ticket, which takes part in
+    /// the draws of the pool. The collateral is held in the pool collateral supply.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source liquidity token account.
+    ///                     $authority can transfer $liquidity_amount.
+    ///   1. `[writable]` Ticket account.
+    ///   2. `[writable]` Pool account.
+    ///   3. `[writable]` Pool collateral SPL Token mint.
+    ///   4. `[writable]` Pool collateral supply SPL Token account.
+    ///   5. `[writable]` Pool liquidity supply SPL Token account.
+    ///   6. `[]` Pool manager account.
+    ///   7. `[]` Derived pool manager authority.
+    ///   8. `[signer]` Ticket owner.
+    ///   9. `[signer]` User transfer authority ($authority).
+    ///   10. `[]` Clock sysvar.
+    ///   11. `[]` Token program id.
+    DepositTicketLiquidity {
+        /// Amount of liquidity to deposit for the ticket
+        liquidity_amount: u64,
+    },
+
+    // 13
+    /// Withdraw collateral from a ticket deposit and redeem it for principal liquidity.
+    /// Mining rewards of the deposit are settled first. Withdrawals right after a deposit or a draw
+    /// pay the pool exit fee into the prize. Requires a refreshed pool.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Ticket account.
+    ///   1. `[writable]` Destination liquidity token account.
+    ///   2. `[writable]` Pool account.
+    ///   3. `[writable]` Pool collateral SPL Token mint.
+    ///   4. `[writable]` Pool collateral supply SPL Token account.
+    ///   5. `[writable]` Pool liquidity supply SPL Token account.
+    ///   6. `[]` Pool manager account.
+    ///   7. `[]` Derived pool manager authority.
+    ///   8. `[signer]` Ticket owner.
+    ///   9. `[]` Clock sysvar.
+    ///   10. `[]` Token program id.
+    WithdrawTicketDeposit {
+        /// Amount of collateral to withdraw from the ticket - u64::MAX for the whole deposit
+        collateral_amount: u64,
+        /// Maximum exit fee accepted, in liquidity
+        max_exit_fee: u64,
+    },
+
+    // 14
+    /// Accepts the ownership of a pool manager proposed by SetPoolingManagerOwner.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pool manager account.
+    ///   1. `[signer]` Pending owner.
+    AcceptPoolingManagerOwner,
+
+    // 15
+    /// Update the configuration of a pool. Mining is accrued at the previous speed before the
+    /// speed changes, so the pool must be refreshed again afterwards. The fee receiver and oracle
+    /// accounts are only expected when they change.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pool account.
+    ///   1. `[]` Pool manager account.
+    ///   2. `[signer]` Pool manager owner.
+    ///   3. `[]` Clock sysvar.
+    ///   .. `[]` Pool liquidity fee receiver - if it changes.
+    ///   .. `[]` Pyth product account - if the oracle changes.
+    ///   .. `[]` Pyth price account - if the oracle changes.
+    ///             This will be used as the pool liquidity oracle account.
+    UpdatePoolConfig {
+        /// Pool configuration values
+        config: PoolConfig,
+        /// Amount of mine token for the pool per slot
+        total_mining_speed: u64,
+        /// Utilization rate at which the mine distribution curve jumps, in basis points
+        kink_util_rate: u64,
+        /// Token account receiving the pool liquidity fees
+        liquidity_fee_receiver: Pubkey,
+        /// Pyth price account used as the pool liquidity oracle
+        pyth_oracle: Pubkey,
+    },
+
+    // 16
+    /// Pause or resume every pool of a pool manager at once, regardless of the pool configs.
+    /// The pool manager guardian may pause, only the owner may resume.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pool manager account.
+    ///   1. `[signer]` Pool manager owner or guardian.
+    SetPoolManagerPaused {
+        /// Whether every pool operation is paused
+        paused: bool,
+    },
+
+    // 17
+    /// Sets the guardian of a pool manager, who may pause the pool manager and its pools.
+    /// Setting the default pubkey revokes the guardian.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pool manager account.
+    ///   1. `[signer]` Pool manager owner.
+    SetPoolManagerGuardian {
+        /// The new guardian
+        guardian: Pubkey,
+    },
+
+    // 18
+    /// Pause operations of a pool, on top of the ones already paused.
+    /// Unpausing is only possible through UpdatePoolConfig.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pool account.
+    ///   1. `[]` Pool manager account.
+    ///   2. `[signer]` Pool manager owner or guardian.
+    PausePool {
+        /// Bitmask of the operations to pause
+        operations: u8,
+    },
+
+    // 19
+    /// Claim the whole tokens of the mine accrued by a ticket.
+    /// The fractional remainder stays in the ticket. Pools must be refreshed in the current slot.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Ticket account.
+    ///   1. `[writable]` Pool manager mine supply account.
+    ///   2. `[writable]` Destination mine token account.
+    ///   3. `[]` Pool manager account.
+    ///   4. `[]` Derived pool manager authority.
+    ///   5. `[signer]` Ticket owner.
+    ///   6. `[]` Clock sysvar.
+    ///   7. `[]` Token program id.
+    ///   .. `[]` Pool of every ticket deposit, in order.
+    ClaimMine,
+
+    // 20
+    /// Initializes a new mining account, which mines with collateral not deposited in a ticket.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Mining account - uninitialized.
+    ///   1. `[]` Pool manager account.
+    ///   2. `[signer]` Mining owner.
+    ///   3. `[]` Rent sysvar.
+    InitMining,
+
+    // 21
+    /// Deposit collateral into a mining account. The collateral is held in the pool
+    /// un-collateralized supply. Mining rewards of the pool are settled first. Requires a refreshed pool.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source collateral token account.
+    ///                     $authority can transfer $collateral_amount.
+    ///   1. `[writable]` Mining account.
+    ///   2. `[]` Pool account.
+    ///   3. `[writable]` Pool un-collateralized supply SPL Token account.
+    ///   4. `[]` Pool manager account.
+    ///   5. `[signer]` Mining owner.
+    ///   6. `[signer]` User transfer authority ($authority).
+    ///   7. `[]` Clock sysvar.
+    ///   8. `[]` Token program id.
+    DepositMining {
+        /// Amount of collateral to deposit
+        collateral_amount: u64,
+    },
+
+    // 22
+    /// Withdraw collateral from a mining account. Mining rewards of the pool are settled first.
+    /// Requires a refreshed pool.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Destination collateral token account.
+    ///   1. `[writable]` Mining account.
+    ///   2. `[]` Pool account.
+    ///   3. `[writable]` Pool un-collateralized supply SPL Token account.
+    ///   4. `[]` Pool manager account.
+    ///   5. `[]` Derived pool manager authority.
+    ///   6. `[signer]` Mining owner.
+    ///   7. `[]` Clock sysvar.
+    ///   8. `[]` Token program id.
+    WithdrawMining {
+        /// Amount of collateral to withdraw - u64::MAX for the whole mining deposit
+        collateral_amount: u64,
+    },
+
+    // 23
+    /// Withdraw the protocol fees skimmed from the prizes of a pool to the pool fee receiver.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pool account.
+    ///   1. `[writable]` Pool liquidity supply SPL Token account.
+    ///   2. `[writable]` Pool liquidity fee receiver.
+    ///   3. `[]` Pool manager account.
+    ///   4. `[]` Derived pool manager authority.
+    ///   5. `[signer]` Pool manager owner.
+    ///   6. `[]` Token program id.
+    WithdrawProtocolFees,
+
+    // 24
+    /// Add liquidity straight to the prize of a pool, spread evenly over the next draws.
+    /// No collateral is minted, the contribution can never be redeemed.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source liquidity token account.
+    ///                     $authority can transfer $liquidity_amount.
+    ///   1. `[writable]` Pool account.
+    ///   2. `[writable]` Pool liquidity supply SPL Token account.
+    ///   3. `[]` Pool manager account.
+    ///   4. `[signer]` User transfer authority ($authority).
+    ///   5. `[]` Token program id.
+    ContributeToPrize {
+        /// Amount of liquidity to contribute
+        liquidity_amount: u64,
+        /// Number of draws sharing the contribution, starting with the next one
+        draws: u8,
+    },
+
+    // 25
+    /// Sets the external lending program a pool supplies liquidity to. Only possible while
+    /// the current yield source holds no pool liquidity.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pool account.
+    ///   1. `[]` Pool manager account.
+    ///   2. `[]` Derived pool manager authority.
+    ///   3. `[signer]` Pool manager owner.
+    ///   4. `[]` Lending program id.
+    ///   5. `[]` Lending reserve account, lending the pool liquidity mint.
+    ///   6. `[]` Pool share supply SPL Token account.
+    ///             Must hold reserve shares and be owned by the pool manager authority.
+    SetPoolYieldSource,
+
+    // 26
+    /// Supply available pool liquidity to the pool yield source. Liquidity supplied out is not
+    /// available to WithdrawTicketDeposit, RedeemPoolCollateral or borrows until it is brought
+    /// back with RedeemYieldSource, so the pool manager keeps enough liquidity in the pool for them.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pool account.
+    ///   1. `[writable]` Pool liquidity supply SPL Token account.
+    ///   2. `[writable]` Pool share supply SPL Token account.
+    ///   3. `[writable]` Lending reserve account.
+    ///   4. `[writable]` Lending reserve liquidity supply SPL Token account.
+    ///   5. `[writable]` Lending reserve share mint.
+    ///   6. `[]` Lending reserve authority.
+    ///   7. `[]` Lending program id.
+    ///   8. `[]` Pool manager account.
+    ///   9. `[]` Derived pool manager authority.
+    ///   10. `[signer]` Pool manager owner.
+    ///   11. `[]` Token program id.
+    SupplyYieldSource {
+        /// Amount of liquidity to supply
+        liquidity_amount: u64,
+    },
+
+    // 27
+    /// Redeem reserve shares of the pool yield source back into available pool liquidity.
+    /// The pool is credited with the liquidity the lending program actually paid out.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   Same accounts as SupplyYieldSource.
+    RedeemYieldSource {
+        /// Amount of reserve shares to redeem - u64::MAX for all of them
+        share_amount: u64,
+    },
+
+    // 28
+    /// Draw winners among the tickets indexed by IndexDrawTickets, weighted by their average
+    /// deposit over the prize period, seeded by the secret revealed by CompleteDraw and the hash
+    /// of its reveal slot. Anyone can settle a revealed draw until the hash of the reveal slot
+    /// leaves the SlotHashes sysvar, after which the draw can be started again. The yield accrued
+    /// since the last draw is credited to the winning tickets, to be collected with ClaimPrize.
+    /// Winners held by the tickets provided are awarded right away, the others by
+    /// AwardDrawWinners. Requires a refreshed pool.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pool account.
+    ///   1. `[]` Pool manager account.
+    ///   2. `[]` Clock sysvar.
+    ///   3. `[]` SlotHashes sysvar.
+    ///   .. `[writable]` Ticket accounts holding the first picks, as in AwardDrawWinners.
+    SettleDraw,
+
+    // 29
+    /// Bind the next LotteryDraw of a pool whose prize period is over to a randomness oracle
+    /// request made for the pool after the period ended and not fulfilled yet. The request cannot
+    /// be replaced until the draw. The draw index must cover every ticket, see IndexDrawTickets.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pool account.
+    ///   1. `[]` Pool manager account.
+    ///   2. `[signer]` Pool manager owner.
+    ///   3. `[]` Clock sysvar.
+    ///   4. `[]` Randomness result account, owned by the randomness oracle program.
+    RequestRandomness,
+
+    // 30
+    /// Claim the whole tokens of the mine accrued by a mining account.
+    /// The fractional remainder stays in the account. Pools must be refreshed in the current slot.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   Same accounts as ClaimMine, with the mining account and its owner in place of the
+    ///   ticket and its owner, and the pool of every mining index, in order.
+    ClaimMining,
+
+    // 31
+    /// Add tickets holding deposits in a pool whose prize period is over to the index of its
+    /// draw, each holding a range of the index as long as its cumulative deposit over the prize
+    /// period. Anyone can index tickets, over as many transactions as needed. Tickets already
+    /// indexed, sponsor tickets and tickets without deposits during the period are skipped. The
+    /// draw cannot be started until the index covers the cumulative ticket balance of the period.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pool account.
+    ///   1. `[]` Pool manager account.
+    ///   2. `[]` Clock sysvar.
+    ///   .. `[writable]` Ticket accounts to index.
+    IndexDrawTickets,
+
+    // 32
+    /// Award the next winners of a draw seeded by LotteryDraw or SettleDraw. Each pick is a point
+    /// of the draw index, won by the ticket holding it unless it already won in the draw, in which
+    /// case the draw picks again. Picks are awarded until the ticket holding the next one is not
+    /// provided. The draw is recorded once every winner is awarded or it made its max number of
+    /// picks, see MAX_DRAW_REPICKS, leaving the prize of the winners not awarded to the next draw.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pool account.
+    ///   1. `[]` Pool manager account.
+    ///   2. `[]` Clock sysvar.
+    ///   .. `[writable]` Ticket accounts holding the next picks.
+    AwardDrawWinners,
+}
+
+/// Pool parameters of InitPool
+#[derive(Clone, Debug, PartialEq)]
+pub struct InitPoolArgs {
+    /// Reserve configuration values, checked like in UpdatePoolConfig
+    pub config: PoolConfig,
+    pub total_mining_speed: u64,
+    pub kink_util_rate: u64,
+    pub use_pyth_oracle: bool,
+    /// Length of a prize period in slots
+    pub prize_period_slots: u64,
+    /// Prize distribution, from the grand prize down
+    pub prize_tiers: Vec<PrizeTier>,
 }
 
 impl PoolingInstruction {
@@ -200,17 +637,30 @@ impl PoolingInstruction {
                 Self::SetPoolingManagerOwner { new_owner }
             }
             2 => {
+                let (config, rest) = unpack_pool_config(rest)?;
                 let (total_mining_speed, rest) = unpack_u64(rest)?;
                 let (kink_util_rate, rest) = unpack_u64(rest)?;
-                let (use_pyth_oracle, _rest) = unpack_bool(rest)?;
-                Self::InitPool {
-                    config: PoolConfig {
-                        deposit_paused: false,
-                    },
+                let (use_pyth_oracle, rest) = unpack_bool(rest)?;
+                let (prize_period_slots, rest) = unpack_u64(rest)?;
+                let (prize_tiers_len, mut rest) = unpack_u8(rest)?;
+                let mut prize_tiers = Vec::with_capacity(prize_tiers_len as usize);
+                for _ in 0..prize_tiers_len {
+                    let (percent, next) = unpack_u8(rest)?;
+                    let (winner_count, next) = unpack_u8(next)?;
+                    prize_tiers.push(PrizeTier {
+                        percent,
+                        winner_count,
+                    });
+                    rest = next;
+                }
+                Self::InitPool(InitPoolArgs {
+                    config,
                     total_mining_speed,
                     kink_util_rate,
                     use_pyth_oracle,
-                }
+                    prize_period_slots,
+                    prize_tiers,
+                })
             }
             3 => Self::RefreshPool,
             4 => {
@@ -221,11 +671,105 @@ impl PoolingInstruction {
                 let (collateral_amount, _rest) = unpack_u64(rest)?;
                 Self::RedeemPoolCollateral { collateral_amount }
             }
-            6 => Self::InitTicket,
+            6 => {
+                // tickets initialized before sponsorship send no data
+                let sponsor = if rest.is_empty() {
+                    false
+                } else {
+                    unpack_bool(rest)?.0
+                };
+                Self::InitTicket { sponsor }
+            }
             7 => Self::RefreshTicket,
             8 => {
                 Self::LotteryDraw
             }
+            9 => {
+                let (commitment, _rest) = unpack_bytes32(rest)?;
+                Self::StartDraw {
+                    commitment: *commitment,
+                }
+            }
+            10 => {
+                let (secret, _rest) = unpack_bytes32(rest)?;
+                Self::CompleteDraw { secret: *secret }
+            }
+            11 => {
+                let (compound, _rest) = unpack_bool(rest)?;
+                Self::ClaimPrize { compound }
+            }
+            12 => {
+                let (liquidity_amount, _rest) = unpack_u64(rest)?;
+                Self::DepositTicketLiquidity { liquidity_amount }
+            }
+            13 => {
+                let (collateral_amount, rest) = unpack_u64(rest)?;
+                let (max_exit_fee, _rest) = unpack_u64(rest)?;
+                Self::WithdrawTicketDeposit {
+                    collateral_amount,
+                    max_exit_fee,
+                }
+            }
+            14 => Self::AcceptPoolingManagerOwner,
+            15 => {
+                let (config, rest) = unpack_pool_config(rest)?;
+                let (total_mining_speed, rest) = unpack_u64(rest)?;
+                let (kink_util_rate, rest) = unpack_u64(rest)?;
+                let (liquidity_fee_receiver, rest) = unpack_pubkey(rest)?;
+                let (pyth_oracle, _rest) = unpack_pubkey(rest)?;
+                Self::UpdatePoolConfig {
+                    config,
+                    total_mining_speed,
+                    kink_util_rate,
+                    liquidity_fee_receiver,
+                    pyth_oracle,
+                }
+            }
+            16 => {
+                let (paused, _rest) = unpack_bool(rest)?;
+                Self::SetPoolManagerPaused { paused }
+            }
+            17 => {
+                let (guardian, _rest) = unpack_pubkey(rest)?;
+                Self::SetPoolManagerGuardian { guardian }
+            }
+            18 => {
+                let (operations, _rest) = unpack_u8(rest)?;
+                Self::PausePool { operations }
+            }
+            19 => Self::ClaimMine,
+            20 => Self::InitMining,
+            21 => {
+                let (collateral_amount, _rest) = unpack_u64(rest)?;
+                Self::DepositMining { collateral_amount }
+            }
+            22 => {
+                let (collateral_amount, _rest) = unpack_u64(rest)?;
+                Self::WithdrawMining { collateral_amount }
+            }
+            23 => Self::WithdrawProtocolFees,
+            24 => {
+                let (liquidity_amount, rest) = unpack_u64(rest)?;
+                let (draws, _rest) = unpack_u8(rest)?;
+                Self::ContributeToPrize {
+                    liquidity_amount,
+                    draws,
+                }
+            }
+            25 => Self::SetPoolYieldSource,
+            26 => {
+                let (liquidity_amount, _rest) = unpack_u64(rest)?;
+                Self::SupplyYieldSource { liquidity_amount }
+            }
+            27 => {
+                let (share_amount, _rest) = unpack_u64(rest)?;
+                Self::RedeemYieldSource { share_amount }
+            }
+            28 => Self::SettleDraw,
+            29 => Self::RequestRandomness,
+            30 => Self::ClaimMining,
+            31 => Self::IndexDrawTickets,
+            32 => Self::AwardDrawWinners,
             _ => {
                 msg!("Instruction cannot be unpacked");
                 return Err(PoolingError::InstructionUnpackError.into());
@@ -234,3 +778,128 @@ impl PoolingInstruction {
     }
 }
 
+/// Unpacks the pool configuration values shared by InitPool and UpdatePoolConfig
+fn unpack_pool_config(input: &[u8]) -> Result<(PoolConfig, &[u8]), ProgramError> {
+    let (paused, rest) = unpack_u8(input)?;
+    let (mine_distribution, rest) = unpack_u8(rest)?;
+    let (exit_fee_wad, rest) = unpack_u64(rest)?;
+    let (exit_fee_decay_slots, rest) = unpack_u64(rest)?;
+    let (protocol_fee_wad, rest) = unpack_u64(rest)?;
+    let (optimal_utilization_rate, rest) = unpack_u8(rest)?;
+    let (min_borrow_rate, rest) = unpack_u8(rest)?;
+    let (optimal_borrow_rate, rest) = unpack_u8(rest)?;
+    let (max_borrow_rate, rest) = unpack_u8(rest)?;
+    let (reserve_owner_fee_wad, rest) = unpack_u64(rest)?;
+    Ok((
+        PoolConfig {
+            paused,
+            mine_distribution: MineDistribution::try_from(mine_distribution)?,
+            exit_fee_wad,
+            exit_fee_decay_slots,
+            protocol_fee_wad,
+            optimal_utilization_rate,
+            min_borrow_rate,
+            optimal_borrow_rate,
+            max_borrow_rate,
+            reserve_owner_fee_wad,
+        },
+        rest,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn init_pool_unpacks_config() {
+        let mut data = vec![2, 0, 1];
+        data.extend_from_slice(&5u64.to_le_bytes()); // exit_fee_wad
+        data.extend_from_slice(&10u64.to_le_bytes()); // exit_fee_decay_slots
+        data.extend_from_slice(&7u64.to_le_bytes()); // protocol_fee_wad
+        data.extend_from_slice(&[80, 0, 4, 30]); // borrow rate model
+        data.extend_from_slice(&3u64.to_le_bytes()); // reserve_owner_fee_wad
+        data.extend_from_slice(&100u64.to_le_bytes()); // total_mining_speed
+        data.extend_from_slice(&50u64.to_le_bytes()); // kink_util_rate
+        data.push(0); // use_pyth_oracle
+        data.extend_from_slice(&1_000u64.to_le_bytes()); // prize_period_slots
+        data.extend_from_slice(&[1, 100, 1]); // prize tiers
+
+        assert_eq!(
+            PoolingInstruction::unpack(&data).unwrap(),
+            PoolingInstruction::InitPool(InitPoolArgs {
+                config: PoolConfig {
+                    paused: 0,
+                    mine_distribution: MineDistribution::try_from(1).unwrap(),
+                    exit_fee_wad: 5,
+                    exit_fee_decay_slots: 10,
+                    protocol_fee_wad: 7,
+                    optimal_utilization_rate: 80,
+                    min_borrow_rate: 0,
+                    optimal_borrow_rate: 4,
+                    max_borrow_rate: 30,
+                    reserve_owner_fee_wad: 3,
+                },
+                total_mining_speed: 100,
+                kink_util_rate: 50,
+                use_pyth_oracle: false,
+                prize_period_slots: 1_000,
+                prize_tiers: vec![PrizeTier {
+                    percent: 100,
+                    winner_count: 1,
+                }],
+            })
+        );
+        assert!(PoolingInstruction::unpack(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn update_pool_config_unpacks_accounts_to_change() {
+        let liquidity_fee_receiver = Pubkey::new_unique();
+        let pyth_oracle = Pubkey::new_unique();
+        let mut data = vec![15, 0, 0];
+        data.extend_from_slice(&0u64.to_le_bytes()); // exit_fee_wad
+        data.extend_from_slice(&0u64.to_le_bytes()); // exit_fee_decay_slots
+        data.extend_from_slice(&0u64.to_le_bytes()); // protocol_fee_wad
+        data.extend_from_slice(&[80, 0, 4, 30]); // borrow rate model
+        data.extend_from_slice(&0u64.to_le_bytes()); // reserve_owner_fee_wad
+        data.extend_from_slice(&100u64.to_le_bytes()); // total_mining_speed
+        data.extend_from_slice(&50u64.to_le_bytes()); // kink_util_rate
+        data.extend_from_slice(liquidity_fee_receiver.as_ref());
+        data.extend_from_slice(pyth_oracle.as_ref());
+
+        match PoolingInstruction::unpack(&data).unwrap() {
+            PoolingInstruction::UpdatePoolConfig {
+                total_mining_speed,
+                kink_util_rate,
+                liquidity_fee_receiver: unpacked_fee_receiver,
+                pyth_oracle: unpacked_oracle,
+                ..
+            } => {
+                assert_eq!(total_mining_speed, 100);
+                assert_eq!(kink_util_rate, 50);
+                assert_eq!(unpacked_fee_receiver, liquidity_fee_receiver);
+                assert_eq!(unpacked_oracle, pyth_oracle);
+            }
+            instruction => panic!("Unexpected instruction {:?}", instruction),
+        }
+        assert!(PoolingInstruction::unpack(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn init_ticket_unpacks_sponsor() {
+        assert_eq!(
+            PoolingInstruction::unpack(&[6]).unwrap(),
+            PoolingInstruction::InitTicket { sponsor: false }
+        );
+        assert_eq!(
+            PoolingInstruction::unpack(&[6, 0]).unwrap(),
+            PoolingInstruction::InitTicket { sponsor: false }
+        );
+        assert_eq!(
+            PoolingInstruction::unpack(&[6, 1]).unwrap(),
+            PoolingInstruction::InitTicket { sponsor: true }
+        );
+        assert!(PoolingInstruction::unpack(&[6, 2]).is_err());
+    }
+}
@@ -11,8 +11,10 @@ pub mod instruction;
 pub mod math;
 pub mod processor;
 pub mod pyth;
+pub mod randomness;
 pub mod state;
 pub mod unpack_util;
+pub mod yield_source;
 
 
 solana_program::declare_id!("TokenLending1111111111111111111111111111111");
@@ -5,6 +5,7 @@ use std::convert::TryInto;
 use num_traits::FromPrimitive;
 use solana_program::{
     account_info::{AccountInfo, next_account_info},
+    bpf_loader_upgradeable,
     decode_error::DecodeError,
     entrypoint::ProgramResult,
     instruction::Instruction,
@@ -12,26 +13,31 @@ use solana_program::{
     program::{invoke, invoke_signed},
     program_error::{PrintProgramError, ProgramError},
     program_pack::{IsInitialized, Pack},
+    clock::Slot,
+    hash::hashv,
     pubkey::Pubkey,
-    sysvar::{clock::Clock, rent::Rent, Sysvar},
+    sysvar::{clock::Clock, rent::Rent, slot_hashes, Sysvar},
 };
 use spl_token::solana_program::instruction::AccountMeta;
 use spl_token::state::{Account, Mint};
 
 use crate::{
     error::PoolingError,
-    instruction::PoolingInstruction,
+    instruction::{InitPoolArgs, PoolingInstruction},
     math::{Decimal, Rate, TryAdd, TryDiv, TryMul},
-    pyth,
+    pyth, randomness,
+    yield_source::{self, LendingAccounts, LendingInstruction, LendingReserve},
     state::{
         CalculateBorrowResult, CalculateLiquidationResult, CalculateRepayResult,
         InitPoolManagerParams, InitTicketParams, InitPoolParams, PoolManager,
         NewReserveCollateralParams, NewReserveLiquidityParams, Ticket, Pool,
-        ReserveCollateral, PoolConfig, ReserveLiquidity,
+        ReserveCollateral, PoolConfig, ReserveLiquidity, MAX_KINK_UTIL_RATE,
     },
 };
 use crate::math::{TrySub, WAD};
-use crate::state::{Lottery, init_pool_accounts_index, InitBonusParams, InitMiningParams, Mining};
+use crate::unpack_util::{unpack_bool, unpack_bytes32, unpack_pubkey, unpack_u64};
+use crate::state::{Lottery, init_pool_accounts_index, InitBonusParams, InitMiningParams, Mining, InitPrizeParams, Prize};
+use crate::state::{PAUSE_CLAIM, PAUSE_DEPOSIT, PAUSE_DRAW, PAUSE_MINING_CLAIM, PAUSE_REDEEM};
 
 
 /// Processes an instruction
@@ -49,18 +55,13 @@ pub fn process_instruction(
             msg!("Instruction: Init Pool Manager");
             process_init_pool_manager(program_id, owner, quote_currency, accounts)
         }
-        PoolingInstruction::InitPool {
-            config,
-            total_mining_speed,
-            kink_util_rate,
-            use_pyth_oracle
-        } => {
+        PoolingInstruction::InitPool(args) => {
             msg!("Instruction: Init Pool");
-            process_init_pool(program_id, config, total_mining_speed, kink_util_rate, use_pyth_oracle, accounts)
+            process_init_pool(program_id, args, accounts)
         }
-        PoolingInstruction::InitTicket => {
+        PoolingInstruction::InitTicket { sponsor } => {
             msg!("Instruction: Init Ticket");
-            process_init_ticket(program_id, accounts)
+            process_init_ticket(program_id, sponsor, accounts)
         }
         PoolingInstruction::DepositPoolLiquidity { liquidity_amount } => {
             msg!("Instruction: Deposit Reserve Liquidity into pool");
@@ -91,9 +92,125 @@ pub fn process_instruction(
             process_refresh_ticket(program_id, accounts)
         }
         PoolingInstruction::LotteryDraw => {
-            msg!("Instruction: Set Pool Manager Owner");
+            msg!("Instruction: Lottery Draw");
             process_lottery_draw(program_id, accounts)
         }
+        PoolingInstruction::StartDraw { commitment } => {
+            msg!("Instruction: Start Draw");
+            process_start_draw(program_id, commitment, accounts)
+        }
+        PoolingInstruction::CompleteDraw { secret } => {
+            msg!("Instruction: Complete Draw");
+            process_complete_draw(program_id, secret, accounts)
+        }
+        PoolingInstruction::ClaimPrize { compound } => {
+            msg!("Instruction: Claim Prize");
+            process_claim_prize(program_id, compound, accounts)
+        }
+        PoolingInstruction::DepositTicketLiquidity { liquidity_amount } => {
+            msg!("Instruction: Deposit Ticket Liquidity");
+            process_deposit_ticket_liquidity(program_id, liquidity_amount, accounts)
+        }
+        PoolingInstruction::WithdrawTicketDeposit {
+            collateral_amount,
+            max_exit_fee,
+        } => {
+            msg!("Instruction: Withdraw Ticket Deposit");
+            process_withdraw_ticket_deposit(program_id, collateral_amount, max_exit_fee, accounts)
+        }
+        PoolingInstruction::AcceptPoolingManagerOwner => {
+            msg!("Instruction: Accept Pool Manager Owner");
+            process_accept_pool_manager_owner(program_id, accounts)
+        }
+        PoolingInstruction::UpdatePoolConfig {
+            config,
+            total_mining_speed,
+            kink_util_rate,
+            liquidity_fee_receiver,
+            pyth_oracle,
+        } => {
+            msg!("Instruction: Update Pool Config");
+            process_update_pool_config(
+                program_id,
+                config,
+                total_mining_speed,
+                kink_util_rate,
+                liquidity_fee_receiver,
+                pyth_oracle,
+                accounts,
+            )
+        }
+        PoolingInstruction::SetPoolManagerPaused { paused } => {
+            msg!("Instruction: Set Pool Manager Paused");
+            process_set_pool_manager_paused(program_id, paused, accounts)
+        }
+        PoolingInstruction::SetPoolManagerGuardian { guardian } => {
+            msg!("Instruction: Set Pool Manager Guardian");
+            process_set_pool_manager_guardian(program_id, guardian, accounts)
+        }
+        PoolingInstruction::PausePool { operations } => {
+            msg!("Instruction: Pause Pool");
+            process_pause_pool(program_id, operations, accounts)
+        }
+        PoolingInstruction::ClaimMine => {
+            msg!("Instruction: Claim Mine");
+            process_claim_mine(program_id, false, accounts)
+        }
+        PoolingInstruction::InitMining => {
+            msg!("Instruction: Init Mining");
+            process_init_mining(program_id, accounts)
+        }
+        PoolingInstruction::DepositMining { collateral_amount } => {
+            msg!("Instruction: Deposit Mining");
+            process_deposit_mining(program_id, collateral_amount, accounts)
+        }
+        PoolingInstruction::WithdrawMining { collateral_amount } => {
+            msg!("Instruction: Withdraw Mining");
+            process_withdraw_mining(program_id, collateral_amount, accounts)
+        }
+        PoolingInstruction::WithdrawProtocolFees => {
+            msg!("Instruction: Withdraw Protocol Fees");
+            process_withdraw_protocol_fees(program_id, accounts)
+        }
+        PoolingInstruction::ContributeToPrize {
+            liquidity_amount,
+            draws,
+        } => {
+            msg!("Instruction: Contribute To Prize");
+            process_contribute_to_prize(program_id, liquidity_amount, draws, accounts)
+        }
+        PoolingInstruction::SetPoolYieldSource => {
+            msg!("Instruction: Set Pool Yield Source");
+            process_set_pool_yield_source(program_id, accounts)
+        }
+        PoolingInstruction::SupplyYieldSource { liquidity_amount } => {
+            msg!("Instruction: Supply Yield Source");
+            process_yield_source_liquidity(program_id, liquidity_amount, true, accounts)
+        }
+        PoolingInstruction::RedeemYieldSource { share_amount } => {
+            msg!("Instruction: Redeem Yield Source");
+            process_yield_source_liquidity(program_id, share_amount, false, accounts)
+        }
+        PoolingInstruction::SettleDraw => {
+            msg!("Instruction: Settle Draw");
+            process_settle_draw(program_id, accounts)
+        }
+        PoolingInstruction::RequestRandomness => {
+            msg!("Instruction: Request Randomness");
+            process_request_randomness(program_id, accounts)
+        }
+        PoolingInstruction::IndexDrawTickets => {
+            msg!("Instruction: Index Draw Tickets");
+            process_index_draw_tickets(program_id, accounts)
+        }
+        PoolingInstruction::AwardDrawWinners => {
+            msg!("Instruction: Award Draw Winners");
+            process_award_draw_winners(program_id, accounts)
+        }
+        PoolingInstruction::ClaimMining => {
+            msg!("Instruction: Claim Mining");
+            process_claim_mine(program_id, true, accounts)
+        }
     }
 }
 
@@ -112,15 +229,9 @@ fn process_init_pool_manager(
     let pyth_oracle_program_id = next_account_info(account_info_iter)?;
     let mine_account_info = next_account_info(account_info_iter)?;
     let mine_supply_account_info = next_account_info(account_info_iter)?;
-    // for open source, this restrict can be lifted
-    if init_pool_manager_authority_info.key.to_string() != "7NzERexiPdyiNp5whD74AwTDpALp5VgPta6hmdcGuNm9" {
-        msg!("Can not init pool manager");
-        return Err(PoolingError::InvalidAccountInput.into());
-    }
-    if !init_pool_manager_authority_info.is_signer {
-        msg!("Init pool manager authority account must be a signer");
-        return Err(PoolingError::InvalidSigner.into());
-    }
+    let randomness_program_id = next_account_info(account_info_iter)?;
+    let program_data_info = next_account_info(account_info_iter)?;
+    assert_init_authority(program_id, program_data_info, init_pool_manager_authority_info)?;
     assert_rent_exempt(rent, pool_manager_info)?;
     let mut pool_manager = assert_uninitialized::<PoolManager>(pool_manager_info)?;
     if pool_manager_info.owner != program_id {
@@ -135,6 +246,7 @@ fn process_init_pool_manager(
         oracle_program_id: *pyth_oracle_program_id.key,
         mine_mint: *mine_account_info.key,
         mine_supply_account: *mine_supply_account_info.key,
+        randomness_program_id: *randomness_program_id.key,
     });
     PoolManager::pack(pool_manager, &mut pool_manager_info.data.borrow_mut())?;
     Ok(())
@@ -142,23 +254,187 @@ fn process_init_pool_manager(
 
 #[inline(never)] // avoid stack frame limit
 fn process_set_pool_manager_owner(
-    _program_id: &Pubkey,
-    _new_owner: Pubkey,
-    _accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    new_owner: Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pool_manager_owner_info = next_account_info(account_info_iter)?;
+
+    let mut pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.owner != pool_manager_owner_info.key {
+        msg!("Pool manager owner does not match the pool manager owner provided");
+        return Err(PoolingError::InvalidMarketOwner.into());
+    }
+    if !pool_manager_owner_info.is_signer {
+        msg!("Pool manager owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+
+    if new_owner == Pubkey::default() {
+        msg!("Pending owner {} cancelled", pool_manager.pending_owner);
+    } else {
+        msg!("Pending owner set to {}", new_owner);
+    }
+    pool_manager.set_pending_owner(new_owner);
+    PoolManager::pack(pool_manager, &mut pool_manager_info.data.borrow_mut())?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_accept_pool_manager_owner(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pending_owner_info = next_account_info(account_info_iter)?;
+
+    let mut pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if !pending_owner_info.is_signer {
+        msg!("Pool manager pending owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+
+    pool_manager.accept_owner(pending_owner_info.key)?;
+    msg!("Pool manager owner set to {}", pool_manager.owner);
+    PoolManager::pack(pool_manager, &mut pool_manager_info.data.borrow_mut())?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_set_pool_manager_paused(
+    program_id: &Pubkey,
+    paused: bool,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pause_authority_info = next_account_info(account_info_iter)?;
+
+    let mut pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if !pause_authority_info.is_signer {
+        msg!("Pause authority provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+    pool_manager.assert_pause_authority(pause_authority_info.key, !paused)?;
+
+    msg!("Pool manager paused changed from {} to {}", pool_manager.paused, paused);
+    pool_manager.paused = paused;
+    PoolManager::pack(pool_manager, &mut pool_manager_info.data.borrow_mut())?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_set_pool_manager_guardian(
+    program_id: &Pubkey,
+    guardian: Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pool_manager_owner_info = next_account_info(account_info_iter)?;
+
+    let mut pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.owner != pool_manager_owner_info.key {
+        msg!("Pool manager owner does not match the pool manager owner provided");
+        return Err(PoolingError::InvalidMarketOwner.into());
+    }
+    if !pool_manager_owner_info.is_signer {
+        msg!("Pool manager owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+
+    if guardian == Pubkey::default() {
+        msg!("Guardian {} revoked", pool_manager.guardian);
+    } else {
+        msg!("Guardian changed from {} to {}", pool_manager.guardian, guardian);
+    }
+    pool_manager.set_guardian(guardian);
+    PoolManager::pack(pool_manager, &mut pool_manager_info.data.borrow_mut())?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_pause_pool(
+    program_id: &Pubkey,
+    operations: u8,
+    accounts: &[AccountInfo],
 ) -> ProgramResult {
-    msg!("Abandoned method ");
+    let account_info_iter = &mut accounts.iter();
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pause_authority_info = next_account_info(account_info_iter)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if !pause_authority_info.is_signer {
+        msg!("Pause authority provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+    pool_manager.assert_pause_authority(pause_authority_info.key, false)?;
+
+    let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+
+    let previous = pool.config.paused;
+    pool.config.pause(operations)?;
+    msg!("Paused operations changed from {:#b} to {:#b}", previous, pool.config.paused);
+    Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
     Ok(())
 }
 
 #[inline(never)] // avoid stack frame limit
 fn process_init_pool(
     program_id: &Pubkey,
-    config: PoolConfig,
-    total_mining_speed: u64,
-    kink_util_rate: u64,
-    use_pyth_oracle: bool,
+    args: InitPoolArgs,
     accounts: &[AccountInfo],
 ) -> ProgramResult {
+    let InitPoolArgs {
+        config,
+        total_mining_speed,
+        kink_util_rate,
+        use_pyth_oracle,
+        prize_period_slots,
+        prize_tiers,
+    } = args;
+    if prize_period_slots == 0 {
+        msg!("Prize period must be at least one slot");
+        return Err(PoolingError::InvalidConfig.into());
+    }
+    if kink_util_rate > MAX_KINK_UTIL_RATE {
+        msg!("Kink utilization rate must be in range [0, 10000] basis points");
+        return Err(PoolingError::InvalidConfig.into());
+    }
+    config.validate()?;
+    Prize::validate_tiers(&prize_tiers)?;
     let clock = &Clock::from_account_info(accounts.get(init_pool_accounts_index::CLOCK_SYSVAR).ok_or(PoolingError::InvalidAccountInput)?)?;
     let rent = &Rent::from_account_info(accounts.get(init_pool_accounts_index::RENT_SYSVAR).ok_or(PoolingError::InvalidAccountInput)?)?;
     assert_rent_exempt(rent, accounts.get(init_pool_accounts_index::RESERVE_ACCOUNT)
@@ -202,44 +478,11 @@ fn process_init_pool(
         msg!("Lending market token program does not match the token program provided");
         return Err(PoolingError::InvalidTokenProgram.into());
     }
-    if &pool_manager.oracle_program_id != accounts.get(init_pool_accounts_index::PYTH_PRODUCT).ok_or(PoolingError::InvalidAccountInput)?.owner {
-        msg!("Pyth product account provided is not owned by the lending market oracle program");
-        return Err(PoolingError::InvalidOracleConfig.into());
-    }
-    if &pool_manager.oracle_program_id != accounts.get(init_pool_accounts_index::PYTH_PRICE).ok_or(PoolingError::InvalidAccountInput)?.owner {
-        msg!("Pyth price account provided is not owned by the lending market oracle program");
-        return Err(PoolingError::InvalidOracleConfig.into());
-    }
-    let pyth_product_data = accounts.get(init_pool_accounts_index::PYTH_PRODUCT).ok_or(PoolingError::InvalidAccountInput)?.try_borrow_data()?;
-    let pyth_product = pyth::load::<pyth::Product>(&pyth_product_data)
-        .map_err(|_| ProgramError::InvalidAccountData)?;
-    if pyth_product.magic != pyth::MAGIC {
-        msg!("Pyth product account provided is not a valid Pyth account");
-        return Err(PoolingError::InvalidOracleConfig.into());
-    }
-    if pyth_product.ver != pyth::VERSION_2 {
-        msg!("Pyth product account provided has a different version than expected");
-        return Err(PoolingError::InvalidOracleConfig.into());
-    }
-    if pyth_product.atype != pyth::AccountType::Product as u32 {
-        msg!("Pyth product account provided is not a valid Pyth product account");
-        return Err(PoolingError::InvalidOracleConfig.into());
-    }
-
-    let pyth_price_pubkey_bytes: &[u8; 32] = accounts.get(init_pool_accounts_index::PYTH_PRICE).ok_or(PoolingError::InvalidAccountInput)?
-        .key
-        .as_ref()
-        .try_into()
-        .map_err(|_| PoolingError::InvalidAccountInput)?;
-    if &pyth_product.px_acc.val != pyth_price_pubkey_bytes {
-        msg!("Pyth product price account does not match the Pyth price provided");
-        return Err(PoolingError::InvalidOracleConfig.into());
-    }
-    let quote_currency = get_pyth_product_quote_currency(pyth_product)?;
-    if pool_manager.quote_currency != quote_currency {
-        msg!("Lending market quote currency does not match the oracle quote currency");
-        return Err(PoolingError::InvalidOracleConfig.into());
-    }
+    assert_pyth_oracle(
+        &pool_manager,
+        accounts.get(init_pool_accounts_index::PYTH_PRODUCT).ok_or(PoolingError::InvalidAccountInput)?,
+        accounts.get(init_pool_accounts_index::PYTH_PRICE).ok_or(PoolingError::InvalidAccountInput)?,
+    )?;
     if accounts.get(init_pool_accounts_index::LIQUIDITY_MINT)
         .ok_or(PoolingError::InvalidAccountInput)?.owner != accounts.get(init_pool_accounts_index::TOKEN_PROGRAM_ID).ok_or(PoolingError::InvalidAccountInput)?.key {
         msg!("Reserve liquidity mint is not owned by the token program provided");
@@ -273,13 +516,171 @@ fn process_init_pool(
             kink_util_rate,
         }),
         config,
+        prize: Prize::new(InitPrizeParams {
+            current_slot: clock.slot,
+            period_slots: prize_period_slots,
+            tiers: prize_tiers,
+        }),
     });
     Pool::pack(reserve, &mut accounts.get(init_pool_accounts_index::RESERVE_ACCOUNT).ok_or(PoolingError::InvalidAccountInput)?.data.borrow_mut())?;
     Ok(())
 }
 
 #[inline(never)] // avoid stack frame limit
-fn process_init_ticket(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+fn process_update_pool_config(
+    program_id: &Pubkey,
+    config: PoolConfig,
+    total_mining_speed: u64,
+    kink_util_rate: u64,
+    liquidity_fee_receiver: Pubkey,
+    pyth_oracle: Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if kink_util_rate > MAX_KINK_UTIL_RATE {
+        msg!("Kink utilization rate must be in range [0, 10000] basis points");
+        return Err(PoolingError::InvalidConfig.into());
+    }
+    config.validate()?;
+    let account_info_iter = &mut accounts.iter();
+
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pool_manager_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.owner != pool_manager_owner_info.key {
+        msg!("Pool manager owner does not match the pool manager owner provided");
+        return Err(PoolingError::InvalidMarketOwner.into());
+    }
+    if !pool_manager_owner_info.is_signer {
+        msg!("Pool manager owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+    let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if pool.reentry_lock {
+        msg!("Can not reentry");
+        return Err(PoolingError::ReentryLocked.into());
+    }
+
+    if pool.liquidity.fee_receiver != liquidity_fee_receiver {
+        let liquidity_fee_receiver_info = next_account_info(account_info_iter)?;
+        if liquidity_fee_receiver_info.key != &liquidity_fee_receiver {
+            msg!("Pool liquidity fee receiver provided does not match the new fee receiver");
+            return Err(PoolingError::InvalidAccountInput.into());
+        }
+        if liquidity_fee_receiver_info.owner != &pool_manager.token_program_id {
+            msg!("Pool liquidity fee receiver is not owned by the token program");
+            return Err(PoolingError::InvalidAccountInput.into());
+        }
+        let liquidity_fee_receiver = Account::unpack(&liquidity_fee_receiver_info.data.borrow())?;
+        if liquidity_fee_receiver.mint != pool.liquidity.mint_pubkey {
+            msg!("Pool liquidity fee receiver is not a token account of pool liquidity");
+            return Err(PoolingError::InvalidAccountInput.into());
+        }
+        msg!("Fee receiver changed from {} to {}", pool.liquidity.fee_receiver, liquidity_fee_receiver_info.key);
+        pool.liquidity.fee_receiver = *liquidity_fee_receiver_info.key;
+    }
+
+    if pool.liquidity.pyth_oracle_pubkey != pyth_oracle {
+        let pyth_product_info = next_account_info(account_info_iter)?;
+        let pyth_price_info = next_account_info(account_info_iter)?;
+        if pyth_price_info.key != &pyth_oracle {
+            msg!("Pyth price account provided does not match the new oracle");
+            return Err(PoolingError::InvalidOracleConfig.into());
+        }
+        assert_pyth_oracle(&pool_manager, pyth_product_info, pyth_price_info)?;
+        msg!("Oracle changed from {} to {}", pool.liquidity.pyth_oracle_pubkey, pyth_price_info.key);
+        pool.liquidity.pyth_oracle_pubkey = *pyth_price_info.key;
+        pool.liquidity.market_price = get_pyth_price(pyth_price_info, clock)?;
+        pool.last_update.mark_stale();
+    }
+
+    if pool.config.paused != config.paused {
+        msg!("Paused operations changed from {:#b} to {:#b}", pool.config.paused, config.paused);
+    }
+    if pool.config.exit_fee_wad != config.exit_fee_wad
+        || pool.config.exit_fee_decay_slots != config.exit_fee_decay_slots {
+        msg!(
+            "Exit fee changed from {} to {}, decaying over {} slots instead of {}",
+            pool.config.exit_fee_wad,
+            config.exit_fee_wad,
+            config.exit_fee_decay_slots,
+            pool.config.exit_fee_decay_slots
+        );
+    }
+    if pool.config.protocol_fee_wad != config.protocol_fee_wad {
+        msg!(
+            "Protocol fee changed from {} to {}",
+            pool.config.protocol_fee_wad,
+            config.protocol_fee_wad
+        );
+    }
+    if pool.config.mine_distribution != config.mine_distribution {
+        msg!(
+            "Mine distribution changed from {:?} to {:?}",
+            pool.config.mine_distribution,
+            config.mine_distribution
+        );
+    }
+
+    let rate_model_changed = pool.config.optimal_utilization_rate != config.optimal_utilization_rate
+        || pool.config.min_borrow_rate != config.min_borrow_rate
+        || pool.config.optimal_borrow_rate != config.optimal_borrow_rate
+        || pool.config.max_borrow_rate != config.max_borrow_rate
+        || pool.config.reserve_owner_fee_wad != config.reserve_owner_fee_wad;
+    if rate_model_changed {
+        msg!(
+            "Borrow rates changed to {}% / {}% / {}% around {}% utilization, reserve owner fee to {}",
+            config.min_borrow_rate,
+            config.optimal_borrow_rate,
+            config.max_borrow_rate,
+            config.optimal_utilization_rate,
+            config.reserve_owner_fee_wad
+        );
+    }
+
+    let mining_changed = pool.lottery.total_mining_speed != total_mining_speed
+        || pool.lottery.kink_util_rate != kink_util_rate
+        || pool.config.mine_distribution != config.mine_distribution;
+    if mining_changed {
+        msg!(
+            "Mining speed changed from {} to {}, kink utilization rate from {} to {}",
+            pool.lottery.total_mining_speed,
+            total_mining_speed,
+            pool.lottery.kink_util_rate,
+            kink_util_rate
+        );
+    }
+
+    // accrue with the previous mining and interest config before switching to the new one
+    if mining_changed || rate_model_changed {
+        pool.update_mining_speed(clock.slot, total_mining_speed, kink_util_rate)?;
+    }
+    pool.config = config;
+
+    Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_init_ticket(
+    program_id: &Pubkey,
+    sponsor: bool,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
 
     let ticket_info = next_account_info(account_info_iter)?;
@@ -317,6 +718,7 @@ fn process_init_ticket(program_id: &Pubkey, accounts: &[AccountInfo]) -> Program
         pool_manager: *pool_manager_info.key,
         owner: *ticket_owner_info.key,
         deposits: vec![],
+        sponsor,
     });
     Ticket::pack(ticket, &mut ticket_info.data.borrow_mut())?;
 
@@ -341,6 +743,13 @@ fn process_refresh_reserve(program_id: &Pubkey, accounts: &[AccountInfo]) -> Pro
     }
     reserve.liquidity.market_price = get_pyth_price(reserve_liquidity_oracle_info, clock)?;
     msg!("reserve.liquidity.market_price={}",reserve.liquidity.market_price.to_string());
+    if reserve.yield_source.is_enabled() {
+        let lending_reserve_info = next_account_info(account_info_iter)?;
+        let share_supply_info = next_account_info(account_info_iter)?;
+        reserve.yield_source.redeemable_amount =
+            get_yield_redeemable_amount(&reserve, lending_reserve_info, share_supply_info)?;
+    }
+    reserve.accrue_interest(clock.slot)?;
     reserve.refresh_index(clock.slot)?;
     reserve.last_update.update_slot(clock.slot);
     Pool::pack(reserve, &mut reserve_info.data.borrow_mut())?;
@@ -473,10 +882,7 @@ fn process_deposit_pool_liquidity(
         msg!("Can not reentry");
         return Err(PoolingError::ReentryLocked.into());
     }
-    if reserve.config.deposit_paused {
-        msg!("Deposits to this reserve is paused");
-        return Err(PoolingError::DepositPaused.into());
-    }
+    assert_not_paused(&pool_manager, &reserve, PAUSE_DEPOSIT)?;
     let authority_signer_seeds = &[
         pool_manager_info.key.as_ref(),
         &[pool_manager.bump_seed],
@@ -598,6 +1004,7 @@ fn process_redeem_pool_collateral(
         msg!("Can not reentry");
         return Err(PoolingError::ReentryLocked.into());
     }
+    assert_not_paused(&lending_market, &reserve, PAUSE_REDEEM)?;
     let authority_signer_seeds = &[
         lending_market_info.key.as_ref(),
         &[lending_market.bump_seed],
@@ -650,13 +1057,1828 @@ fn process_redeem_pool_collateral(
     Ok(())
 }
 
+#[inline(never)] // avoid stack frame limit
+fn process_deposit_ticket_liquidity(
+    program_id: &Pubkey,
+    amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if amount == 0 {
+        msg!("Liquidity amount provided cannot be zero");
+        return Err(PoolingError::InvalidAmount.into());
+    }
+    let account_info_iter = &mut accounts.iter();
+
+    let source_liquidity_info = next_account_info(account_info_iter)?;
+    let ticket_info = next_account_info(account_info_iter)?;
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_collateral_mint_info = next_account_info(account_info_iter)?;
+    let pool_collateral_supply_info = next_account_info(account_info_iter)?;
+    let pool_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pool_manager_authority_info = next_account_info(account_info_iter)?;
+    let ticket_owner_info = next_account_info(account_info_iter)?;
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.token_program_id != token_program_id.key {
+        msg!("Pool manager token program does not match the token program provided");
+        return Err(PoolingError::InvalidTokenProgram.into());
+    }
+    let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.liquidity.supply_pubkey != pool_liquidity_supply_info.key {
+        msg!("Pool liquidity supply does not match the pool liquidity supply provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.liquidity.supply_pubkey == source_liquidity_info.key {
+        msg!("Pool liquidity supply cannot be used as the source liquidity provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.collateral.mint_pubkey != pool_collateral_mint_info.key {
+        msg!("Pool collateral mint does not match the pool collateral mint provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.collateral.supply_pubkey != pool_collateral_supply_info.key {
+        msg!("Pool collateral supply does not match the pool collateral supply provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if pool.last_update.is_stale(clock.slot)? {
+        msg!("Pool is stale and must be refreshed in the current slot");
+        return Err(PoolingError::ReserveStale.into());
+    }
+    if pool.reentry_lock {
+        msg!("Can not reentry");
+        return Err(PoolingError::ReentryLocked.into());
+    }
+    assert_not_paused(&pool_manager, &pool, PAUSE_DEPOSIT)?;
+
+    let mut ticket = Ticket::unpack(&ticket_info.data.borrow())?;
+    if ticket_info.owner != program_id {
+        msg!("Ticket provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &ticket.pool_manager != pool_manager_info.key {
+        msg!("Ticket pool manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &ticket.owner != ticket_owner_info.key {
+        msg!("Ticket owner does not match the ticket owner provided");
+        return Err(PoolingError::InvalidObligationOwner.into());
+    }
+    if !ticket_owner_info.is_signer {
+        msg!("Ticket owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+
+    let authority_signer_seeds = &[
+        pool_manager_info.key.as_ref(),
+        &[pool_manager.bump_seed],
+    ];
+    let pool_manager_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &pool_manager_authority_pubkey != pool_manager_authority_info.key {
+        msg!(
+            "Derived pool manager authority does not match the pool manager authority provided"
+        );
+        return Err(PoolingError::InvalidMarketAuthority.into());
+    }
+
+    let liquidity_account = Account::unpack(&source_liquidity_info.data.borrow())?;
+    let liquidity_amount = if amount == u64::MAX {
+        liquidity_account.amount
+    } else {
+        if amount > liquidity_account.amount {
+            msg!("Deposit amount too large for account balance");
+            return Err(PoolingError::DepositAmountTooLarge.into());
+        };
+        amount
+    };
+    let collateral_amount = pool.deposit_liquidity(liquidity_amount)?;
+
+    let (_, collateral_index) = ticket
+        .find_or_add_collateral_to_deposits(*pool_info.key, pool.lottery.l_token_mining_index)?;
+    ticket.refresh_deposit_unclaimed(collateral_index, &pool)?;
+    ticket.deposit(collateral_index, collateral_amount, clock.slot, &pool.prize)?;
+    if ticket.sponsor {
+        pool.deposit_sponsorship(collateral_amount)?;
+    } else {
+        pool.deposit_ticket_balance(collateral_amount, clock.slot)?;
+    }
+    ticket.last_update.mark_stale();
+    Ticket::pack(ticket, &mut ticket_info.data.borrow_mut())?;
+
+    pool.last_update.mark_stale();
+    Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_liquidity_info.clone(),
+        destination: pool_liquidity_supply_info.clone(),
+        amount: liquidity_amount,
+        authority: user_transfer_authority_info.clone(),
+        authority_signer_seeds: &[],
+        token_program: token_program_id.clone(),
+    })?;
+
+    spl_token_mint_to(TokenMintToParams {
+        mint: pool_collateral_mint_info.clone(),
+        destination: pool_collateral_supply_info.clone(),
+        amount: collateral_amount,
+        authority: pool_manager_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_withdraw_ticket_deposit(
+    program_id: &Pubkey,
+    amount: u64,
+    max_exit_fee: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if amount == 0 {
+        msg!("Collateral amount provided cannot be zero");
+        return Err(PoolingError::InvalidAmount.into());
+    }
+    let account_info_iter = &mut accounts.iter();
+
+    let ticket_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_collateral_mint_info = next_account_info(account_info_iter)?;
+    let pool_collateral_supply_info = next_account_info(account_info_iter)?;
+    let pool_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pool_manager_authority_info = next_account_info(account_info_iter)?;
+    let ticket_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.token_program_id != token_program_id.key {
+        msg!("Pool manager token program does not match the token program provided");
+        return Err(PoolingError::InvalidTokenProgram.into());
+    }
+    let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.liquidity.supply_pubkey != pool_liquidity_supply_info.key {
+        msg!("Pool liquidity supply does not match the pool liquidity supply provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.liquidity.supply_pubkey == destination_liquidity_info.key {
+        msg!("Pool liquidity supply cannot be used as the destination liquidity provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.collateral.mint_pubkey != pool_collateral_mint_info.key {
+        msg!("Pool collateral mint does not match the pool collateral mint provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.collateral.supply_pubkey != pool_collateral_supply_info.key {
+        msg!("Pool collateral supply does not match the pool collateral supply provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if pool.last_update.is_stale(clock.slot)? {
+        msg!("Pool is stale and must be refreshed in the current slot");
+        return Err(PoolingError::ReserveStale.into());
+    }
+    if pool.reentry_lock {
+        msg!("Can not reentry");
+        return Err(PoolingError::ReentryLocked.into());
+    }
+    assert_not_paused(&pool_manager, &pool, PAUSE_REDEEM)?;
+
+    let mut ticket = Ticket::unpack(&ticket_info.data.borrow())?;
+    if ticket_info.owner != program_id {
+        msg!("Ticket provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &ticket.pool_manager != pool_manager_info.key {
+        msg!("Ticket pool manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &ticket.owner != ticket_owner_info.key {
+        msg!("Ticket owner does not match the ticket owner provided");
+        return Err(PoolingError::InvalidObligationOwner.into());
+    }
+    if !ticket_owner_info.is_signer {
+        msg!("Ticket owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+
+    let authority_signer_seeds = &[
+        pool_manager_info.key.as_ref(),
+        &[pool_manager.bump_seed],
+    ];
+    let pool_manager_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &pool_manager_authority_pubkey != pool_manager_authority_info.key {
+        msg!(
+            "Derived pool manager authority does not match the pool manager authority provided"
+        );
+        return Err(PoolingError::InvalidMarketAuthority.into());
+    }
+
+    let (collateral, collateral_index) = ticket.find_collateral_in_deposits(*pool_info.key)?;
+    if collateral.deposited_amount == 0 {
+        msg!("Ticket has no deposit in the pool");
+        return Err(PoolingError::ObligationCollateralEmpty.into());
+    }
+    let collateral_amount = if amount == u64::MAX {
+        collateral.deposited_amount
+    } else {
+        if amount > collateral.deposited_amount {
+            msg!("Withdraw amount too large for ticket deposit");
+            return Err(PoolingError::WithdrawAmountTooLarge.into());
+        };
+        amount
+    };
+    let deposit_slot = collateral.deposit_slot;
+    ticket.refresh_deposit_unclaimed(collateral_index, &pool)?;
+    ticket.withdraw(collateral_amount, collateral_index, clock.slot, &pool.prize)?;
+    if ticket.sponsor {
+        pool.withdraw_sponsorship(collateral_amount)?;
+    } else {
+        pool.withdraw_ticket_balance(collateral_amount, clock.slot)?;
+    }
+    ticket.last_update.mark_stale();
+    Ticket::pack(ticket, &mut ticket_info.data.borrow_mut())?;
+
+    let liquidity_amount = pool.redeem_collateral(collateral_amount)?;
+    let exit_fee = pool.calculate_exit_fee(liquidity_amount, deposit_slot, clock.slot)?;
+    if exit_fee > max_exit_fee {
+        msg!("Exit fee {} exceeds the maximum exit fee {}", exit_fee, max_exit_fee);
+        return Err(PoolingError::ExitFeeTooHigh.into());
+    }
+    if exit_fee > 0 {
+        msg!("Exit fee {} added to the prize", exit_fee);
+        pool.collect_exit_fee(exit_fee)?;
+    }
+    pool.last_update.mark_stale();
+    Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+
+    spl_token_burn(TokenBurnParams {
+        mint: pool_collateral_mint_info.clone(),
+        source: pool_collateral_supply_info.clone(),
+        amount: collateral_amount,
+        authority: pool_manager_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: pool_liquidity_supply_info.clone(),
+        destination: destination_liquidity_info.clone(),
+        amount: liquidity_amount
+            .checked_sub(exit_fee)
+            .ok_or(PoolingError::MathOverflow)?,
+        authority: pool_manager_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
 fn process_lottery_draw(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
 ) -> ProgramResult {
+    process_draw(program_id, accounts, |pool_pubkey, pool, pool_manager, _clock, randomness_info| {
+        if pool.prize.draw_pending {
+            msg!("Draw started at slot {} is still pending", pool.prize.pending_commit_slot);
+            return Err(PoolingError::DrawAlreadyPending.into());
+        }
+        if &pool.prize.randomness_account != randomness_info.key {
+            msg!("Randomness result account provided is not the one requested for the draw");
+            return Err(PoolingError::InvalidRandomnessResult.into());
+        }
+        let result = get_randomness_result(randomness_info, pool_pubkey, pool, pool_manager)?;
+        if result.status != randomness::ResultStatus::Fulfilled as u32 {
+            msg!("Randomness result is not fulfilled yet");
+            return Err(PoolingError::InvalidRandomnessResult.into());
+        }
+        Ok(hashv(&[&result.result, pool_pubkey.as_ref()]).to_bytes())
+    })
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_request_randomness(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pool_manager_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let randomness_info = next_account_info(account_info_iter)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.owner != pool_manager_owner_info.key {
+        msg!("Pool manager owner does not match the pool manager owner provided");
+        return Err(PoolingError::InvalidMarketOwner.into());
+    }
+    if !pool_manager_owner_info.is_signer {
+        msg!("Pool manager owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+    let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    assert_not_paused(&pool_manager, &pool, PAUSE_DRAW)?;
+    // the draw index must be settled before anyone can know the randomness
+    pool.prize.check_indexed(pool.period_ticket_balance()?)?;
+    let result = get_randomness_result(randomness_info, pool_info.key, &pool, &pool_manager)?;
+    // a result known before the request is bound could be picked for its outcome
+    if result.status != randomness::ResultStatus::Pending as u32 {
+        msg!("Randomness result must still be pending when requested for a draw");
+        return Err(PoolingError::InvalidRandomnessResult.into());
+    }
+    pool.prize.request_randomness(*randomness_info.key, clock.slot)?;
+    Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_start_draw(
+    program_id: &Pubkey,
+    commitment: [u8; 32],
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pool_manager_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.owner != pool_manager_owner_info.key {
+        msg!("Pool manager owner does not match the pool manager owner provided");
+        return Err(PoolingError::InvalidMarketOwner.into());
+    }
+    if !pool_manager_owner_info.is_signer {
+        msg!("Pool manager owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+    let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    assert_not_paused(&pool_manager, &pool, PAUSE_DRAW)?;
+    // the draw index must be settled before the committed secret can be used
+    pool.prize.check_indexed(pool.period_ticket_balance()?)?;
+    pool.prize.start_draw(commitment, clock.slot)?;
+    Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_complete_draw(
+    program_id: &Pubkey,
+    secret: [u8; 32],
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pool_manager_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.owner != pool_manager_owner_info.key {
+        msg!("Pool manager owner does not match the pool manager owner provided");
+        return Err(PoolingError::InvalidMarketOwner.into());
+    }
+    if !pool_manager_owner_info.is_signer {
+        msg!("Pool manager owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+    let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    assert_not_paused(&pool_manager, &pool, PAUSE_DRAW)?;
+    pool.prize.reveal_draw(&secret, clock.slot)?;
+    Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_settle_draw(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    process_draw(program_id, accounts, |pool_pubkey, pool, _pool_manager, clock, slot_hashes_info| {
+        if !slot_hashes::check_id(slot_hashes_info.key) {
+            msg!("Slot hashes sysvar provided is invalid");
+            return Err(PoolingError::InvalidAccountInput.into());
+        }
+        let (secret, reveal_slot) = pool.prize.settle_draw(clock.slot)?;
+        let slot_hash = get_slot_hash(slot_hashes_info, reveal_slot)?;
+        Ok(hashv(&[&secret, &slot_hash, pool_pubkey.as_ref()]).to_bytes())
+    })
+}
+
+/// Seed the draw of the tickets indexed by IndexDrawTickets, then award the winners held by the
+/// tickets passed after the randomness account. `draw_seed` validates the randomness account
+/// against the pool and returns the draw seed.
+fn process_draw<F>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    draw_seed: F,
+) -> ProgramResult
+    where
+        F: FnOnce(&Pubkey, &mut Pool, &PoolManager, &Clock, &AccountInfo) -> Result<[u8; 32], ProgramError>,
+{
+    let account_info_iter = &mut accounts.iter();
+
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let randomness_info = next_account_info(account_info_iter)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if pool.last_update.is_stale(clock.slot)? {
+        msg!("Pool is stale and must be refreshed in the current slot");
+        return Err(PoolingError::ReserveStale.into());
+    }
+    if pool.reentry_lock {
+        msg!("Can not reentry");
+        return Err(PoolingError::ReentryLocked.into());
+    }
+    assert_not_paused(&pool_manager, &pool, PAUSE_DRAW)?;
+    if !pool.prize.is_period_over(clock.slot)? {
+        msg!("Prize period is not over until slot {}", pool.prize.period_end_slot()?);
+        return Err(PoolingError::PrizePeriodNotOver.into());
+    }
+    if pool.prize.awarding {
+        msg!("Draw is already awarding its winners");
+        return Err(PoolingError::DrawAwardingWinners.into());
+    }
+    // the draw is permissionless, leaving any ticket out of the index would skew the odds
+    let period_ticket_balance = pool.period_ticket_balance()?;
+    pool.prize.check_indexed(period_ticket_balance)?;
+
+    let seed = draw_seed(pool_info.key, &mut pool, &pool_manager, clock, randomness_info)?;
+    if period_ticket_balance == 0 {
+        msg!("No ticket balance during the prize period, the prize is carried over");
+        pool.roll_over_prize(clock.slot)?;
+        pool.last_update.mark_stale();
+        Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+        return Ok(());
+    }
+    let prize_amount = pool.skim_protocol_fee()?;
+    pool.prize.start_awarding(seed, prize_amount)?;
+    award_draw_winners(
+        program_id,
+        pool_info.key,
+        pool_manager_info.key,
+        &mut pool,
+        account_info_iter.as_slice(),
+        clock.slot,
+    )?;
+
+    pool.last_update.mark_stale();
+    Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_index_draw_tickets(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if pool.reentry_lock {
+        msg!("Can not reentry");
+        return Err(PoolingError::ReentryLocked.into());
+    }
+    assert_not_paused(&pool_manager, &pool, PAUSE_DRAW)?;
+    // balances of the period only stop changing once it is over
+    if !pool.prize.is_period_over(clock.slot)? {
+        msg!("Prize period is not over until slot {}", pool.prize.period_end_slot()?);
+        return Err(PoolingError::PrizePeriodNotOver.into());
+    }
+
+    let period_ticket_balance = pool.period_ticket_balance()?;
+    for ticket_info in account_info_iter {
+        if ticket_info.owner != program_id {
+            msg!("Ticket provided is not owned by the pooling program");
+            return Err(PoolingError::InvalidAccountOwner.into());
+        }
+        let mut ticket = Ticket::unpack(&ticket_info.data.borrow())?;
+        if &ticket.pool_manager != pool_manager_info.key {
+            msg!("Ticket pool manager does not match the pool manager provided");
+            return Err(PoolingError::InvalidAccountInput.into());
+        }
+        let collateral_index = match ticket.find_collateral_in_deposits(*pool_info.key) {
+            Ok((_, collateral_index)) => collateral_index,
+            Err(_) => continue,
+        };
+        if ticket.index_for_draw(collateral_index, &mut pool.prize, period_ticket_balance)? {
+            Ticket::pack(ticket, &mut ticket_info.data.borrow_mut())?;
+        }
+    }
+    msg!(
+        "Tickets indexed hold {} of the {} cumulative ticket balance of the period",
+        pool.prize.indexed_balance,
+        period_ticket_balance
+    );
+
+    Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_award_draw_winners(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if pool.reentry_lock {
+        msg!("Can not reentry");
+        return Err(PoolingError::ReentryLocked.into());
+    }
+    assert_not_paused(&pool_manager, &pool, PAUSE_DRAW)?;
+    if !pool.prize.awarding {
+        msg!("No draw is awarding its winners");
+        return Err(PoolingError::DrawNotAwarding.into());
+    }
+
+    let picks = award_draw_winners(
+        program_id,
+        pool_info.key,
+        pool_manager_info.key,
+        &mut pool,
+        account_info_iter.as_slice(),
+        clock.slot,
+    )?;
+    if picks == 0 {
+        msg!("Ticket holding pick {} of the draw is not provided", pool.prize.draw_picks);
+        return Err(PoolingError::DrawWinnerNotProvided.into());
+    }
+
+    pool.last_update.mark_stale();
+    Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+    Ok(())
+}
+
+/// Award the picks of the draw to the tickets of `ticket_infos` holding them in the draw index,
+/// until the ticket holding the next pick is not among them. A pick landing on a ticket which
+/// already won is picked again. Records the draw once every winner is awarded, and returns the
+/// number of picks made.
+fn award_draw_winners(
+    program_id: &Pubkey,
+    pool_pubkey: &Pubkey,
+    pool_manager_pubkey: &Pubkey,
+    pool: &mut Pool,
+    ticket_infos: &[AccountInfo],
+    slot: Slot,
+) -> Result<u64, ProgramError> {
+    let draw_number = pool.prize.draw_number()?;
+    // ranges held by the tickets, with whether they already won
+    let mut ranges = Vec::with_capacity(ticket_infos.len());
+    for (pos, ticket_info) in ticket_infos.iter().enumerate() {
+        if ticket_info.owner != program_id {
+            msg!("Ticket provided is not owned by the pooling program");
+            return Err(PoolingError::InvalidAccountOwner.into());
+        }
+        if ticket_infos[..pos].iter().any(|info| info.key == ticket_info.key) {
+            msg!("Ticket {} is provided more than once", ticket_info.key);
+            return Err(PoolingError::DuplicateTicket.into());
+        }
+        let ticket = Ticket::unpack(&ticket_info.data.borrow())?;
+        if &ticket.pool_manager != pool_manager_pubkey {
+            msg!("Ticket pool manager does not match the pool manager provided");
+            return Err(PoolingError::InvalidAccountInput.into());
+        }
+        let range = match ticket.find_collateral_in_deposits(*pool_pubkey) {
+            Ok((collateral, _)) => collateral
+                .draw_range(draw_number, &pool.prize)?
+                .map(|range| (range, collateral.won_draw == draw_number)),
+            Err(_) => None,
+        };
+        ranges.push(range);
+    }
+
+    let period_ticket_balance = pool.period_ticket_balance()?;
+    let mut picks: u64 = 0;
+    while !pool.prize.is_awarding_complete() {
+        let target = pool.prize.pick_target(period_ticket_balance)?;
+        let position = match ranges.iter().position(|range| match range {
+            Some(((start, end), _)) => *start <= target && target < *end,
+            None => false,
+        }) {
+            Some(position) => position,
+            None => break,
+        };
+        picks += 1;
+        if let Some((_, won)) = &mut ranges[position] {
+            if *won {
+                pool.prize.skip_pick()?;
+                continue;
+            }
+            *won = true;
+        }
+
+        let winner_info = &ticket_infos[position];
+        let amount = pool.prize.next_award_amount()?;
+        msg!("Lottery winner {} with prize {}", winner_info.key, amount);
+        pool.award_prize(amount)?;
+
+        let mut winner = Ticket::unpack(&winner_info.data.borrow())?;
+        let (_, collateral_index) = winner.find_collateral_in_deposits(*pool_pubkey)?;
+        winner.award_prize(collateral_index, amount, draw_number)?;
+        Ticket::pack(winner, &mut winner_info.data.borrow_mut())?;
+
+        pool.prize.record_award(*winner_info.key, amount)?;
+    }
+    if pool.prize.is_awarding_complete() {
+        pool.prize.complete_awarding(slot)?;
+    }
+    Ok(picks)
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_claim_prize(
+    program_id: &Pubkey,
+    compound: bool,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let ticket_info = next_account_info(account_info_iter)?;
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pool_manager_authority_info = next_account_info(account_info_iter)?;
+    let ticket_owner_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
+    let pool_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let pool_collateral_mint_info = next_account_info(account_info_iter)?;
+    let pool_collateral_supply_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.token_program_id != token_program_id.key {
+        msg!("Pool manager token program does not match the token program provided");
+        return Err(PoolingError::InvalidTokenProgram.into());
+    }
+    let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.liquidity.supply_pubkey != pool_liquidity_supply_info.key {
+        msg!("Pool liquidity supply does not match the pool liquidity supply provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.liquidity.supply_pubkey == destination_liquidity_info.key {
+        msg!("Pool liquidity supply cannot be used as the destination liquidity provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.collateral.mint_pubkey != pool_collateral_mint_info.key {
+        msg!("Pool collateral mint does not match the pool collateral mint provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.collateral.supply_pubkey != pool_collateral_supply_info.key {
+        msg!("Pool collateral supply does not match the pool collateral supply provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if pool.last_update.is_stale(clock.slot)? {
+        msg!("Pool is stale and must be refreshed in the current slot");
+        return Err(PoolingError::ReserveStale.into());
+    }
+    if pool.reentry_lock {
+        msg!("Can not reentry");
+        return Err(PoolingError::ReentryLocked.into());
+    }
+    assert_not_paused(&pool_manager, &pool, PAUSE_CLAIM)?;
+
+    let mut ticket = Ticket::unpack(&ticket_info.data.borrow())?;
+    if ticket_info.owner != program_id {
+        msg!("Ticket provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &ticket.pool_manager != pool_manager_info.key {
+        msg!("Ticket pool manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &ticket.owner != ticket_owner_info.key {
+        msg!("Ticket owner does not match the ticket owner provided");
+        return Err(PoolingError::InvalidObligationOwner.into());
+    }
+    if !ticket_owner_info.is_signer {
+        msg!("Ticket owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+
+    let authority_signer_seeds = &[
+        pool_manager_info.key.as_ref(),
+        &[pool_manager.bump_seed],
+    ];
+    let pool_manager_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &pool_manager_authority_pubkey != pool_manager_authority_info.key {
+        msg!(
+            "Derived pool manager authority does not match the pool manager authority provided"
+        );
+        return Err(PoolingError::InvalidMarketAuthority.into());
+    }
+
+    let (_, collateral_index) = ticket.find_collateral_in_deposits(*pool_info.key)?;
+    let prize_amount = ticket.claim_prize(collateral_index, &pool.prize)?;
+    msg!("Claimed prize {}", prize_amount);
+
+    if compound {
+        let collateral_amount = pool.compound_prize(prize_amount)?;
+        let (_, collateral_index) = ticket
+            .find_or_add_collateral_to_deposits(*pool_info.key, pool.lottery.l_token_mining_index)?;
+        ticket.refresh_deposit_unclaimed(collateral_index, &pool)?;
+        ticket.deposit_compounded_prize(collateral_index, collateral_amount, clock.slot, &pool.prize)?;
+        ticket.last_update.mark_stale();
+        Ticket::pack(ticket, &mut ticket_info.data.borrow_mut())?;
+        pool.deposit_ticket_balance(collateral_amount, clock.slot)?;
+        pool.last_update.mark_stale();
+        Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+
+        spl_token_mint_to(TokenMintToParams {
+            mint: pool_collateral_mint_info.clone(),
+            destination: pool_collateral_supply_info.clone(),
+            amount: collateral_amount,
+            authority: pool_manager_authority_info.clone(),
+            authority_signer_seeds,
+            token_program: token_program_id.clone(),
+        })?;
+    } else {
+        pool.claim_prize(prize_amount)?;
+        Ticket::pack(ticket, &mut ticket_info.data.borrow_mut())?;
+        pool.last_update.mark_stale();
+        Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+
+        spl_token_transfer(TokenTransferParams {
+            source: pool_liquidity_supply_info.clone(),
+            destination: destination_liquidity_info.clone(),
+            amount: prize_amount,
+            authority: pool_manager_authority_info.clone(),
+            authority_signer_seeds,
+            token_program: token_program_id.clone(),
+        })?;
+    }
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_claim_mine(
+    program_id: &Pubkey,
+    from_mining: bool,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let claimer_info = next_account_info(account_info_iter)?;
+    let mine_supply_info = next_account_info(account_info_iter)?;
+    let destination_mine_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pool_manager_authority_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+    let pool_infos = account_info_iter.as_slice();
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.token_program_id != token_program_id.key {
+        msg!("Pool manager token program does not match the token program provided");
+        return Err(PoolingError::InvalidTokenProgram.into());
+    }
+    if &pool_manager.mine_supply_account != mine_supply_info.key {
+        msg!("Pool manager mine supply does not match the mine supply provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool_manager.mine_supply_account == destination_mine_info.key {
+        msg!("Pool manager mine supply cannot be used as the destination provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if claimer_info.owner != program_id {
+        msg!("Ticket or mining account provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if !owner_info.is_signer {
+        msg!("Ticket or mining owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+    let authority_signer_seeds = &[
+        pool_manager_info.key.as_ref(),
+        &[pool_manager.bump_seed],
+    ];
+    let pool_manager_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &pool_manager_authority_pubkey != pool_manager_authority_info.key {
+        msg!(
+            "Derived pool manager authority does not match the pool manager authority provided"
+        );
+        return Err(PoolingError::InvalidMarketAuthority.into());
+    }
+
+    let claim_amount = if from_mining {
+        let mut mining = Mining::unpack(&claimer_info.data.borrow())?;
+        if &mining.pool_manager != pool_manager_info.key {
+            msg!("Mining pool manager does not match the pool manager provided");
+            return Err(PoolingError::InvalidAccountInput.into());
+        }
+        if &mining.owner != owner_info.key {
+            msg!("Mining owner does not match the mining owner provided");
+            return Err(PoolingError::InvalidMininigOwner.into());
+        }
+        if pool_infos.len() != mining.mining_indices.len() {
+            msg!("Expected {} pools for the mining indices", mining.mining_indices.len());
+            return Err(PoolingError::InvalidAccountInput.into());
+        }
+        for (pos, pool_info) in pool_infos.iter().enumerate() {
+            let pool = unpack_mining_pool(
+                program_id,
+                &pool_manager,
+                &mining.mining_indices[pos].reserve,
+                pool_info,
+                clock,
+            )?;
+            mining.refresh_unclaimed(pos, &pool)?;
+        }
+        let claim_amount = mining.claim_mine()?;
+        Mining::pack(mining, &mut claimer_info.data.borrow_mut())?;
+        claim_amount
+    } else {
+        let mut ticket = Ticket::unpack(&claimer_info.data.borrow())?;
+        if &ticket.pool_manager != pool_manager_info.key {
+            msg!("Ticket pool manager does not match the pool manager provided");
+            return Err(PoolingError::InvalidAccountInput.into());
+        }
+        if &ticket.owner != owner_info.key {
+            msg!("Ticket owner does not match the ticket owner provided");
+            return Err(PoolingError::InvalidObligationOwner.into());
+        }
+        if pool_infos.len() != ticket.deposits.len() {
+            msg!("Expected {} pools for the ticket deposits", ticket.deposits.len());
+            return Err(PoolingError::InvalidAccountInput.into());
+        }
+        for (pos, pool_info) in pool_infos.iter().enumerate() {
+            let pool = unpack_mining_pool(
+                program_id,
+                &pool_manager,
+                &ticket.deposits[pos].deposit_reserve,
+                pool_info,
+                clock,
+            )?;
+            ticket.refresh_deposit_unclaimed(pos, &pool)?;
+        }
+        let claim_amount = ticket.claim_mine()?;
+        Ticket::pack(ticket, &mut claimer_info.data.borrow_mut())?;
+        claim_amount
+    };
+    if claim_amount == 0 {
+        msg!("Less than one whole mine token is unclaimed");
+        return Err(PoolingError::NoMineToClaim.into());
+    }
+
+    let mine_supply = Account::unpack(&mine_supply_info.data.borrow())?;
+    if mine_supply.amount < claim_amount {
+        msg!(
+            "Mine supply holds {} but {} is claimed",
+            mine_supply.amount,
+            claim_amount
+        );
+        return Err(PoolingError::InsufficientMineSupply.into());
+    }
+    msg!("Claimed mine {}", claim_amount);
+
+    spl_token_transfer(TokenTransferParams {
+        source: mine_supply_info.clone(),
+        destination: destination_mine_info.clone(),
+        amount: claim_amount,
+        authority: pool_manager_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_init_mining(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let mining_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let mining_owner_info = next_account_info(account_info_iter)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+
+    assert_rent_exempt(rent, mining_info)?;
+    let mut mining = assert_uninitialized::<Mining>(mining_info)?;
+    if mining_info.owner != program_id {
+        msg!("Mining provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if !mining_owner_info.is_signer {
+        msg!("Mining owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+
+    mining.init(InitMiningParams {
+        lending_market: *pool_manager_info.key,
+        owner: *mining_owner_info.key,
+        mining_indices: vec![],
+    });
+    Mining::pack(mining, &mut mining_info.data.borrow_mut())?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_deposit_mining(
+    program_id: &Pubkey,
+    amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if amount == 0 {
+        msg!("Collateral amount provided cannot be zero");
+        return Err(PoolingError::InvalidAmount.into());
+    }
+    let account_info_iter = &mut accounts.iter();
+
+    let source_collateral_info = next_account_info(account_info_iter)?;
+    let mining_info = next_account_info(account_info_iter)?;
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_un_coll_supply_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let mining_owner_info = next_account_info(account_info_iter)?;
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.token_program_id != token_program_id.key {
+        msg!("Pool manager token program does not match the token program provided");
+        return Err(PoolingError::InvalidTokenProgram.into());
+    }
+    let pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.lottery.un_coll_supply_account != pool_un_coll_supply_info.key {
+        msg!("Pool un-collateralized supply does not match the pool un-collateralized supply provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.lottery.un_coll_supply_account == source_collateral_info.key {
+        msg!("Pool un-collateralized supply cannot be used as the source collateral provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if pool.last_update.is_stale(clock.slot)? {
+        msg!("Pool is stale and must be refreshed in the current slot");
+        return Err(PoolingError::ReserveStale.into());
+    }
+    if pool.reentry_lock {
+        msg!("Can not reentry");
+        return Err(PoolingError::ReentryLocked.into());
+    }
+    assert_not_paused(&pool_manager, &pool, PAUSE_DEPOSIT)?;
+
+    let mut mining = Mining::unpack(&mining_info.data.borrow())?;
+    if mining_info.owner != program_id {
+        msg!("Mining provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &mining.pool_manager != pool_manager_info.key {
+        msg!("Mining pool manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &mining.owner != mining_owner_info.key {
+        msg!("Mining owner does not match the mining owner provided");
+        return Err(PoolingError::InvalidMininigOwner.into());
+    }
+    if !mining_owner_info.is_signer {
+        msg!("Mining owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+
+    let (_, mining_index) =
+        mining.find_or_add_reserve_in_vec(*pool_info.key, pool.lottery.l_token_mining_index)?;
+    mining.refresh_unclaimed(mining_index, &pool)?;
+    mining.deposit(mining_index, amount)?;
+    Mining::pack(mining, &mut mining_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_collateral_info.clone(),
+        destination: pool_un_coll_supply_info.clone(),
+        amount,
+        authority: user_transfer_authority_info.clone(),
+        authority_signer_seeds: &[],
+        token_program: token_program_id.clone(),
+    })?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_withdraw_mining(
+    program_id: &Pubkey,
+    amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if amount == 0 {
+        msg!("Collateral amount provided cannot be zero");
+        return Err(PoolingError::InvalidAmount.into());
+    }
+    let account_info_iter = &mut accounts.iter();
+
+    let destination_collateral_info = next_account_info(account_info_iter)?;
+    let mining_info = next_account_info(account_info_iter)?;
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_un_coll_supply_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pool_manager_authority_info = next_account_info(account_info_iter)?;
+    let mining_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.token_program_id != token_program_id.key {
+        msg!("Pool manager token program does not match the token program provided");
+        return Err(PoolingError::InvalidTokenProgram.into());
+    }
+    let pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.lottery.un_coll_supply_account != pool_un_coll_supply_info.key {
+        msg!("Pool un-collateralized supply does not match the pool un-collateralized supply provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.lottery.un_coll_supply_account == destination_collateral_info.key {
+        msg!("Pool un-collateralized supply cannot be used as the destination collateral provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if pool.last_update.is_stale(clock.slot)? {
+        msg!("Pool is stale and must be refreshed in the current slot");
+        return Err(PoolingError::ReserveStale.into());
+    }
+    if pool.reentry_lock {
+        msg!("Can not reentry");
+        return Err(PoolingError::ReentryLocked.into());
+    }
+    assert_not_paused(&pool_manager, &pool, PAUSE_REDEEM)?;
+
+    let mut mining = Mining::unpack(&mining_info.data.borrow())?;
+    if mining_info.owner != program_id {
+        msg!("Mining provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &mining.pool_manager != pool_manager_info.key {
+        msg!("Mining pool manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &mining.owner != mining_owner_info.key {
+        msg!("Mining owner does not match the mining owner provided");
+        return Err(PoolingError::InvalidMininigOwner.into());
+    }
+    if !mining_owner_info.is_signer {
+        msg!("Mining owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+    let authority_signer_seeds = &[
+        pool_manager_info.key.as_ref(),
+        &[pool_manager.bump_seed],
+    ];
+    let pool_manager_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &pool_manager_authority_pubkey != pool_manager_authority_info.key {
+        msg!(
+            "Derived pool manager authority does not match the pool manager authority provided"
+        );
+        return Err(PoolingError::InvalidMarketAuthority.into());
+    }
+
+    let mining_index = mining.find_mining_index(pool_info.key)?;
+    mining.refresh_unclaimed(mining_index, &pool)?;
+    let deposited_amount = mining.mining_indices[mining_index].un_coll_l_token_amount;
+    let withdraw_amount = if amount == u64::MAX {
+        deposited_amount
+    } else {
+        amount
+    };
+    if withdraw_amount > deposited_amount {
+        msg!("Withdraw amount is larger than the {} deposited", deposited_amount);
+        return Err(PoolingError::WithdrawAmountTooLarge.into());
+    }
+    mining.withdraw(mining_index, withdraw_amount)?;
+    Mining::pack(mining, &mut mining_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: pool_un_coll_supply_info.clone(),
+        destination: destination_collateral_info.clone(),
+        amount: withdraw_amount,
+        authority: pool_manager_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_withdraw_protocol_fees(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let pool_liquidity_fee_receiver_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pool_manager_authority_info = next_account_info(account_info_iter)?;
+    let pool_manager_owner_info = next_account_info(account_info_iter)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.token_program_id != token_program_id.key {
+        msg!("Pool manager token program does not match the token program provided");
+        return Err(PoolingError::InvalidTokenProgram.into());
+    }
+    if &pool_manager.owner != pool_manager_owner_info.key {
+        msg!("Pool manager owner does not match the pool manager owner provided");
+        return Err(PoolingError::InvalidMarketOwner.into());
+    }
+    if !pool_manager_owner_info.is_signer {
+        msg!("Pool manager owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+    let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.liquidity.supply_pubkey != pool_liquidity_supply_info.key {
+        msg!("Pool liquidity supply does not match the pool liquidity supply provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.liquidity.fee_receiver != pool_liquidity_fee_receiver_info.key {
+        msg!("Pool liquidity fee receiver does not match the pool liquidity fee receiver provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if pool.reentry_lock {
+        msg!("Can not reentry");
+        return Err(PoolingError::ReentryLocked.into());
+    }
+    let authority_signer_seeds = &[
+        pool_manager_info.key.as_ref(),
+        &[pool_manager.bump_seed],
+    ];
+    let pool_manager_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &pool_manager_authority_pubkey != pool_manager_authority_info.key {
+        msg!(
+            "Derived pool manager authority does not match the pool manager authority provided"
+        );
+        return Err(PoolingError::InvalidMarketAuthority.into());
+    }
+
+    let fee_amount = pool.liquidity.withdraw_owner_unclaimed()?;
+    if fee_amount == 0 {
+        msg!("No whole protocol fee token to withdraw");
+        return Err(PoolingError::NoProtocolFees.into());
+    }
+    msg!("Protocol fees {} withdrawn", fee_amount);
+    Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: pool_liquidity_supply_info.clone(),
+        destination: pool_liquidity_fee_receiver_info.clone(),
+        amount: fee_amount,
+        authority: pool_manager_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_contribute_to_prize(
+    program_id: &Pubkey,
+    liquidity_amount: u64,
+    draws: u8,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if liquidity_amount == 0 {
+        msg!("Liquidity amount provided cannot be zero");
+        return Err(PoolingError::InvalidAmount.into());
+    }
+    let account_info_iter = &mut accounts.iter();
+
+    let source_liquidity_info = next_account_info(account_info_iter)?;
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.token_program_id != token_program_id.key {
+        msg!("Pool manager token program does not match the token program provided");
+        return Err(PoolingError::InvalidTokenProgram.into());
+    }
+    let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.liquidity.supply_pubkey != pool_liquidity_supply_info.key {
+        msg!("Pool liquidity supply does not match the pool liquidity supply provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.liquidity.supply_pubkey == source_liquidity_info.key {
+        msg!("Pool liquidity supply cannot be used as the source liquidity provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if pool.reentry_lock {
+        msg!("Can not reentry");
+        return Err(PoolingError::ReentryLocked.into());
+    }
+    assert_not_paused(&pool_manager, &pool, PAUSE_DEPOSIT)?;
+
+    pool.contribute_to_prize(liquidity_amount, draws)?;
+    msg!("Contributed {} to the prize of the next {} draws", liquidity_amount, draws);
+    Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_liquidity_info.clone(),
+        destination: pool_liquidity_supply_info.clone(),
+        amount: liquidity_amount,
+        authority: user_transfer_authority_info.clone(),
+        authority_signer_seeds: &[],
+        token_program: token_program_id.clone(),
+    })?;
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_set_pool_yield_source(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pool_manager_authority_info = next_account_info(account_info_iter)?;
+    let pool_manager_owner_info = next_account_info(account_info_iter)?;
+    let lending_program_info = next_account_info(account_info_iter)?;
+    let lending_reserve_info = next_account_info(account_info_iter)?;
+    let share_supply_info = next_account_info(account_info_iter)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.owner != pool_manager_owner_info.key {
+        msg!("Pool manager owner does not match the pool manager owner provided");
+        return Err(PoolingError::InvalidMarketOwner.into());
+    }
+    if !pool_manager_owner_info.is_signer {
+        msg!("Pool manager owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+    let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if pool.yield_source.redeemable_amount != 0 {
+        msg!(
+            "Yield source {} still holds {} of the pool liquidity",
+            pool.yield_source.reserve,
+            pool.yield_source.redeemable_amount
+        );
+        return Err(PoolingError::YieldSourceInUse.into());
+    }
+    let authority_signer_seeds = &[
+        pool_manager_info.key.as_ref(),
+        &[pool_manager.bump_seed],
+    ];
+    let pool_manager_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &pool_manager_authority_pubkey != pool_manager_authority_info.key {
+        msg!(
+            "Derived pool manager authority does not match the pool manager authority provided"
+        );
+        return Err(PoolingError::InvalidMarketAuthority.into());
+    }
+
+    if !lending_program_info.executable {
+        msg!("Lending program provided is not executable");
+        return Err(PoolingError::InvalidYieldSource.into());
+    }
+    let lending_reserve = load_lending_reserve(lending_program_info.key, lending_reserve_info)?;
+    if lending_reserve.liquidity_mint != pool.liquidity.mint_pubkey.to_bytes() {
+        msg!("Lending reserve does not lend the pool liquidity mint");
+        return Err(PoolingError::InvalidYieldSource.into());
+    }
+    if share_supply_info.owner != &pool_manager.token_program_id {
+        msg!("Share supply provided is not owned by the pool manager token program");
+        return Err(PoolingError::InvalidTokenOwner.into());
+    }
+    let share_supply = Account::unpack(&share_supply_info.data.borrow())?;
+    if share_supply.mint.to_bytes() != lending_reserve.share_mint {
+        msg!("Share supply mint does not match the lending reserve share mint");
+        return Err(PoolingError::InvalidYieldSource.into());
+    }
+    if &share_supply.owner != pool_manager_authority_info.key {
+        msg!("Share supply provided must be owned by the pool manager authority");
+        return Err(PoolingError::InvalidTokenOwner.into());
+    }
+
+    msg!(
+        "Yield source changed from {} to {}",
+        pool.yield_source.reserve,
+        lending_reserve_info.key
+    );
+    pool.yield_source.program_id = *lending_program_info.key;
+    pool.yield_source.reserve = *lending_reserve_info.key;
+    pool.yield_source.share_supply = *share_supply_info.key;
+    pool.yield_source.redeemable_amount = lending_reserve.shares_to_liquidity(share_supply.amount)?;
+    pool.last_update.mark_stale();
+    Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
+    Ok(())
+}
+
+/// Supply `amount` of liquidity to the pool yield source, or redeem `amount` of its shares
+#[inline(never)] // avoid stack frame limit
+fn process_yield_source_liquidity(
+    program_id: &Pubkey,
+    amount: u64,
+    supply: bool,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if amount == 0 {
+        msg!("Amount provided cannot be zero");
+        return Err(PoolingError::InvalidAmount.into());
+    }
+    let account_info_iter = &mut accounts.iter();
+
+    let pool_info = next_account_info(account_info_iter)?;
+    let pool_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let share_supply_info = next_account_info(account_info_iter)?;
+    let lending_reserve_info = next_account_info(account_info_iter)?;
+    let lending_reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let lending_share_mint_info = next_account_info(account_info_iter)?;
+    let lending_reserve_authority_info = next_account_info(account_info_iter)?;
+    let lending_program_info = next_account_info(account_info_iter)?;
+    let pool_manager_info = next_account_info(account_info_iter)?;
+    let pool_manager_authority_info = next_account_info(account_info_iter)?;
+    let pool_manager_owner_info = next_account_info(account_info_iter)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let pool_manager = PoolManager::unpack(&pool_manager_info.data.borrow())?;
+    if pool_manager_info.owner != program_id {
+        msg!("Pool manager provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool_manager.token_program_id != token_program_id.key {
+        msg!("Pool manager token program does not match the token program provided");
+        return Err(PoolingError::InvalidTokenProgram.into());
+    }
+    if &pool_manager.owner != pool_manager_owner_info.key {
+        msg!("Pool manager owner does not match the pool manager owner provided");
+        return Err(PoolingError::InvalidMarketOwner.into());
+    }
+    if !pool_manager_owner_info.is_signer {
+        msg!("Pool manager owner provided must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+    let mut pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if &pool.pool_manager != pool_manager_info.key {
+        msg!("pool's manager does not match the pool manager provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if &pool.liquidity.supply_pubkey != pool_liquidity_supply_info.key {
+        msg!("Pool liquidity supply does not match the pool liquidity supply provided");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if !pool.yield_source.is_enabled() {
+        msg!("Pool has no yield source");
+        return Err(PoolingError::InvalidYieldSource.into());
+    }
+    if &pool.yield_source.program_id != lending_program_info.key {
+        msg!("Yield source program does not match the lending program provided");
+        return Err(PoolingError::InvalidYieldSource.into());
+    }
+    if pool.reentry_lock {
+        msg!("Can not reentry");
+        return Err(PoolingError::ReentryLocked.into());
+    }
+    let authority_signer_seeds = &[
+        pool_manager_info.key.as_ref(),
+        &[pool_manager.bump_seed],
+    ];
+    let pool_manager_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &pool_manager_authority_pubkey != pool_manager_authority_info.key {
+        msg!(
+            "Derived pool manager authority does not match the pool manager authority provided"
+        );
+        return Err(PoolingError::InvalidMarketAuthority.into());
+    }
+
+    load_lending_reserve(lending_program_info.key, lending_reserve_info)?;
+    let liquidity_supply_amount = Account::unpack(&pool_liquidity_supply_info.data.borrow())?.amount;
+    let lending_accounts = LendingAccounts {
+        source: *pool_liquidity_supply_info.key,
+        destination: *share_supply_info.key,
+        reserve: *lending_reserve_info.key,
+        reserve_liquidity_supply: *lending_reserve_liquidity_supply_info.key,
+        share_mint: *lending_share_mint_info.key,
+        reserve_authority: *lending_reserve_authority_info.key,
+        user_transfer_authority: *pool_manager_authority_info.key,
+        token_program_id: *token_program_id.key,
+    };
+    let (instruction, account_infos) = if supply {
+        pool.supply_yield_source(amount)?;
+        (
+            lending_accounts.instruction(
+                lending_program_info.key,
+                LendingInstruction::Supply {
+                    liquidity_amount: amount,
+                },
+            ),
+            [pool_liquidity_supply_info.clone(), share_supply_info.clone()],
+        )
+    } else {
+        let share_amount = if amount == u64::MAX {
+            Account::unpack(&share_supply_info.data.borrow())?.amount
+        } else {
+            amount
+        };
+        (
+            LendingAccounts {
+                source: *share_supply_info.key,
+                destination: *pool_liquidity_supply_info.key,
+                ..lending_accounts
+            }
+            .instruction(
+                lending_program_info.key,
+                LendingInstruction::Redeem { share_amount },
+            ),
+            [share_supply_info.clone(), pool_liquidity_supply_info.clone()],
+        )
+    };
+    let [source_info, destination_info] = account_infos;
+    invoke_signed(
+        &instruction,
+        &[
+            source_info,
+            destination_info,
+            lending_reserve_info.clone(),
+            lending_reserve_liquidity_supply_info.clone(),
+            lending_share_mint_info.clone(),
+            lending_reserve_authority_info.clone(),
+            pool_manager_authority_info.clone(),
+            token_program_id.clone(),
+            lending_program_info.clone(),
+        ],
+        &[authority_signer_seeds],
+    )?;
+
+    if !supply {
+        // credit what the lending program paid out, not what the shares were quoted at
+        let redeemed_amount = Account::unpack(&pool_liquidity_supply_info.data.borrow())?
+            .amount
+            .checked_sub(liquidity_supply_amount)
+            .ok_or(PoolingError::MathOverflow)?;
+        msg!("Redeemed {} liquidity from the yield source", redeemed_amount);
+        pool.redeem_yield_source(redeemed_amount)?;
+    }
+    pool.yield_source.redeemable_amount =
+        get_yield_redeemable_amount(&pool, lending_reserve_info, share_supply_info)?;
+    msg!("Yield source holds {} of the pool liquidity", pool.yield_source.redeemable_amount);
+    pool.last_update.mark_stale();
+    Pool::pack(pool, &mut pool_info.data.borrow_mut())?;
     Ok(())
 }
 
+/// Load a lending reserve of the lending program `lending_program_id`
+fn load_lending_reserve(
+    lending_program_id: &Pubkey,
+    lending_reserve_info: &AccountInfo,
+) -> Result<LendingReserve, ProgramError> {
+    if lending_reserve_info.owner != lending_program_id {
+        msg!("Lending reserve provided is not owned by the lending program");
+        return Err(PoolingError::InvalidYieldSource.into());
+    }
+    let lending_reserve_data = lending_reserve_info.try_borrow_data()?;
+    let lending_reserve = yield_source::load(&lending_reserve_data)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    if lending_reserve.magic != yield_source::MAGIC {
+        msg!("Lending reserve account provided is not a valid lending reserve");
+        return Err(PoolingError::InvalidYieldSource.into());
+    }
+    if lending_reserve.ver != yield_source::VERSION_1 {
+        msg!("Lending reserve account provided has a different version than expected");
+        return Err(PoolingError::InvalidYieldSource.into());
+    }
+    Ok(*lending_reserve)
+}
+
+/// Read the liquidity redeemable for the reserve shares held by the pool yield source
+fn get_yield_redeemable_amount(
+    pool: &Pool,
+    lending_reserve_info: &AccountInfo,
+    share_supply_info: &AccountInfo,
+) -> Result<u64, ProgramError> {
+    if &pool.yield_source.reserve != lending_reserve_info.key {
+        msg!("Yield source reserve does not match the lending reserve provided");
+        return Err(PoolingError::InvalidYieldSource.into());
+    }
+    if &pool.yield_source.share_supply != share_supply_info.key {
+        msg!("Yield source share supply does not match the share supply provided");
+        return Err(PoolingError::InvalidYieldSource.into());
+    }
+    let lending_reserve = load_lending_reserve(&pool.yield_source.program_id, lending_reserve_info)?;
+    let share_supply = Account::unpack(&share_supply_info.data.borrow())?;
+    lending_reserve.shares_to_liquidity(share_supply.amount)
+}
+
+/// Unpack a pool refreshed in the current slot whose mine can be claimed
+fn unpack_mining_pool(
+    program_id: &Pubkey,
+    pool_manager: &PoolManager,
+    pool_pubkey: &Pubkey,
+    pool_info: &AccountInfo,
+    clock: &Clock,
+) -> Result<Pool, ProgramError> {
+    if pool_info.owner != program_id {
+        msg!("Pool provided is not owned by the pooling program");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    if pool_pubkey != pool_info.key {
+        msg!("Pool {} is expected instead of the pool provided", pool_pubkey);
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    let pool = Pool::unpack(&pool_info.data.borrow())?;
+    if pool.last_update.is_stale(clock.slot)? {
+        msg!("Pool is stale and must be refreshed in the current slot");
+        return Err(PoolingError::ReserveStale.into());
+    }
+    assert_not_paused(pool_manager, &pool, PAUSE_MINING_CLAIM)?;
+    Ok(pool)
+}
+
+/// Read a randomness result of the pool manager oracle requested for `pool` after its prize
+/// period ended
+fn get_randomness_result(
+    randomness_info: &AccountInfo,
+    pool_pubkey: &Pubkey,
+    pool: &Pool,
+    pool_manager: &PoolManager,
+) -> Result<randomness::RandomnessResult, ProgramError> {
+    if &pool_manager.randomness_program_id != randomness_info.owner {
+        msg!("Randomness result account provided is not owned by the pool manager randomness program");
+        return Err(PoolingError::InvalidRandomnessConfig.into());
+    }
+    let randomness_data = randomness_info.try_borrow_data()?;
+    let result = randomness::load(&randomness_data)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    if result.magic != randomness::MAGIC {
+        msg!("Randomness result account provided is not a valid randomness account");
+        return Err(PoolingError::InvalidRandomnessConfig.into());
+    }
+    if result.ver != randomness::VERSION_1 {
+        msg!("Randomness result account provided has a different version than expected");
+        return Err(PoolingError::InvalidRandomnessConfig.into());
+    }
+    if result.requester != pool_pubkey.to_bytes() {
+        msg!("Randomness result was not requested for this pool");
+        return Err(PoolingError::InvalidRandomnessResult.into());
+    }
+    if result.request_slot < pool.prize.period_end_slot()? {
+        msg!("Randomness result must be requested after the prize period ends");
+        return Err(PoolingError::InvalidRandomnessResult.into());
+    }
+    Ok(*result)
+}
+
+/// Look up the hash of `slot` in the SlotHashes sysvar without deserializing all of its entries
+fn get_slot_hash(slot_hashes_info: &AccountInfo, slot: Slot) -> Result<[u8; 32], ProgramError> {
+    const ENTRY_LEN: usize = 8 + 32;
+    let data = slot_hashes_info.try_borrow_data()?;
+    let (len, entries) = unpack_u64(&data)?;
+    for entry in entries.chunks_exact(ENTRY_LEN).take(len as usize) {
+        let (entry_slot, hash) = unpack_u64(entry)?;
+        if entry_slot == slot {
+            let (hash, _) = unpack_bytes32(hash)?;
+            return Ok(*hash);
+        }
+    }
+    msg!("Slot hash of slot {} is no longer available", slot);
+    Err(PoolingError::SlotHashNotFound.into())
+}
+
+/// Check that the init authority is the upgrade authority of the program, read from its program
+/// data account. Built with `permissionless-init`, any signer can initialize a pool manager.
+fn assert_init_authority(
+    program_id: &Pubkey,
+    program_data_info: &AccountInfo,
+    init_authority_info: &AccountInfo,
+) -> ProgramResult {
+    if !init_authority_info.is_signer {
+        msg!("Init pool manager authority account must be a signer");
+        return Err(PoolingError::InvalidSigner.into());
+    }
+    if cfg!(feature = "permissionless-init") {
+        return Ok(());
+    }
+    let (program_data_pubkey, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    if &program_data_pubkey != program_data_info.key {
+        msg!("Program data account provided does not belong to the pooling program");
+        return Err(PoolingError::InvalidAccountInput.into());
+    }
+    if program_data_info.owner != &bpf_loader_upgradeable::id() {
+        msg!("Program data account provided is not owned by the upgradeable loader");
+        return Err(PoolingError::InvalidAccountOwner.into());
+    }
+    match get_upgrade_authority(&program_data_info.try_borrow_data()?)? {
+        Some(upgrade_authority) if &upgrade_authority == init_authority_info.key => Ok(()),
+        _ => {
+            msg!("Init pool manager authority is not the program upgrade authority");
+            Err(PoolingError::InvalidInitAuthority.into())
+        }
+    }
+}
+
+/// Read the upgrade authority out of the bincode encoded `UpgradeableLoaderState::ProgramData`
+fn get_upgrade_authority(data: &[u8]) -> Result<Option<Pubkey>, ProgramError> {
+    const PROGRAM_DATA_TAG: [u8; 4] = [3, 0, 0, 0];
+    if data.get(..4) != Some(&PROGRAM_DATA_TAG[..]) {
+        msg!("Program data account cannot be unpacked");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let (_slot, rest) = unpack_u64(&data[4..])?;
+    let (has_authority, rest) = unpack_bool(rest)?;
+    if !has_authority {
+        return Ok(None);
+    }
+    let (upgrade_authority, _rest) = unpack_pubkey(rest)?;
+    Ok(Some(upgrade_authority))
+}
+
 fn assert_rent_exempt(rent: &Rent, account_info: &AccountInfo) -> ProgramResult {
     if !rent.is_exempt(account_info.lamports(), account_info.data_len()) {
         msg!(&rent.minimum_balance(account_info.data_len()).to_string());
@@ -677,12 +2899,69 @@ fn assert_uninitialized<T: Pack + IsInitialized>(
     }
 }
 
+/// Check that neither the pool manager kill switch nor the pool config pauses `operation`
+fn assert_not_paused(pool_manager: &PoolManager, pool: &Pool, operation: u8) -> ProgramResult {
+    if pool_manager.paused {
+        msg!("Pool manager is paused");
+        return Err(PoolingError::PoolManagerPaused.into());
+    }
+    pool.config.check_paused(operation)
+}
+
 /// Unpacks a spl_token `Mint`.
 fn unpack_mint(data: &[u8]) -> Result<Mint, PoolingError> {
     Mint::unpack(data).map_err(|_| PoolingError::InvalidTokenMint)
 }
 
 
+/// Check that the Pyth product and price accounts belong to the pool manager oracle program,
+/// match each other and quote in the pool manager quote currency
+fn assert_pyth_oracle(
+    pool_manager: &PoolManager,
+    pyth_product_info: &AccountInfo,
+    pyth_price_info: &AccountInfo,
+) -> ProgramResult {
+    if &pool_manager.oracle_program_id != pyth_product_info.owner {
+        msg!("Pyth product account provided is not owned by the lending market oracle program");
+        return Err(PoolingError::InvalidOracleConfig.into());
+    }
+    if &pool_manager.oracle_program_id != pyth_price_info.owner {
+        msg!("Pyth price account provided is not owned by the lending market oracle program");
+        return Err(PoolingError::InvalidOracleConfig.into());
+    }
+    let pyth_product_data = pyth_product_info.try_borrow_data()?;
+    let pyth_product = pyth::load::<pyth::Product>(&pyth_product_data)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    if pyth_product.magic != pyth::MAGIC {
+        msg!("Pyth product account provided is not a valid Pyth account");
+        return Err(PoolingError::InvalidOracleConfig.into());
+    }
+    if pyth_product.ver != pyth::VERSION_2 {
+        msg!("Pyth product account provided has a different version than expected");
+        return Err(PoolingError::InvalidOracleConfig.into());
+    }
+    if pyth_product.atype != pyth::AccountType::Product as u32 {
+        msg!("Pyth product account provided is not a valid Pyth product account");
+        return Err(PoolingError::InvalidOracleConfig.into());
+    }
+
+    let pyth_price_pubkey_bytes: &[u8; 32] = pyth_price_info
+        .key
+        .as_ref()
+        .try_into()
+        .map_err(|_| PoolingError::InvalidAccountInput)?;
+    if &pyth_product.px_acc.val != pyth_price_pubkey_bytes {
+        msg!("Pyth product price account does not match the Pyth price provided");
+        return Err(PoolingError::InvalidOracleConfig.into());
+    }
+    let quote_currency = get_pyth_product_quote_currency(pyth_product)?;
+    if pool_manager.quote_currency != quote_currency {
+        msg!("Lending market quote currency does not match the oracle quote currency");
+        return Err(PoolingError::InvalidOracleConfig.into());
+    }
+    Ok(())
+}
+
 fn get_pyth_price(pyth_price_info: &AccountInfo, _clock: &Clock) -> Result<Decimal, ProgramError> {
     // const STALE_AFTER_SLOTS_ELAPSED: u64 = 5;
 
@@ -911,3 +3190,117 @@ pub fn get_pyth_product_quote_currency(pyth_product: &pyth::Product) -> Result<[
     Err(PoolingError::InvalidOracleConfig.into())
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn program_data(upgrade_authority: Option<&Pubkey>) -> Vec<u8> {
+        let mut data = vec![3, 0, 0, 0];
+        data.extend_from_slice(&42u64.to_le_bytes());
+        match upgrade_authority {
+            Some(upgrade_authority) => {
+                data.push(1);
+                data.extend_from_slice(upgrade_authority.as_ref());
+            }
+            None => data.push(0),
+        }
+        data
+    }
+
+    #[test]
+    fn unpack_upgrade_authority() {
+        let upgrade_authority = Pubkey::new_unique();
+        assert_eq!(
+            get_upgrade_authority(&program_data(Some(&upgrade_authority))).unwrap(),
+            Some(upgrade_authority)
+        );
+        assert_eq!(get_upgrade_authority(&program_data(None)).unwrap(), None);
+
+        let mut program = program_data(None);
+        program[0] = 2;
+        assert_eq!(
+            get_upgrade_authority(&program),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[cfg(not(feature = "permissionless-init"))]
+    #[test]
+    fn init_authority_is_upgrade_authority() {
+        let program_id = Pubkey::new_unique();
+        let loader_id = bpf_loader_upgradeable::id();
+        let (program_data_pubkey, _) =
+            Pubkey::find_program_address(&[program_id.as_ref()], &loader_id);
+        let upgrade_authority = Pubkey::new_unique();
+        let other_authority = Pubkey::new_unique();
+        let mut data = program_data(Some(&upgrade_authority));
+        let mut lamports = 0;
+        let program_data_info = AccountInfo::new(
+            &program_data_pubkey, false, false, &mut lamports, &mut data, &loader_id, false, 0,
+        );
+        let mut authority_lamports = 0;
+        let mut authority_data = [];
+        let system_id = Pubkey::default();
+        let authority_info = AccountInfo::new(
+            &upgrade_authority, true, false, &mut authority_lamports, &mut authority_data, &system_id, false, 0,
+        );
+        assert_eq!(assert_init_authority(&program_id, &program_data_info, &authority_info), Ok(()));
+
+        let mut other_lamports = 0;
+        let mut other_data = [];
+        let mut other_info = AccountInfo::new(
+            &other_authority, true, false, &mut other_lamports, &mut other_data, &system_id, false, 0,
+        );
+        assert_eq!(
+            assert_init_authority(&program_id, &program_data_info, &other_info),
+            Err(PoolingError::InvalidInitAuthority.into())
+        );
+        other_info.is_signer = false;
+        assert_eq!(
+            assert_init_authority(&program_id, &program_data_info, &other_info),
+            Err(PoolingError::InvalidSigner.into())
+        );
+
+        // program data of another program
+        assert_eq!(
+            assert_init_authority(&Pubkey::new_unique(), &program_data_info, &authority_info),
+            Err(PoolingError::InvalidAccountInput.into())
+        );
+
+        // immutable programs have no upgrade authority
+        let mut immutable_data = program_data(None);
+        let mut immutable_lamports = 0;
+        let immutable_info = AccountInfo::new(
+            &program_data_pubkey, false, false, &mut immutable_lamports, &mut immutable_data, &loader_id, false, 0,
+        );
+        assert_eq!(
+            assert_init_authority(&program_id, &immutable_info, &authority_info),
+            Err(PoolingError::InvalidInitAuthority.into())
+        );
+    }
+
+    #[cfg(feature = "permissionless-init")]
+    #[test]
+    fn init_authority_is_any_signer() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let system_id = Pubkey::default();
+        let mut lamports = 0;
+        let mut data = [];
+        let mut authority_info = AccountInfo::new(
+            &authority, true, false, &mut lamports, &mut data, &system_id, false, 0,
+        );
+        let mut program_data_lamports = 0;
+        let mut program_data = [];
+        let program_data_info = AccountInfo::new(
+            &system_id, false, false, &mut program_data_lamports, &mut program_data, &system_id, false, 0,
+        );
+        assert_eq!(assert_init_authority(&program_id, &program_data_info, &authority_info), Ok(()));
+        authority_info.is_signer = false;
+        assert_eq!(
+            assert_init_authority(&program_id, &program_data_info, &authority_info),
+            Err(PoolingError::InvalidSigner.into())
+        );
+    }
+}
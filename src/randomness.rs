@@ -0,0 +1,115 @@
+//! Randomness-result account layout of a VRF-style randomness oracle.
+//!
+//! The oracle program fulfills a request made for a `requester` account by writing a
+//! verified random `result`. Locally, any program id can act as the oracle: a mock
+//! result account owned by that program id and filled with [`RandomnessResult::new_fulfilled`]
+//! is enough to exercise draws in `solana-program-test` without network access.
+
+use bytemuck::{bytes_of, Pod, PodCastError, Zeroable};
+use solana_program::{clock::Slot, pubkey::Pubkey};
+use std::mem::size_of;
+
+/// Magic number identifying a randomness result account
+pub const MAGIC: u32 = 0x7a3c1e5b;
+/// First randomness result layout version
+pub const VERSION_1: u32 = 1;
+/// Current randomness result layout version
+pub const VERSION: u32 = VERSION_1;
+
+/// Status of a randomness request
+#[derive(Copy, Clone, PartialEq)]
+#[repr(u32)]
+pub enum ResultStatus {
+    /// Result account not in use
+    Unknown,
+    /// Randomness requested but not written yet
+    Pending,
+    /// Verified randomness written
+    Fulfilled,
+}
+
+/// Randomness result account written by the oracle program
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct RandomnessResult {
+    /// Randomness oracle magic number
+    pub magic: u32,
+    /// Layout version
+    pub ver: u32,
+    /// Result status
+    pub status: u32,
+    /// Unused, keeps the slots aligned
+    pub unused: u32,
+    /// Slot the randomness was requested at
+    pub request_slot: u64,
+    /// Slot the randomness was written at
+    pub fulfill_slot: u64,
+    /// Account the randomness was requested for
+    pub requester: [u8; 32],
+    /// Verified random value
+    pub result: [u8; 32],
+}
+
+#[cfg(target_endian = "little")]
+unsafe impl Zeroable for RandomnessResult {}
+
+#[cfg(target_endian = "little")]
+unsafe impl Pod for RandomnessResult {}
+
+impl RandomnessResult {
+    /// Create a fulfilled result, as written by the (mock) oracle program
+    pub fn new_fulfilled(
+        requester: &Pubkey,
+        request_slot: Slot,
+        fulfill_slot: Slot,
+        result: [u8; 32],
+    ) -> Self {
+        Self {
+            magic: MAGIC,
+            ver: VERSION,
+            status: ResultStatus::Fulfilled as u32,
+            unused: 0,
+            request_slot,
+            fulfill_slot,
+            requester: requester.to_bytes(),
+            result,
+        }
+    }
+
+    /// Serialize the result into account data
+    pub fn to_account_data(&self) -> Vec<u8> {
+        bytes_of(self).to_vec()
+    }
+}
+
+/// Load a randomness result from account data
+pub fn load(data: &[u8]) -> Result<&RandomnessResult, PodCastError> {
+    if data.len() < size_of::<RandomnessResult>() {
+        return Err(PodCastError::SizeMismatch);
+    }
+    crate::pyth::load::<RandomnessResult>(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mock_result_round_trip() {
+        let requester = Pubkey::new_unique();
+        let mock = RandomnessResult::new_fulfilled(&requester, 10, 12, [9u8; 32]);
+        let data = mock.to_account_data();
+        assert_eq!(data.len(), size_of::<RandomnessResult>());
+
+        let result = load(&data).unwrap();
+        assert_eq!(result.magic, MAGIC);
+        assert_eq!(result.ver, VERSION);
+        assert_eq!(result.status, ResultStatus::Fulfilled as u32);
+        assert_eq!(result.request_slot, 10);
+        assert_eq!(result.fulfill_slot, 12);
+        assert_eq!(result.requester, requester.to_bytes());
+        assert_eq!(result.result, [9u8; 32]);
+
+        assert!(load(&data[..data.len() - 1]).is_err());
+    }
+}
@@ -80,6 +80,10 @@ impl Mining {
         self.mining_indices[index].index = reserve.lottery.l_token_mining_index;
         Ok(())
     }
+    /// Take the whole tokens of the unclaimed mine, the fractional remainder stays unclaimed
+    pub fn claim_mine(&mut self) -> Result<u64, ProgramError> {
+        claim_whole_mine(&mut self.unclaimed_mine)
+    }
     pub fn find_mining_index(&mut self, reserve: &Pubkey)
      -> Result<usize, ProgramError> {
         if self.mining_indices.is_empty() {
@@ -251,3 +255,42 @@ pub struct InitMiningParams{
     pub owner: Pubkey,
     pub mining_indices: Vec<MiningIndex>
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deposit_and_withdraw_settle_mine() {
+        let pool_pubkey = Pubkey::new_unique();
+        let mut pool = Pool::default();
+        pool.lottery.l_token_mining_index = Decimal::from(2u64);
+        let mut mining = Mining::new(InitMiningParams {
+            lending_market: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            mining_indices: vec![],
+        });
+
+        let (_, index) = mining
+            .find_or_add_reserve_in_vec(pool_pubkey, pool.lottery.l_token_mining_index)
+            .unwrap();
+        mining.refresh_unclaimed(index, &pool).unwrap();
+        mining.deposit(index, 10).unwrap();
+        assert_eq!(mining.unclaimed_mine, Decimal::zero());
+
+        pool.lottery.l_token_mining_index = Decimal::from(5u64);
+        let index = mining.find_mining_index(&pool_pubkey).unwrap();
+        mining.refresh_unclaimed(index, &pool).unwrap();
+        mining.withdraw(index, 4).unwrap();
+        assert_eq!(mining.unclaimed_mine, Decimal::from(30u64));
+        assert_eq!(mining.mining_indices[0].un_coll_l_token_amount, 6);
+
+        let mut packed = [0u8; Mining::LEN];
+        Mining::pack(mining.clone(), &mut packed).unwrap();
+        assert_eq!(Mining::unpack(&packed).unwrap(), mining);
+
+        mining.withdraw(index, 6).unwrap();
+        assert!(mining.mining_indices.is_empty());
+        assert_eq!(mining.claim_mine().unwrap(), 30);
+    }
+}
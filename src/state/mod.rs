@@ -11,14 +11,18 @@ pub use pool_manager::*;
 pub use mining::*;
 pub use ticket::*;
 pub use pool::*;
+pub use prize::*;
+pub use twab::*;
 
-use crate::math::{Decimal, WAD};
+use crate::math::{Decimal, TrySub, WAD};
 
 pub(crate) mod last_update;
 mod pool_manager;
 mod ticket;
 mod pool;
 mod mining;
+mod prize;
+mod twab;
 
 
 /// Collateral tokens are initially valued at a ratio of 5:1 (collateral:liquidity)
@@ -26,8 +30,22 @@ mod mining;
 pub const INITIAL_COLLATERAL_RATIO: u64 = 1;
 const INITIAL_COLLATERAL_RATE: u64 = INITIAL_COLLATERAL_RATIO * WAD;
 
-/// Current version of the program and all new accounts created
-pub const PROGRAM_VERSION: u8 = 1;
+/// Current version of the program and all new accounts created.
+///
+/// Version 2 pools keep the principal, prize, draw and yield source state in a 1024 byte layout
+/// with reserved padding, where version 1 pools are 646 bytes with that space unused. Version 2
+/// tickets are 1932 bytes, with prize, time-weighted balance, draw index and exit fee state per
+/// deposit and reserved padding, where version 1 tickets are 827 bytes. Accounts cannot be resized, so
+/// version 1 accounts are not migrated in place: unpacking them fails, and deposits must be
+/// withdrawn and liquidity redeemed with the version 1 program before the upgrade, then deposited
+/// into new pools and tickets.
+pub const PROGRAM_VERSION: u8 = 2;
+
+/// Oldest pool version the current layout can read
+pub const MIN_POOL_VERSION: u8 = 2;
+
+/// Oldest ticket version the current layout can read
+pub const MIN_TICKET_VERSION: u8 = 2;
 
 /// Accounts are created with data zeroed out, so uninitialized state instances
 /// will have the version set to 0.
@@ -38,6 +56,13 @@ pub const SLOTS_PER_YEAR: u64 = 78840000;
 pub const HOST_FEE_RECEIVER_COUNT: usize = 5;
 
 // Helpers
+/// Take the whole tokens of an unclaimed mine amount, the fractional remainder stays unclaimed
+fn claim_whole_mine(unclaimed_mine: &mut Decimal) -> Result<u64, ProgramError> {
+    let claim_amount = unclaimed_mine.try_floor_u64()?;
+    *unclaimed_mine = unclaimed_mine.try_sub(Decimal::from(claim_amount))?;
+    Ok(claim_amount)
+}
+
 fn pack_decimal(decimal: Decimal, dst: &mut [u8; 16]) {
     *dst = decimal
         .to_scaled_val()
@@ -69,6 +69,16 @@ pub struct Pool {
     pub lottery: Lottery,
     /// Entry lock
     pub reentry_lock: bool,
+    /// Prize draw state
+    pub prize: Prize,
+    /// Total collateral deposited into tickets
+    pub ticket_balance: u64,
+    /// Time-weighted ticket balance of the prize period
+    pub ticket_twab: Twab,
+    /// Total collateral deposited into sponsor tickets, earning for the prize without drawing
+    pub sponsored_amount: u64,
+    /// External lending program earning yield on the pool liquidity
+    pub yield_source: YieldSource,
 }
 
 impl Pool {
@@ -89,6 +99,11 @@ impl Pool {
         self.config = params.config;
         self.lottery = params.lottery;
         self.reentry_lock = false;
+        self.prize = params.prize;
+        self.ticket_balance = 0;
+        self.ticket_twab = Twab::default();
+        self.sponsored_amount = 0;
+        self.yield_source = YieldSource::default();
     }
     pub fn refresh_index(&mut self, slot: Slot) -> ProgramResult {
         if self.collateral.mint_total_supply == 0 {
@@ -116,37 +131,97 @@ impl Pool {
         )?;
         Ok(())
     }
-    ///
-    /// 挖矿比例
+    /// Calculate the current borrow rate along the interest rate model, which rises from the min
+    /// to the optimal borrow rate up to the optimal utilization rate, then up to the max borrow rate
+    pub fn current_borrow_rate(&self) -> Result<Rate, ProgramError> {
+        let utilization_rate = self.liquidity.utilization_rate()?;
+        let optimal_utilization_rate = Rate::from_percent(self.config.optimal_utilization_rate);
+        let low_utilization = utilization_rate < optimal_utilization_rate;
+        if low_utilization || self.config.optimal_utilization_rate == 100 {
+            let normalized_rate = utilization_rate.try_div(optimal_utilization_rate)?;
+            let min_rate = Rate::from_percent(self.config.min_borrow_rate);
+            let rate_range = Rate::from_percent(
+                self.config.optimal_borrow_rate - self.config.min_borrow_rate,
+            );
+            normalized_rate.try_mul(rate_range)?.try_add(min_rate)
+        } else {
+            let normalized_rate = utilization_rate
+                .try_sub(optimal_utilization_rate)?
+                .try_div(Rate::from_percent(100 - self.config.optimal_utilization_rate))?;
+            let min_rate = Rate::from_percent(self.config.optimal_borrow_rate);
+            let rate_range = Rate::from_percent(
+                self.config.max_borrow_rate - self.config.optimal_borrow_rate,
+            );
+            normalized_rate.try_mul(rate_range)?.try_add(min_rate)
+        }
+    }
+
+    /// Compound interest at the current borrow rate over the slots elapsed since the last
+    /// update, keeping the reserve owner share of it
+    pub fn accrue_interest(&mut self, slot: Slot) -> ProgramResult {
+        let slots_elapsed = self.last_update.slots_elapsed(slot)?;
+        if slots_elapsed > 0 {
+            let current_borrow_rate = self.current_borrow_rate()?;
+            self.liquidity.compound_interest(
+                current_borrow_rate,
+                slots_elapsed,
+                self.config.reserve_owner_fee_wad,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Accrue mining at the current speed up to `slot`, then change the mining speed.
+    /// The pool must be refreshed again before being used.
+    pub fn update_mining_speed(
+        &mut self,
+        slot: Slot,
+        total_mining_speed: u64,
+        kink_util_rate: u64,
+    ) -> ProgramResult {
+        self.accrue_interest(slot)?;
+        self.refresh_index(slot)?;
+        self.last_update.update_slot(slot);
+        self.last_update.mark_stale();
+        self.lottery.total_mining_speed = total_mining_speed;
+        self.lottery.kink_util_rate = kink_util_rate;
+        Ok(())
+    }
+
+    /// Split of the mining speed between the (lend, borrow) sides, which always sums to one.
+    /// Without any borrow, the whole mining speed goes to the lend side.
     fn get_mine_ratio(&self) -> Result<(Rate, Rate), ProgramError> {
-        Ok((Rate::one().try_div(Rate::from_percent(50))?, Rate::one().try_div(Rate::from_percent(50))?))
-        // if self.collateral.mint_total_supply == 0 as u64 {
-        //     return Ok((Rate::zero(), Rate::zero()));
-        // }
-        // if self.liquidity.borrowed_amount_wads.lt(&Decimal::one()) {
-        //     return Ok((Rate::one(), Rate::zero()));
-        // }
-        //
-        // let utilization_rate = self.liquidity.utilization_rate()?;
-        // let kink_rate = Rate::try_from(
-        //     Decimal::from(self.lottery.kink_util_rate).try_div(Decimal::from(10000 as u64))?
-        // )?;
-        // if utilization_rate < kink_rate {
-        //     let normalized_rate = utilization_rate.try_div(kink_rate)?;
-        //     let min_rate = Rate::from_percent(0);
-        //     let rate_range = Rate::from_percent(50);
-        //     let mining_rate = normalized_rate.try_mul(rate_range)?.try_add(min_rate)?;
-        //
-        //     Ok((mining_rate, Rate::one().try_sub(mining_rate)?))
-        // } else {
-        //     let normalized_rate = utilization_rate
-        //         .try_sub(kink_rate)?
-        //         .try_div(Rate::from_percent(100u8).try_sub(kink_rate)?)?;
-        //     let min_rate = Rate::from_percent(50);
-        //     let rate_range = Rate::from_percent(100u8).try_sub(min_rate)?;
-        //     let mining_rate = normalized_rate.try_mul(rate_range)?.try_add(min_rate)?;
-        //     Ok((mining_rate, Rate::one().try_sub(mining_rate)?))
-        // }
+        if self.liquidity.borrowed_amount_wads.lt(&Decimal::one()) {
+            return Ok((Rate::one(), Rate::zero()));
+        }
+        let lend_side_ratio = match self.config.mine_distribution {
+            MineDistribution::Kinked => self.get_kinked_mine_ratio()?,
+            MineDistribution::Flat => Rate::from_percent(50),
+            MineDistribution::DepositOnly => Rate::one(),
+        };
+        Ok((lend_side_ratio, Rate::one().try_sub(lend_side_ratio)?))
+    }
+
+    /// Lend side ratio rising linearly from 0% to 50% up to the kink utilization rate,
+    /// then from 50% to 100% up to full utilization
+    fn get_kinked_mine_ratio(&self) -> Result<Rate, ProgramError> {
+        let utilization_rate = self.liquidity.utilization_rate()?;
+        let kink_rate = Rate::try_from(
+            Decimal::from(self.lottery.kink_util_rate.min(MAX_KINK_UTIL_RATE))
+                .try_div(MAX_KINK_UTIL_RATE)?,
+        )?;
+        let half_rate = Rate::from_percent(50);
+        if utilization_rate < kink_rate {
+            let normalized_rate = utilization_rate.try_div(kink_rate)?;
+            normalized_rate.try_mul(half_rate)
+        } else if kink_rate == Rate::one() {
+            Ok(half_rate)
+        } else {
+            let normalized_rate = utilization_rate
+                .try_sub(kink_rate)?
+                .try_div(Rate::one().try_sub(kink_rate)?)?;
+            normalized_rate.try_mul(half_rate)?.try_add(half_rate)
+        }
     }
 
     /// Record deposited liquidity and return amount of collateral tokens to mint
@@ -156,12 +231,14 @@ impl Pool {
             .liquidity_to_collateral(liquidity_amount)?;
 
         self.liquidity.deposit(liquidity_amount)?;
+        self.liquidity.add_principal(liquidity_amount)?;
         self.collateral.mint(collateral_amount)?;
 
         Ok(collateral_amount)
     }
 
-    /// Record redeemed collateral and return amount of liquidity to withdraw
+    /// Record redeemed collateral and return amount of liquidity to withdraw.
+    /// Collateral is redeemed for principal only, the prize pot stays in the pool.
     pub fn redeem_collateral(&mut self, collateral_amount: u64) -> Result<u64, ProgramError> {
         let collateral_exchange_rate = self.collateral_exchange_rate()?;
         let liquidity_amount =
@@ -169,15 +246,188 @@ impl Pool {
 
         self.collateral.burn(collateral_amount)?;
         self.liquidity.withdraw(liquidity_amount)?;
+        self.liquidity.sub_principal(liquidity_amount)?;
 
         Ok(liquidity_amount)
     }
 
+    /// Liquidity accrued on top of the deposited principal and the prizes not claimed yet,
+    /// which forms the prize of the next draw
+    pub fn prize_available(&self) -> Result<u64, ProgramError> {
+        let reserved = Decimal::from(self.liquidity.principal_amount)
+            .try_add(Decimal::from(self.prize.unclaimed_amount))?
+            .try_add(Decimal::from(self.prize.deferred_contribution_amount()?))?;
+        let total_supply = self
+            .liquidity
+            .total_supply()?
+            .try_add(Decimal::from(self.yield_source.redeemable_amount))?;
+        if total_supply.le(&reserved) {
+            return Ok(0);
+        }
+        total_supply.try_sub(reserved)?.try_floor_u64()
+    }
+
+    /// Calculate the exit fee of withdrawing `liquidity_amount` at `slot` from a deposit made at
+    /// `deposit_slot`. The fee window restarts at every draw.
+    pub fn calculate_exit_fee(
+        &self,
+        liquidity_amount: u64,
+        deposit_slot: Slot,
+        slot: Slot,
+    ) -> Result<u64, ProgramError> {
+        let start_slot = deposit_slot.max(self.prize.last_draw_slot);
+        let exit_fee_rate = self.config.exit_fee_rate(start_slot, slot)?;
+        if exit_fee_rate == Rate::zero() {
+            return Ok(0);
+        }
+        Decimal::from(liquidity_amount)
+            .try_mul(exit_fee_rate)?
+            .try_ceil_u64()
+    }
+
+    /// Add a contribution to the prize of the next `draws` draws, without minting collateral
+    pub fn contribute_to_prize(&mut self, liquidity_amount: u64, draws: u8) -> ProgramResult {
+        self.prize.schedule_contribution(liquidity_amount, draws)?;
+        self.liquidity.deposit(liquidity_amount)
+    }
+
+    /// Move available liquidity to the yield source, where it keeps counting toward the prize
+    /// but cannot be withdrawn until redeemed
+    pub fn supply_yield_source(&mut self, liquidity_amount: u64) -> ProgramResult {
+        self.liquidity.withdraw(liquidity_amount)?;
+        self.yield_source.redeemable_amount = self
+            .yield_source
+            .redeemable_amount
+            .checked_add(liquidity_amount)
+            .ok_or(PoolingError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Make liquidity redeemed from the yield source available again
+    pub fn redeem_yield_source(&mut self, liquidity_amount: u64) -> ProgramResult {
+        self.yield_source.redeemable_amount = self
+            .yield_source
+            .redeemable_amount
+            .saturating_sub(liquidity_amount);
+        self.liquidity.deposit(liquidity_amount)
+    }
+
+    /// Keep an exit fee in the pool, where it adds to the prize of the next draw
+    pub fn collect_exit_fee(&mut self, exit_fee: u64) -> ProgramResult {
+        self.liquidity.deposit(exit_fee)
+    }
+
+    /// Reserve the protocol fee out of the prize available for a draw, and return the prize
+    /// left for the winners. The fee accrues to the reserve owner until it is withdrawn.
+    /// The prize left over by previous draws was already taxed and is skipped.
+    pub fn skim_protocol_fee(&mut self) -> Result<u64, ProgramError> {
+        let untaxed_amount = self
+            .prize_available()?
+            .saturating_sub(self.prize.taxed_amount);
+        let protocol_fee = Decimal::from(untaxed_amount)
+            .try_mul(Rate::from_scaled_val(self.config.protocol_fee_wad))?;
+        self.liquidity.owner_unclaimed = self.liquidity.owner_unclaimed.try_add(protocol_fee)?;
+        let prize_amount = self.prize_available()?;
+        self.prize.taxed_amount = prize_amount;
+        Ok(prize_amount)
+    }
+
+    /// Cumulative ticket balance of the whole prize period, shared by the tickets of its draw
+    pub fn period_ticket_balance(&self) -> Result<u128, ProgramError> {
+        self.ticket_twab
+            .period_cumulative_balance(self.ticket_balance, &self.prize)
+    }
+
+    /// Close a prize period without ticket balance, carrying its prize over to the next draw
+    pub fn roll_over_prize(&mut self, slot: Slot) -> ProgramResult {
+        self.prize.record_draw(slot, Pubkey::default(), 0)
+    }
+
+    /// Record collateral deposited into tickets, accumulating the time-weighted ticket balance first
+    pub fn deposit_ticket_balance(&mut self, collateral_amount: u64, slot: Slot) -> ProgramResult {
+        self.ticket_twab.accrue(self.ticket_balance, slot, &self.prize)?;
+        self.ticket_balance = self
+            .ticket_balance
+            .checked_add(collateral_amount)
+            .ok_or(PoolingError::MathOverflow)?;
+        Ok(())
+    }
 
-    /// Collateral exchange rate
+    /// Record collateral withdrawn from tickets, accumulating the time-weighted ticket balance first
+    pub fn withdraw_ticket_balance(&mut self, collateral_amount: u64, slot: Slot) -> ProgramResult {
+        self.ticket_twab.accrue(self.ticket_balance, slot, &self.prize)?;
+        self.ticket_balance = self
+            .ticket_balance
+            .checked_sub(collateral_amount)
+            .ok_or(PoolingError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Record collateral deposited into sponsor tickets
+    pub fn deposit_sponsorship(&mut self, collateral_amount: u64) -> ProgramResult {
+        self.sponsored_amount = self
+            .sponsored_amount
+            .checked_add(collateral_amount)
+            .ok_or(PoolingError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Record collateral withdrawn from sponsor tickets
+    pub fn withdraw_sponsorship(&mut self, collateral_amount: u64) -> ProgramResult {
+        self.sponsored_amount = self
+            .sponsored_amount
+            .checked_sub(collateral_amount)
+            .ok_or(PoolingError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Set aside a prize awarded to a winner until it is claimed
+    pub fn award_prize(&mut self, prize_amount: u64) -> ProgramResult {
+        if prize_amount > self.prize_available()? {
+            msg!("Prize amount cannot exceed the prize available");
+            return Err(PoolingError::InsufficientLiquidity.into());
+        }
+        self.prize.unclaimed_amount = self
+            .prize
+            .unclaimed_amount
+            .checked_add(prize_amount)
+            .ok_or(PoolingError::MathOverflow)?;
+        self.prize.taxed_amount = self.prize.taxed_amount.saturating_sub(prize_amount);
+        Ok(())
+    }
+
+    /// Record a claimed prize as liquidity withdrawn from the pool
+    pub fn claim_prize(&mut self, prize_amount: u64) -> ProgramResult {
+        self.prize.unclaimed_amount = self
+            .prize
+            .unclaimed_amount
+            .checked_sub(prize_amount)
+            .ok_or(PoolingError::MathOverflow)?;
+        self.liquidity.withdraw(prize_amount)
+    }
+
+    /// Record a claimed prize deposited back as principal and return amount of collateral tokens to mint.
+    /// The prize never leaves the pool, so the available liquidity is left untouched.
+    pub fn compound_prize(&mut self, prize_amount: u64) -> Result<u64, ProgramError> {
+        let collateral_amount = self
+            .collateral_exchange_rate()?
+            .liquidity_to_collateral(prize_amount)?;
+
+        self.prize.unclaimed_amount = self
+            .prize
+            .unclaimed_amount
+            .checked_sub(prize_amount)
+            .ok_or(PoolingError::MathOverflow)?;
+        self.liquidity.add_principal(prize_amount)?;
+        self.collateral.mint(collateral_amount)?;
+
+        Ok(collateral_amount)
+    }
+
+    /// Collateral exchange rate, which only accounts for the principal deposited
     pub fn collateral_exchange_rate(&self) -> Result<CollateralExchangeRate, ProgramError> {
-        let total_liquidity = self.liquidity.total_supply()?;
-        self.collateral.exchange_rate(total_liquidity)
+        let principal = Decimal::from(self.liquidity.principal_amount);
+        self.collateral.exchange_rate(principal)
     }
 
     // Check if host fee receiver the check_receiver is
@@ -246,6 +496,8 @@ pub struct ReserveLiquidity {
     pub market_price: Decimal,
     /// unclaimed fee by reserve owner
     pub owner_unclaimed: Decimal,
+    /// Reserve liquidity deposited as principal, redeemable by depositors
+    pub principal_amount: u64,
 }
 
 impl ReserveLiquidity {
@@ -264,6 +516,7 @@ impl ReserveLiquidity {
             cumulative_borrow_rate_wads: Decimal::one(),
             market_price: params.market_price,
             owner_unclaimed: Decimal::zero(),
+            principal_amount: 0,
         }
     }
 
@@ -288,6 +541,24 @@ impl ReserveLiquidity {
         Ok(())
     }
 
+    /// Add liquidity to principal amount
+    pub fn add_principal(&mut self, liquidity_amount: u64) -> ProgramResult {
+        self.principal_amount = self
+            .principal_amount
+            .checked_add(liquidity_amount)
+            .ok_or(PoolingError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Remove liquidity from principal amount
+    pub fn sub_principal(&mut self, liquidity_amount: u64) -> ProgramResult {
+        self.principal_amount = self
+            .principal_amount
+            .checked_sub(liquidity_amount)
+            .ok_or(PoolingError::MathOverflow)?;
+        Ok(())
+    }
+
     /// Remove liquidity from available amount
     pub fn withdraw(&mut self, liquidity_amount: u64) -> ProgramResult {
         if liquidity_amount > self.liquidity_amount()? {
@@ -324,6 +595,20 @@ impl ReserveLiquidity {
             )
         }
     }
+    /// Remove the whole fees reserved for the reserve owner from available liquidity,
+    /// up to the liquidity available
+    pub fn withdraw_owner_unclaimed(&mut self) -> Result<u64, ProgramError> {
+        let fee_amount = self
+            .owner_unclaimed
+            .try_floor_u64()?
+            .min(self.available_amount);
+        self.owner_unclaimed = self.owner_unclaimed.try_sub(Decimal::from(fee_amount))?;
+        self.available_amount = self
+            .available_amount
+            .checked_sub(fee_amount)
+            .ok_or(PoolingError::MathOverflow)?;
+        Ok(fee_amount)
+    }
     pub fn decimal_liquidity_amount(&self) -> Result<Decimal, ProgramError> {
         if Decimal::from(self.available_amount).lt(&self.owner_unclaimed) {
             Ok(Decimal::zero())
@@ -512,6 +797,9 @@ impl From<CollateralExchangeRate> for Rate {
     }
 }
 
+/// Kink utilization rate of 100%, the kink utilization rate being in basis points
+pub const MAX_KINK_UTIL_RATE: u64 = 10_000;
+
 #[derive(Clone, Debug, Default, PartialEq, Copy)]
 pub struct Lottery {
     /// Supply address of un-collaterized LToken
@@ -523,7 +811,7 @@ pub struct Lottery {
 
     /// Amount of mine token for this reserve per slot
     pub total_mining_speed: u64,
-    /// the critical liquidity utilization rate at which the mine distribution curve jumps
+    /// the critical liquidity utilization rate at which the mine distribution curve jumps, in basis points
     pub kink_util_rate: u64,
 }
 
@@ -545,6 +833,26 @@ impl Lottery {
     }
 }
 
+/// External lending program the pool supplies liquidity to
+#[derive(Clone, Debug, Default, PartialEq, Copy)]
+pub struct YieldSource {
+    /// Lending program id, default pubkey when the pool keeps all its liquidity
+    pub program_id: Pubkey,
+    /// Lending reserve the liquidity is supplied to
+    pub reserve: Pubkey,
+    /// Pool token account holding the reserve shares
+    pub share_supply: Pubkey,
+    /// Liquidity redeemable for the reserve shares, as of the last refresh
+    pub redeemable_amount: u64,
+}
+
+impl YieldSource {
+    /// Check if the pool supplies liquidity to a lending program
+    pub fn is_enabled(&self) -> bool {
+        self.program_id != Pubkey::default()
+    }
+}
+
 /// Initialize a reserve
 pub struct InitPoolParams {
     /// Last slot when supply and rates updated
@@ -559,12 +867,163 @@ pub struct InitPoolParams {
     pub config: PoolConfig,
     /// Reserve bonus
     pub lottery: Lottery,
+    /// Prize draw state
+    pub prize: Prize,
+}
+
+/// Pauses deposits into the pool
+pub const PAUSE_DEPOSIT: u8 = 1 << 0;
+/// Pauses redeeming collateral and withdrawing ticket deposits
+pub const PAUSE_REDEEM: u8 = 1 << 1;
+/// Pauses starting and completing draws
+pub const PAUSE_DRAW: u8 = 1 << 2;
+/// Pauses claiming prizes
+pub const PAUSE_CLAIM: u8 = 1 << 3;
+/// Pauses claiming mined tokens
+pub const PAUSE_MINING_CLAIM: u8 = 1 << 4;
+/// Every operation which can be paused
+pub const PAUSE_ALL: u8 =
+    PAUSE_DEPOSIT | PAUSE_REDEEM | PAUSE_DRAW | PAUSE_CLAIM | PAUSE_MINING_CLAIM;
+
+/// How the mining speed of a pool is split between its lend and borrow sides
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MineDistribution {
+    /// Split along the utilization rate, jumping at the kink utilization rate
+    Kinked,
+    /// Half of the mining speed for each side
+    Flat,
+    /// The whole mining speed for the lend side
+    DepositOnly,
+}
+
+impl Default for MineDistribution {
+    fn default() -> Self {
+        MineDistribution::Kinked
+    }
+}
+
+impl TryFrom<u8> for MineDistribution {
+    type Error = ProgramError;
+
+    fn try_from(distribution: u8) -> Result<Self, Self::Error> {
+        match distribution {
+            0 => Ok(Self::Kinked),
+            1 => Ok(Self::Flat),
+            2 => Ok(Self::DepositOnly),
+            _ => {
+                msg!("Mine distribution {} is unknown", distribution);
+                Err(PoolingError::InvalidConfig.into())
+            }
+        }
+    }
 }
 
 /// Reserve configuration values
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct PoolConfig {
-    pub deposit_paused: bool,
+    /// Bitmask of the paused operations, made of `PAUSE_*` flags
+    pub paused: u8,
+    /// Split of the mining speed between lend and borrow sides
+    pub mine_distribution: MineDistribution,
+    /// Fee on ticket withdrawals right after a deposit or a draw, expressed as a Wad.
+    /// Decays linearly to zero over `exit_fee_decay_slots`.
+    pub exit_fee_wad: u64,
+    /// Number of slots after a deposit or a draw during which withdrawals pay the exit fee
+    pub exit_fee_decay_slots: u64,
+    /// Share of each prize kept as protocol fee before the draw, expressed as a Wad
+    pub protocol_fee_wad: u64,
+    /// Optimal utilization rate, as a percentage
+    pub optimal_utilization_rate: u8,
+    /// Min borrow APY
+    pub min_borrow_rate: u8,
+    /// Optimal (utilization) borrow APY
+    pub optimal_borrow_rate: u8,
+    /// Max borrow APY
+    pub max_borrow_rate: u8,
+    /// Share of the accrued interest kept for the reserve owner, expressed as a Wad
+    pub reserve_owner_fee_wad: u64,
+}
+
+impl PoolConfig {
+    /// Check that only known operations are paused, the fees are at most 100% and the
+    /// borrow rates increase with utilization
+    pub fn validate(&self) -> ProgramResult {
+        if self.paused & !PAUSE_ALL != 0 {
+            msg!("Pause flags {:#b} are unknown", self.paused & !PAUSE_ALL);
+            return Err(PoolingError::InvalidConfig.into());
+        }
+        if self.exit_fee_wad > WAD {
+            msg!("Exit fee must be in range [0, 1_000_000_000_000_000_000]");
+            return Err(PoolingError::InvalidConfig.into());
+        }
+        if self.protocol_fee_wad > WAD {
+            msg!("Protocol fee must be in range [0, 1_000_000_000_000_000_000]");
+            return Err(PoolingError::InvalidConfig.into());
+        }
+        if self.optimal_utilization_rate > 100 {
+            msg!("Optimal utilization rate must be in range [0, 100]");
+            return Err(PoolingError::InvalidConfig.into());
+        }
+        if self.optimal_borrow_rate < self.min_borrow_rate {
+            msg!("Optimal borrow rate must be >= min borrow rate");
+            return Err(PoolingError::InvalidConfig.into());
+        }
+        if self.optimal_borrow_rate > self.max_borrow_rate {
+            msg!("Optimal borrow rate must be <= max borrow rate");
+            return Err(PoolingError::InvalidConfig.into());
+        }
+        if self.reserve_owner_fee_wad > WAD {
+            msg!("Reserve owner fee must be in range [0, 1_000_000_000_000_000_000]");
+            return Err(PoolingError::InvalidConfig.into());
+        }
+        Ok(())
+    }
+
+    /// Exit fee rate of a withdrawal at `slot`, decaying linearly from the exit fee at `start_slot`
+    /// down to zero once `exit_fee_decay_slots` have elapsed
+    pub fn exit_fee_rate(&self, start_slot: Slot, slot: Slot) -> Result<Rate, ProgramError> {
+        let slots_elapsed = slot.saturating_sub(start_slot);
+        if slots_elapsed >= self.exit_fee_decay_slots {
+            return Ok(Rate::zero());
+        }
+        Rate::from_scaled_val(self.exit_fee_wad)
+            .try_mul(self.exit_fee_decay_slots - slots_elapsed)?
+            .try_div(self.exit_fee_decay_slots)
+    }
+
+    /// Check if any of `operations` is paused
+    pub fn is_paused(&self, operations: u8) -> bool {
+        self.paused & operations != 0
+    }
+
+    /// Pause `operations` on top of the already paused ones
+    pub fn pause(&mut self, operations: u8) -> ProgramResult {
+        if operations == 0 {
+            msg!("No operation to pause");
+            return Err(PoolingError::InvalidConfig.into());
+        }
+        let mut config = self.clone();
+        config.paused |= operations;
+        config.validate()?;
+        *self = config;
+        Ok(())
+    }
+
+    /// Fail with the error matching `operation` if it is paused
+    pub fn check_paused(&self, operation: u8) -> ProgramResult {
+        if !self.is_paused(operation) {
+            return Ok(());
+        }
+        msg!("Operation {:#b} is paused on this pool", operation);
+        let error = match operation {
+            PAUSE_DEPOSIT => PoolingError::DepositPaused,
+            PAUSE_REDEEM => PoolingError::RedeemPaused,
+            PAUSE_DRAW => PoolingError::DrawPaused,
+            PAUSE_CLAIM => PoolingError::ClaimPaused,
+            _ => PoolingError::MiningClaimPaused,
+        };
+        Err(error.into())
+    }
 }
 
 /// Calculate fees exlusive or inclusive of an amount
@@ -583,11 +1042,25 @@ impl IsInitialized for Pool {
     }
 }
 
-const RESERVE_LEN: usize = 646;
+const RESERVE_LEN: usize = 1024;
+const PRIZE_TIER_LEN: usize = 2; // 1 + 1
 
 impl Pack for Pool {
     const LEN: usize = RESERVE_LEN;
 
+    /// Packs a pool into a byte buffer, rejecting more prize tiers than the layout holds
+    fn pack(src: Self, dst: &mut [u8]) -> Result<(), ProgramError> {
+        if src.prize.tiers.len() > MAX_PRIZE_TIERS {
+            msg!("Prize cannot have more than {} tiers", MAX_PRIZE_TIERS);
+            return Err(PoolingError::InvalidConfig.into());
+        }
+        if dst.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        src.pack_into_slice(dst);
+        Ok(())
+    }
+
     // @TODO: break this up by reserve / liquidity / collateral / config https://git.io/JOCca
     fn pack_into_slice(&self, output: &mut [u8]) {
         let output = array_mut_ref![output, 0, RESERVE_LEN];
@@ -611,13 +1084,58 @@ impl Pack for Pool {
             collateral_mint_pubkey,
             collateral_mint_total_supply,
             collateral_supply_pubkey,
-            deposit_paused,
+            paused,
             un_coll_supply_account,
             l_token_mining_index,
             borrow_mining_index,
             total_mining_speed,
             kink_util_rate,
             reentry_lock,
+            last_draw_slot,
+            last_winner,
+            last_prize_amount,
+            prize_period_slots,
+            prize_period_start_slot,
+            draw_count,
+            draw_pending,
+            pending_commitment,
+            pending_commit_slot,
+            prize_tiers_len,
+            prize_tiers_flat,
+            liquidity_principal_amount,
+            prize_unclaimed_amount,
+            mine_distribution,
+            ticket_balance,
+            ticket_twab_period_start_slot,
+            ticket_twab_last_slot,
+            ticket_twab_cumulative_balance,
+            exit_fee_wad,
+            exit_fee_decay_slots,
+            protocol_fee_wad,
+            sponsored_amount,
+            deferred_contributions_flat,
+            yield_program_id,
+            yield_reserve,
+            yield_share_supply,
+            yield_redeemable_amount,
+            optimal_utilization_rate,
+            min_borrow_rate,
+            optimal_borrow_rate,
+            max_borrow_rate,
+            reserve_owner_fee_wad,
+            reveal_slot,
+            randomness_account,
+            prize_taxed_amount,
+            indexed_balance,
+            indexed_tickets,
+            awarding,
+            draw_seed,
+            draw_prize_amount,
+            draw_picks,
+            winners_awarded,
+            awarded_amount,
+            draw_winner,
+            ticket_twab_next_cumulative_balance,
             _padding,
         ) = mut_array_refs![
                output,
@@ -639,14 +1157,59 @@ impl Pack for Pool {
             PUBKEY_BYTES,// collateral_mint_pubkey 276
             8,// collateral_mint_total_supply 284
             PUBKEY_BYTES,// collateral_supply_pubkey 316
-            1,// deposit_paused 317
+            1,// paused 317
             PUBKEY_BYTES,// un_coll_supply_account 349
             16,// l_token_mining_index 365
             16,// borrow_mining_index 381
             8,// total_mining_speed 389
             8,// kink_util_rate 397
             1, // reentry_lock  398
-            248 //_padding 646
+            8, // last_draw_slot 406
+            PUBKEY_BYTES, // last_winner 438
+            8, // last_prize_amount 446
+            8, // prize_period_slots 454
+            8, // prize_period_start_slot 462
+            8, // draw_count 470
+            1, // draw_pending 471
+            32, // pending_commitment 503
+            8, // pending_commit_slot 511
+            1, // prize_tiers_len 512
+            PRIZE_TIER_LEN * MAX_PRIZE_TIERS, // prize_tiers 520
+            8, // liquidity_principal_amount 528
+            8, // prize_unclaimed_amount 536
+            1, // mine_distribution 537
+            8, // ticket_balance 545
+            8, // ticket_twab_period_start_slot 553
+            8, // ticket_twab_last_slot 561
+            16, // ticket_twab_cumulative_balance 577
+            8, // exit_fee_wad 585
+            8, // exit_fee_decay_slots 593
+            8, // protocol_fee_wad 601
+            8, // sponsored_amount 609
+            8 * (MAX_CONTRIBUTION_DRAWS - 1), // deferred_contributions 633
+            PUBKEY_BYTES, // yield_program_id 665
+            PUBKEY_BYTES, // yield_reserve 697
+            PUBKEY_BYTES, // yield_share_supply 729
+            8, // yield_redeemable_amount 737
+            1, // optimal_utilization_rate 738
+            1, // min_borrow_rate 739
+            1, // optimal_borrow_rate 740
+            1, // max_borrow_rate 741
+            8, // reserve_owner_fee_wad 749
+            8, // reveal_slot 757
+            PUBKEY_BYTES, // randomness_account 789
+            8, // prize_taxed_amount 797
+            16, // indexed_balance 813
+            8, // indexed_tickets 821
+            1, // awarding 822
+            32, // draw_seed 854
+            8, // draw_prize_amount 862
+            8, // draw_picks 870
+            8, // winners_awarded 878
+            8, // awarded_amount 886
+            PUBKEY_BYTES, // draw_winner 918
+            16, // ticket_twab_next_cumulative_balance 934
+            90 //_padding 1024
         ];
 
         // reserve
@@ -673,13 +1236,14 @@ impl Pack for Pool {
             liquidity_cumulative_borrow_rate_wads,
         );
         pack_decimal(self.liquidity.market_price, liquidity_market_price);
+        *liquidity_principal_amount = self.liquidity.principal_amount.to_le_bytes();
 
         // collateral
         collateral_mint_pubkey.copy_from_slice(self.collateral.mint_pubkey.as_ref());
         *collateral_mint_total_supply = self.collateral.mint_total_supply.to_le_bytes();
         collateral_supply_pubkey.copy_from_slice(self.collateral.supply_pubkey.as_ref());
 
-        pack_bool(self.config.deposit_paused, deposit_paused);
+        *paused = self.config.paused.to_le_bytes();
 
         un_coll_supply_account.copy_from_slice(self.lottery.un_coll_supply_account.as_ref());
         pack_decimal(self.lottery.l_token_mining_index, l_token_mining_index);
@@ -689,6 +1253,68 @@ impl Pack for Pool {
         *kink_util_rate = self.lottery.kink_util_rate.to_le_bytes();
         pack_decimal(self.liquidity.owner_unclaimed, owner_unclaimed);
         pack_bool(self.reentry_lock, reentry_lock);
+
+        // prize
+        *last_draw_slot = self.prize.last_draw_slot.to_le_bytes();
+        last_winner.copy_from_slice(self.prize.last_winner.as_ref());
+        *last_prize_amount = self.prize.last_prize_amount.to_le_bytes();
+        *prize_unclaimed_amount = self.prize.unclaimed_amount.to_le_bytes();
+        *mine_distribution = (self.config.mine_distribution as u8).to_le_bytes();
+        *ticket_balance = self.ticket_balance.to_le_bytes();
+        *ticket_twab_period_start_slot = self.ticket_twab.period_start_slot.to_le_bytes();
+        *ticket_twab_last_slot = self.ticket_twab.last_slot.to_le_bytes();
+        *ticket_twab_cumulative_balance = self.ticket_twab.cumulative_balance.to_le_bytes();
+        *exit_fee_wad = self.config.exit_fee_wad.to_le_bytes();
+        *exit_fee_decay_slots = self.config.exit_fee_decay_slots.to_le_bytes();
+        *protocol_fee_wad = self.config.protocol_fee_wad.to_le_bytes();
+        *sponsored_amount = self.sponsored_amount.to_le_bytes();
+        for (deferred_flat, deferred) in deferred_contributions_flat
+            .chunks_exact_mut(8)
+            .zip(self.prize.deferred_contributions.iter())
+        {
+            deferred_flat.copy_from_slice(&deferred.to_le_bytes());
+        }
+        yield_program_id.copy_from_slice(self.yield_source.program_id.as_ref());
+        yield_reserve.copy_from_slice(self.yield_source.reserve.as_ref());
+        yield_share_supply.copy_from_slice(self.yield_source.share_supply.as_ref());
+        *yield_redeemable_amount = self.yield_source.redeemable_amount.to_le_bytes();
+        *optimal_utilization_rate = self.config.optimal_utilization_rate.to_le_bytes();
+        *min_borrow_rate = self.config.min_borrow_rate.to_le_bytes();
+        *optimal_borrow_rate = self.config.optimal_borrow_rate.to_le_bytes();
+        *max_borrow_rate = self.config.max_borrow_rate.to_le_bytes();
+        *reserve_owner_fee_wad = self.config.reserve_owner_fee_wad.to_le_bytes();
+        *prize_period_slots = self.prize.period_slots.to_le_bytes();
+        *prize_period_start_slot = self.prize.period_start_slot.to_le_bytes();
+        *draw_count = self.prize.draw_count.to_le_bytes();
+        pack_bool(self.prize.draw_pending, draw_pending);
+        *pending_commitment = self.prize.pending_commitment;
+        *pending_commit_slot = self.prize.pending_commit_slot.to_le_bytes();
+        *reveal_slot = self.prize.reveal_slot.to_le_bytes();
+        randomness_account.copy_from_slice(self.prize.randomness_account.as_ref());
+        *prize_taxed_amount = self.prize.taxed_amount.to_le_bytes();
+        *indexed_balance = self.prize.indexed_balance.to_le_bytes();
+        *indexed_tickets = self.prize.indexed_tickets.to_le_bytes();
+        pack_bool(self.prize.awarding, awarding);
+        *draw_seed = self.prize.draw_seed;
+        *draw_prize_amount = self.prize.draw_prize_amount.to_le_bytes();
+        *draw_picks = self.prize.draw_picks.to_le_bytes();
+        *winners_awarded = self.prize.winners_awarded.to_le_bytes();
+        *awarded_amount = self.prize.awarded_amount.to_le_bytes();
+        draw_winner.copy_from_slice(self.prize.draw_winner.as_ref());
+        *ticket_twab_next_cumulative_balance =
+            self.ticket_twab.next_cumulative_balance.to_le_bytes();
+        // `pack` rejects pools with more tiers, never packed past the tiers array
+        let tiers = &self.prize.tiers[..self.prize.tiers.len().min(MAX_PRIZE_TIERS)];
+        *prize_tiers_len = (tiers.len() as u8).to_le_bytes();
+        let mut offset = 0;
+        for tier in tiers {
+            let tier_flat = array_mut_ref![prize_tiers_flat, offset, PRIZE_TIER_LEN];
+            #[allow(clippy::ptr_offset_with_cast)]
+                let (percent, winner_count) = mut_array_refs![tier_flat, 1, 1];
+            *percent = tier.percent.to_le_bytes();
+            *winner_count = tier.winner_count.to_le_bytes();
+            offset += PRIZE_TIER_LEN;
+        }
     }
 
     /// Unpacks a byte buffer into a [ReserveInfo](struct.ReserveInfo.html).
@@ -714,13 +1340,58 @@ impl Pack for Pool {
             collateral_mint_pubkey,
             collateral_mint_total_supply,
             collateral_supply_pubkey,
-            deposit_paused,
+            paused,
             un_coll_supply_account,
             l_token_mining_index,
             borrow_mining_index,
             total_mining_speed,
             kink_util_rate,
             reentry_lock,
+            last_draw_slot,
+            last_winner,
+            last_prize_amount,
+            prize_period_slots,
+            prize_period_start_slot,
+            draw_count,
+            draw_pending,
+            pending_commitment,
+            pending_commit_slot,
+            prize_tiers_len,
+            prize_tiers_flat,
+            liquidity_principal_amount,
+            prize_unclaimed_amount,
+            mine_distribution,
+            ticket_balance,
+            ticket_twab_period_start_slot,
+            ticket_twab_last_slot,
+            ticket_twab_cumulative_balance,
+            exit_fee_wad,
+            exit_fee_decay_slots,
+            protocol_fee_wad,
+            sponsored_amount,
+            deferred_contributions_flat,
+            yield_program_id,
+            yield_reserve,
+            yield_share_supply,
+            yield_redeemable_amount,
+            optimal_utilization_rate,
+            min_borrow_rate,
+            optimal_borrow_rate,
+            max_borrow_rate,
+            reserve_owner_fee_wad,
+            reveal_slot,
+            randomness_account,
+            prize_taxed_amount,
+            indexed_balance,
+            indexed_tickets,
+            awarding,
+            draw_seed,
+            draw_prize_amount,
+            draw_picks,
+            winners_awarded,
+            awarded_amount,
+            draw_winner,
+            ticket_twab_next_cumulative_balance,
             _padding,
         ) = array_refs![
             input,
@@ -749,7 +1420,52 @@ impl Pack for Pool {
             8,
             8,
             1,
-            248
+            8,
+            PUBKEY_BYTES,
+            8,
+            8,
+            8,
+            8,
+            1,
+            32,
+            8,
+            1,
+            PRIZE_TIER_LEN * MAX_PRIZE_TIERS,
+            8,
+            8,
+            1,
+            8,
+            8,
+            8,
+            16,
+            8,
+            8,
+            8,
+            8,
+            8 * (MAX_CONTRIBUTION_DRAWS - 1),
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            8,
+            1,
+            1,
+            1,
+            1,
+            8,
+            8,
+            PUBKEY_BYTES,
+            8,
+            16,
+            8,
+            1,
+            32,
+            8,
+            8,
+            8,
+            8,
+            PUBKEY_BYTES,
+            16,
+            90
         ];
 
         let version = u8::from_le_bytes(*version);
@@ -757,6 +1473,35 @@ impl Pack for Pool {
             msg!("Reserve version does not match pooling program version");
             return Err(ProgramError::InvalidAccountData);
         }
+        if version != UNINITIALIZED_VERSION && version < MIN_POOL_VERSION {
+            msg!("Pool version {} predates the current pool layout", version);
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let prize_tiers_len = u8::from_le_bytes(*prize_tiers_len) as usize;
+        if prize_tiers_len > MAX_PRIZE_TIERS {
+            msg!("Prize tiers cannot be unpacked");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut prize_tiers = Vec::with_capacity(prize_tiers_len);
+        let mut offset = 0;
+        for _ in 0..prize_tiers_len {
+            let tier_flat = array_ref![prize_tiers_flat, offset, PRIZE_TIER_LEN];
+            #[allow(clippy::ptr_offset_with_cast)]
+                let (percent, winner_count) = array_refs![tier_flat, 1, 1];
+            prize_tiers.push(PrizeTier {
+                percent: u8::from_le_bytes(*percent),
+                winner_count: u8::from_le_bytes(*winner_count),
+            });
+            offset += PRIZE_TIER_LEN;
+        }
+        let mut deferred_contributions = [0u64; MAX_CONTRIBUTION_DRAWS - 1];
+        for (deferred, deferred_flat) in deferred_contributions
+            .iter_mut()
+            .zip(deferred_contributions_flat.chunks_exact(8))
+        {
+            *deferred = u64::from_le_bytes(deferred_flat.try_into().unwrap());
+        }
 
         Ok(Self {
             version,
@@ -777,6 +1522,7 @@ impl Pack for Pool {
                 cumulative_borrow_rate_wads: unpack_decimal(liquidity_cumulative_borrow_rate_wads),
                 market_price: unpack_decimal(liquidity_market_price),
                 owner_unclaimed: unpack_decimal(owner_unclaimed),
+                principal_amount: u64::from_le_bytes(*liquidity_principal_amount),
             },
             collateral: ReserveCollateral {
                 mint_pubkey: Pubkey::new_from_array(*collateral_mint_pubkey),
@@ -784,7 +1530,16 @@ impl Pack for Pool {
                 supply_pubkey: Pubkey::new_from_array(*collateral_supply_pubkey),
             },
             config: PoolConfig {
-                deposit_paused: unpack_bool(deposit_paused)?,
+                paused: u8::from_le_bytes(*paused),
+                mine_distribution: MineDistribution::try_from(u8::from_le_bytes(*mine_distribution))?,
+                exit_fee_wad: u64::from_le_bytes(*exit_fee_wad),
+                exit_fee_decay_slots: u64::from_le_bytes(*exit_fee_decay_slots),
+                protocol_fee_wad: u64::from_le_bytes(*protocol_fee_wad),
+                optimal_utilization_rate: u8::from_le_bytes(*optimal_utilization_rate),
+                min_borrow_rate: u8::from_le_bytes(*min_borrow_rate),
+                optimal_borrow_rate: u8::from_le_bytes(*optimal_borrow_rate),
+                max_borrow_rate: u8::from_le_bytes(*max_borrow_rate),
+                reserve_owner_fee_wad: u64::from_le_bytes(*reserve_owner_fee_wad),
             },
             lottery: Lottery {
                 un_coll_supply_account: Pubkey::new_from_array(*un_coll_supply_account),
@@ -794,6 +1549,46 @@ impl Pack for Pool {
                 kink_util_rate: u64::from_le_bytes(*kink_util_rate),
             },
             reentry_lock: unpack_bool(reentry_lock)?,
+            prize: Prize {
+                last_draw_slot: u64::from_le_bytes(*last_draw_slot),
+                last_winner: Pubkey::new_from_array(*last_winner),
+                last_prize_amount: u64::from_le_bytes(*last_prize_amount),
+                unclaimed_amount: u64::from_le_bytes(*prize_unclaimed_amount),
+                taxed_amount: u64::from_le_bytes(*prize_taxed_amount),
+                period_slots: u64::from_le_bytes(*prize_period_slots),
+                period_start_slot: u64::from_le_bytes(*prize_period_start_slot),
+                draw_count: u64::from_le_bytes(*draw_count),
+                draw_pending: unpack_bool(draw_pending)?,
+                pending_commitment: *pending_commitment,
+                pending_commit_slot: u64::from_le_bytes(*pending_commit_slot),
+                reveal_slot: u64::from_le_bytes(*reveal_slot),
+                randomness_account: Pubkey::new_from_array(*randomness_account),
+                tiers: prize_tiers,
+                deferred_contributions,
+                indexed_balance: u128::from_le_bytes(*indexed_balance),
+                indexed_tickets: u64::from_le_bytes(*indexed_tickets),
+                awarding: unpack_bool(awarding)?,
+                draw_seed: *draw_seed,
+                draw_prize_amount: u64::from_le_bytes(*draw_prize_amount),
+                draw_picks: u64::from_le_bytes(*draw_picks),
+                winners_awarded: u64::from_le_bytes(*winners_awarded),
+                awarded_amount: u64::from_le_bytes(*awarded_amount),
+                draw_winner: Pubkey::new_from_array(*draw_winner),
+            },
+            ticket_balance: u64::from_le_bytes(*ticket_balance),
+            ticket_twab: Twab {
+                period_start_slot: u64::from_le_bytes(*ticket_twab_period_start_slot),
+                last_slot: u64::from_le_bytes(*ticket_twab_last_slot),
+                cumulative_balance: u128::from_le_bytes(*ticket_twab_cumulative_balance),
+                next_cumulative_balance: u128::from_le_bytes(*ticket_twab_next_cumulative_balance),
+            },
+            sponsored_amount: u64::from_le_bytes(*sponsored_amount),
+            yield_source: YieldSource {
+                program_id: Pubkey::new_from_array(*yield_program_id),
+                reserve: Pubkey::new_from_array(*yield_reserve),
+                share_supply: Pubkey::new_from_array(*yield_share_supply),
+                redeemable_amount: u64::from_le_bytes(*yield_redeemable_amount),
+            },
         })
     }
 }
@@ -818,605 +1613,859 @@ mod test {
         }
     }
 
-    // Creates rates (threshold, ltv) where 2 <= threshold <= 100 and threshold <= ltv <= 1,000%
+    // Creates a range of reasonable collateral exchange rates
     prop_compose! {
-        fn unhealthy_rates()(threshold in 2..=100u8)(
-            ltv_rate in threshold as u64..=1000u64,
-            threshold in Just(threshold),
-        ) -> (Decimal, u8) {
-            (Decimal::from_scaled_val(ltv_rate as u128 * PERCENT_SCALER as u128), threshold)
+        fn collateral_exchange_rate_range()(percent in 1..=500u64) -> CollateralExchangeRate {
+            CollateralExchangeRate(Rate::from_scaled_val(percent * PERCENT_SCALER))
         }
     }
 
-    // Creates a range of reasonable token conversion rates
-    prop_compose! {
-        fn token_conversion_rate()(
-            conversion_rate in 1..=u16::MAX,
-            invert_conversion_rate: bool,
-        ) -> Decimal {
-            let conversion_rate = Decimal::from(conversion_rate as u64);
-            if invert_conversion_rate {
-                Decimal::one().try_div(conversion_rate).unwrap()
-            } else {
-                conversion_rate
-            }
-        }
+    #[test]
+    fn redeem_principal_only() {
+        let mut pool = Pool::default();
+        let collateral_amount = pool.deposit_liquidity(1000).unwrap();
+        assert_eq!(pool.liquidity.principal_amount, 1000);
+        assert_eq!(pool.prize_available().unwrap(), 0);
+
+        // yield accrues on top of the principal
+        pool.liquidity.deposit(100).unwrap();
+        assert_eq!(pool.prize_available().unwrap(), 100);
+
+        let liquidity_amount = pool.redeem_collateral(collateral_amount / 2).unwrap();
+        assert_eq!(liquidity_amount, 500);
+        assert_eq!(pool.liquidity.principal_amount, 500);
+        assert_eq!(pool.prize_available().unwrap(), 100);
     }
 
-    // Creates a range of reasonable collateral exchange rates
-    prop_compose! {
-        fn collateral_exchange_rate_range()(percent in 1..=500u64) -> CollateralExchangeRate {
-            CollateralExchangeRate(Rate::from_scaled_val(percent * PERCENT_SCALER))
+    #[test]
+    fn award_and_claim_prize() {
+        let mut pool = Pool::default();
+        pool.deposit_liquidity(1000).unwrap();
+        pool.liquidity.deposit(100).unwrap();
+        assert_eq!(
+            pool.award_prize(101),
+            Err(PoolingError::InsufficientLiquidity.into())
+        );
+
+        pool.award_prize(60).unwrap();
+        pool.award_prize(30).unwrap();
+        assert_eq!(pool.prize.unclaimed_amount, 90);
+        assert_eq!(pool.prize_available().unwrap(), 10);
+
+        pool.claim_prize(60).unwrap();
+        assert_eq!(pool.liquidity.available_amount, 1040);
+        assert_eq!(pool.prize_available().unwrap(), 10);
+
+        // compounded prizes become principal
+        let collateral_amount = pool.compound_prize(30).unwrap();
+        assert_eq!(pool.prize.unclaimed_amount, 0);
+        assert_eq!(pool.liquidity.principal_amount, 1030);
+        assert_eq!(pool.prize_available().unwrap(), 10);
+        assert_eq!(pool.redeem_collateral(collateral_amount).unwrap(), 30);
+        assert_eq!(pool.claim_prize(1), Err(PoolingError::MathOverflow.into()));
+    }
+
+    #[test]
+    fn compound_prize_supplied_to_yield_source() {
+        let mut pool = Pool::default();
+        pool.deposit_liquidity(1000).unwrap();
+        pool.liquidity.deposit(100).unwrap();
+        pool.award_prize(80).unwrap();
+        pool.supply_yield_source(1050).unwrap();
+        assert_eq!(pool.liquidity.available_amount, 50);
+
+        let collateral_amount = pool.compound_prize(80).unwrap();
+        assert_eq!(collateral_amount, 80);
+        assert_eq!(pool.liquidity.available_amount, 50);
+        assert_eq!(pool.prize.unclaimed_amount, 0);
+        assert_eq!(pool.liquidity.principal_amount, 1080);
+        assert_eq!(pool.collateral.mint_total_supply, 1080);
+        assert_eq!(pool.prize_available().unwrap(), 20);
+    }
+
+    #[test]
+    fn update_mining_speed_accrues_first() {
+        let mut pool = Pool {
+            last_update: LastUpdate::new(0),
+            liquidity: ReserveLiquidity {
+                cumulative_borrow_rate_wads: Decimal::one(),
+                ..ReserveLiquidity::default()
+            },
+            collateral: ReserveCollateral {
+                mint_total_supply: 100,
+                ..ReserveCollateral::default()
+            },
+            lottery: Lottery {
+                total_mining_speed: 10,
+                ..Lottery::default()
+            },
+            ..Pool::default()
+        };
+        pool.update_mining_speed(20, 30, 50).unwrap();
+        let accrued_index = pool.lottery.l_token_mining_index;
+        assert!(accrued_index > Decimal::zero());
+        assert_eq!(pool.last_update.slot, 20);
+        assert!(pool.last_update.stale);
+        assert_eq!(pool.lottery.total_mining_speed, 30);
+        assert_eq!(pool.lottery.kink_util_rate, 50);
+
+        // nothing more accrues in the same slot
+        pool.update_mining_speed(20, 40, 50).unwrap();
+        assert_eq!(pool.lottery.l_token_mining_index, accrued_index);
+    }
+
+    #[test]
+    fn pause_flags() {
+        let config = PoolConfig {
+            paused: PAUSE_DEPOSIT | PAUSE_CLAIM,
+            ..PoolConfig::default()
+        };
+        assert!(config.validate().is_ok());
+        assert!(config.is_paused(PAUSE_DEPOSIT));
+        assert!(!config.is_paused(PAUSE_REDEEM));
+        assert_eq!(
+            config.check_paused(PAUSE_DEPOSIT),
+            Err(PoolingError::DepositPaused.into())
+        );
+        assert_eq!(
+            config.check_paused(PAUSE_CLAIM),
+            Err(PoolingError::ClaimPaused.into())
+        );
+        assert_eq!(config.check_paused(PAUSE_DRAW), Ok(()));
+        assert_eq!(config.check_paused(PAUSE_MINING_CLAIM), Ok(()));
+
+        let config = PoolConfig {
+            paused: PAUSE_ALL,
+            ..PoolConfig::default()
+        };
+        assert_eq!(
+            config.check_paused(PAUSE_REDEEM),
+            Err(PoolingError::RedeemPaused.into())
+        );
+        assert_eq!(
+            config.check_paused(PAUSE_MINING_CLAIM),
+            Err(PoolingError::MiningClaimPaused.into())
+        );
+        let config = PoolConfig {
+            paused: 1 << 7,
+            ..PoolConfig::default()
+        };
+        assert!(config.validate().is_err());
+
+        let mut config = PoolConfig {
+            paused: PAUSE_DEPOSIT,
+            ..PoolConfig::default()
+        };
+        config.pause(PAUSE_DRAW).unwrap();
+        assert_eq!(config.paused, PAUSE_DEPOSIT | PAUSE_DRAW);
+        assert!(config.pause(0).is_err());
+        assert!(config.pause(1 << 7).is_err());
+        assert_eq!(config.paused, PAUSE_DEPOSIT | PAUSE_DRAW);
+    }
+
+    #[test]
+    fn exit_fee_decays() {
+        let mut pool = Pool {
+            config: PoolConfig {
+                exit_fee_wad: WAD / 100,
+                exit_fee_decay_slots: 100,
+                ..PoolConfig::default()
+            },
+            ..Pool::default()
+        };
+        assert!(pool.config.validate().is_ok());
+        assert_eq!(pool.calculate_exit_fee(10_000, 50, 50).unwrap(), 100);
+        assert_eq!(pool.calculate_exit_fee(10_000, 50, 100).unwrap(), 50);
+        assert_eq!(pool.calculate_exit_fee(10_000, 50, 149).unwrap(), 1);
+        assert_eq!(pool.calculate_exit_fee(10_000, 50, 150).unwrap(), 0);
+        // rounded up
+        assert_eq!(pool.calculate_exit_fee(1, 50, 50).unwrap(), 1);
+
+        // the window restarts at every draw
+        pool.prize.last_draw_slot = 200;
+        assert_eq!(pool.calculate_exit_fee(10_000, 50, 250).unwrap(), 50);
+
+        pool.config.exit_fee_decay_slots = 0;
+        assert_eq!(pool.calculate_exit_fee(10_000, 50, 200).unwrap(), 0);
+        pool.config.exit_fee_wad = WAD + 1;
+        assert!(pool.config.validate().is_err());
+    }
+
+    #[test]
+    fn protocol_fee_skimmed_from_prize() {
+        let mut pool = Pool {
+            liquidity: ReserveLiquidity {
+                available_amount: 1_100,
+                principal_amount: 1_000,
+                ..ReserveLiquidity::default()
+            },
+            config: PoolConfig {
+                protocol_fee_wad: WAD / 10,
+                ..PoolConfig::default()
+            },
+            ..Pool::default()
+        };
+        assert_eq!(pool.skim_protocol_fee().unwrap(), 90);
+        assert_eq!(pool.liquidity.owner_unclaimed, Decimal::from(10u64));
+        assert_eq!(pool.liquidity.liquidity_amount().unwrap(), 1_090);
+
+        assert_eq!(pool.liquidity.withdraw_owner_unclaimed().unwrap(), 10);
+        assert_eq!(pool.liquidity.owner_unclaimed, Decimal::zero());
+        assert_eq!(pool.liquidity.available_amount, 1_090);
+        assert_eq!(pool.prize_available().unwrap(), 90);
+        assert_eq!(pool.liquidity.withdraw_owner_unclaimed().unwrap(), 0);
+    }
+
+    #[test]
+    fn leftover_prize_taxed_once() {
+        let mut pool = Pool {
+            liquidity: ReserveLiquidity {
+                available_amount: 1_100,
+                principal_amount: 1_000,
+                ..ReserveLiquidity::default()
+            },
+            config: PoolConfig {
+                protocol_fee_wad: WAD / 10,
+                ..PoolConfig::default()
+            },
+            ..Pool::default()
+        };
+        assert_eq!(pool.skim_protocol_fee().unwrap(), 90);
+        // the draw leaves 30 of the prize over
+        pool.award_prize(60).unwrap();
+        assert_eq!(pool.prize.taxed_amount, 30);
+
+        // only the yield accrued since is taxed at the next draw
+        pool.liquidity.deposit(100).unwrap();
+        assert_eq!(pool.skim_protocol_fee().unwrap(), 120);
+        assert_eq!(pool.liquidity.owner_unclaimed, Decimal::from(20u64));
+        assert_eq!(pool.prize.taxed_amount, 120);
+
+        // a period rolled over without tickets is not taxed twice either
+        assert_eq!(pool.skim_protocol_fee().unwrap(), 120);
+        assert_eq!(pool.liquidity.owner_unclaimed, Decimal::from(20u64));
+    }
+
+    #[test]
+    fn sponsorship_kept_out_of_ticket_balance() {
+        let mut pool = Pool::default();
+        pool.deposit_ticket_balance(100, 10).unwrap();
+        pool.deposit_sponsorship(50).unwrap();
+        assert_eq!(pool.ticket_balance, 100);
+        assert_eq!(pool.sponsored_amount, 50);
+
+        assert!(pool.withdraw_sponsorship(51).is_err());
+        pool.withdraw_sponsorship(50).unwrap();
+        assert_eq!(pool.sponsored_amount, 0);
+        assert_eq!(pool.ticket_balance, 100);
+    }
+
+    #[test]
+    fn contribution_released_draw_by_draw() {
+        let mut pool = Pool {
+            prize: Prize::new(InitPrizeParams {
+                current_slot: 0,
+                period_slots: 10,
+                tiers: vec![PrizeTier::grand_prize()],
+            }),
+            ..Pool::default()
+        };
+        pool.deposit_liquidity(1000).unwrap();
+        pool.contribute_to_prize(90, 3).unwrap();
+        assert_eq!(pool.liquidity.principal_amount, 1000);
+        assert_eq!(pool.collateral.mint_total_supply, 1000);
+        assert_eq!(pool.prize_available().unwrap(), 30);
+
+        pool.prize.record_draw(10, Pubkey::default(), 0).unwrap();
+        assert_eq!(pool.prize_available().unwrap(), 60);
+        pool.prize.record_draw(20, Pubkey::default(), 0).unwrap();
+        assert_eq!(pool.prize_available().unwrap(), 90);
+    }
+
+    #[test]
+    fn empty_first_period_rolls_over() {
+        let mut pool = Pool {
+            prize: Prize::new(InitPrizeParams {
+                current_slot: 0,
+                period_slots: 10,
+                tiers: vec![PrizeTier::grand_prize()],
+            }),
+            ..Pool::default()
+        };
+        pool.deposit_liquidity(1000).unwrap();
+        pool.collect_exit_fee(50).unwrap();
+        let period_balance = |pool: &Pool| {
+            pool.ticket_twab
+                .period_cumulative_balance(pool.ticket_balance, &pool.prize)
+                .unwrap()
+        };
+        assert_eq!(period_balance(&pool), 0);
+
+        pool.roll_over_prize(12).unwrap();
+        assert_eq!(pool.prize.period_start_slot, 10);
+        assert_eq!(pool.prize.draw_count, 1);
+        assert_eq!(pool.prize.last_winner, Pubkey::default());
+        assert_eq!(pool.prize_available().unwrap(), 50);
+        assert_eq!(pool.liquidity.owner_unclaimed, Decimal::zero());
+
+        // deposits after the roll over count toward the next period
+        pool.deposit_ticket_balance(100, 15).unwrap();
+        assert_eq!(period_balance(&pool), 500);
+        pool.prize.record_draw(20, Pubkey::default(), 50).unwrap();
+        assert_eq!(pool.prize.period_start_slot, 20);
+    }
+
+    #[test]
+    fn yield_source_counts_toward_prize() {
+        let mut pool = Pool::default();
+        pool.deposit_liquidity(1000).unwrap();
+        assert!(!pool.yield_source.is_enabled());
+
+        pool.supply_yield_source(800).unwrap();
+        assert_eq!(pool.liquidity.available_amount, 200);
+        assert_eq!(pool.prize_available().unwrap(), 0);
+        assert!(pool.supply_yield_source(201).is_err());
+
+        // interest earned by the lending reserve, read back on refresh
+        pool.yield_source.redeemable_amount = 850;
+        assert_eq!(pool.prize_available().unwrap(), 50);
+
+        pool.redeem_yield_source(425).unwrap();
+        assert_eq!(pool.liquidity.available_amount, 625);
+        assert_eq!(pool.yield_source.redeemable_amount, 425);
+        assert_eq!(pool.prize_available().unwrap(), 50);
+    }
+
+    #[test]
+    fn accrue_interest_along_rate_model() {
+        let mut pool = Pool {
+            liquidity: ReserveLiquidity {
+                available_amount: 500,
+                borrowed_amount_wads: Decimal::from(500u64),
+                cumulative_borrow_rate_wads: Decimal::one(),
+                ..ReserveLiquidity::default()
+            },
+            config: PoolConfig {
+                optimal_utilization_rate: 80,
+                min_borrow_rate: 0,
+                optimal_borrow_rate: 8,
+                max_borrow_rate: 50,
+                reserve_owner_fee_wad: WAD / 10,
+                ..PoolConfig::default()
+            },
+            ..Pool::default()
+        };
+        assert!(pool.config.validate().is_ok());
+        // 50% utilization, 5/8 of the way to the optimal borrow rate
+        assert_eq!(pool.current_borrow_rate().unwrap(), Rate::from_percent(5));
+
+        pool.accrue_interest(0).unwrap();
+        assert_eq!(pool.liquidity.cumulative_borrow_rate_wads, Decimal::one());
+
+        pool.accrue_interest(SLOTS_PER_YEAR).unwrap();
+        assert!(pool.liquidity.cumulative_borrow_rate_wads > Decimal::one());
+        assert!(pool.liquidity.borrowed_amount_wads > Decimal::from(525u64));
+        assert!(pool.liquidity.owner_unclaimed > Decimal::from(2u64));
+        assert!(pool.liquidity.owner_unclaimed < Decimal::from(3u64));
+
+        // above the optimal utilization rate, the rate rises toward the max borrow rate
+        pool.liquidity.borrowed_amount_wads = Decimal::from(9_000u64);
+        pool.liquidity.available_amount = 1_000;
+        pool.liquidity.owner_unclaimed = Decimal::zero();
+        assert_eq!(pool.current_borrow_rate().unwrap(), Rate::from_percent(29));
+
+        pool.config.max_borrow_rate = 7;
+        assert!(pool.config.validate().is_err());
+    }
+
+    fn mine_ratio_pool(
+        total_liquidity: u64,
+        borrowed_percent: u64,
+        kink_util_rate: u64,
+        mine_distribution: MineDistribution,
+    ) -> Pool {
+        let borrowed_amount_wads = Decimal::from(total_liquidity)
+            .try_mul(Rate::from_scaled_val(borrowed_percent))
+            .unwrap();
+        Pool {
+            liquidity: ReserveLiquidity {
+                borrowed_amount_wads,
+                available_amount: total_liquidity - borrowed_amount_wads.try_floor_u64().unwrap(),
+                ..ReserveLiquidity::default()
+            },
+            config: PoolConfig {
+                mine_distribution,
+                ..PoolConfig::default()
+            },
+            lottery: Lottery {
+                kink_util_rate,
+                ..Lottery::default()
+            },
+            ..Pool::default()
         }
     }
 
+    #[test]
+    fn kinked_mine_ratio() {
+        // 25% utilization with the kink at 50% is halfway to the kink
+        let pool = mine_ratio_pool(100, WAD / 4, 5_000, MineDistribution::Kinked);
+        assert_eq!(
+            pool.get_mine_ratio().unwrap(),
+            (Rate::from_percent(25), Rate::from_percent(75))
+        );
+        // 75% utilization is halfway from the kink to full utilization
+        let pool = mine_ratio_pool(100, WAD / 4 * 3, 5_000, MineDistribution::Kinked);
+        assert_eq!(
+            pool.get_mine_ratio().unwrap(),
+            (Rate::from_percent(75), Rate::from_percent(25))
+        );
+        let pool = mine_ratio_pool(100, WAD, MAX_KINK_UTIL_RATE, MineDistribution::Kinked);
+        assert_eq!(
+            pool.get_mine_ratio().unwrap(),
+            (Rate::from_percent(50), Rate::from_percent(50))
+        );
+        let pool = mine_ratio_pool(100, WAD / 4, 5_000, MineDistribution::Flat);
+        assert_eq!(
+            pool.get_mine_ratio().unwrap(),
+            (Rate::from_percent(50), Rate::from_percent(50))
+        );
+        // without borrows everything goes to the lend side
+        let pool = mine_ratio_pool(100, 0, 5_000, MineDistribution::Flat);
+        assert_eq!(pool.get_mine_ratio().unwrap(), (Rate::one(), Rate::zero()));
+    }
+
     proptest! {
         #[test]
-        fn total_supply(
+        fn mine_ratio_sums_to_one(
             total_liquidity in 0..=MAX_LIQUIDITY,
             borrowed_percent in 0..=WAD,
-            owner_unclaimed_amount in 0..=u128::from(MAX_LIQUIDITY/100) * u128::from(WAD),
-        ){
-             let borrowed_amount_wads = Decimal::from(total_liquidity).try_mul(Rate::from_scaled_val(borrowed_percent))?;
-            let owner_unclaimed = Decimal::from_scaled_val(owner_unclaimed_amount);
-
-             let liquidity:ReserveLiquidity = ReserveLiquidity {
-                    borrowed_amount_wads,
-                    available_amount: total_liquidity - borrowed_amount_wads.try_round_u64()?,
-                    owner_unclaimed,
-                    ..ReserveLiquidity::default()
-                };
-            let total_supply = liquidity.total_supply()?;
-            // println!("total_liquidity={},borrowed_percent={},borrowed_amount_wads={},owner_unclaimed={},total_supply={}",total_liquidity,Rate::from_scaled_val(borrowed_percent),borrowed_amount_wads,owner_unclaimed,total_supply);
-        }
-        #[test]
-        fn utilization_rate(
-             total_liquidity in 0..=MAX_LIQUIDITY,
-            borrowed_percent in 0..=WAD,
-            owner_unclaimed_amount in 0..=u128::from(MAX_LIQUIDITY/100) * u128::from(WAD),
-        ){
-              let borrowed_amount_wads = Decimal::from(total_liquidity).try_mul(Rate::from_scaled_val(borrowed_percent))?;
-            let owner_unclaimed = Decimal::from_scaled_val(owner_unclaimed_amount);
-
-             let liquidity:ReserveLiquidity = ReserveLiquidity {
-                    borrowed_amount_wads,
-                    available_amount: total_liquidity - borrowed_amount_wads.try_round_u64()?,
-                    owner_unclaimed,
-                    ..ReserveLiquidity::default()
-                };
-            let utilization_rate = liquidity.utilization_rate()?;
-            // println!("total_liquidity={},borrowed_percent={},borrowed_amount_wads={},owner_unclaimed={},utilization_rate={}",total_liquidity,Rate::from_scaled_val(borrowed_percent),borrowed_amount_wads,owner_unclaimed,utilization_rate);
+            kink_util_rate in 0..=MAX_KINK_UTIL_RATE,
+            mine_distribution in 0..=2u8,
+        ) {
+            let mine_distribution = MineDistribution::try_from(mine_distribution)?;
+            let pool = mine_ratio_pool(total_liquidity, borrowed_percent, kink_util_rate, mine_distribution);
+            let (lend_side_ratio, borrow_side_ratio) = pool.get_mine_ratio()?;
+            assert!(lend_side_ratio <= Rate::one());
+            assert_eq!(lend_side_ratio.try_add(borrow_side_ratio)?, Rate::one());
+            if mine_distribution == MineDistribution::DepositOnly {
+                assert_eq!(lend_side_ratio, Rate::one());
+            }
         }
+    }
+
+    fn borrowed_liquidity(
+        total_liquidity: u64,
+        borrowed_rate: Rate,
+    ) -> Result<ReserveLiquidity, ProgramError> {
+        let borrowed_amount_wads = Decimal::from(total_liquidity).try_mul(borrowed_rate)?;
+        Ok(ReserveLiquidity {
+            borrowed_amount_wads,
+            available_amount: total_liquidity - borrowed_amount_wads.try_floor_u64()?,
+            cumulative_borrow_rate_wads: Decimal::one(),
+            ..ReserveLiquidity::default()
+        })
+    }
+
+    proptest! {
         #[test]
-        fn get_mine_ratio(
-            mint_total_supply in 0..=MAX_LIQUIDITY,
+        fn total_supply(
             total_liquidity in 0..=MAX_LIQUIDITY,
             borrowed_percent in 0..=WAD,
-            optimal_utilization_rate in 0..=100u8,
-            owner_unclaimed_amount in 0..=u128::MAX / u128::from(u64::MAX) / 1000 as u128 * u128::from(WAD),
-            (min_borrow_rate, optimal_borrow_rate, max_borrow_rate) in borrow_rates(),
-        ){
-            let borrowed_amount_wads = Decimal::from(total_liquidity).try_mul(Rate::from_scaled_val(borrowed_percent))?;
-            let owner_unclaimed = Decimal::from_scaled_val(owner_unclaimed_amount);
-            let reserve = Pool {
-                liquidity: ReserveLiquidity {
-                    borrowed_amount_wads,
-                    available_amount: total_liquidity - borrowed_amount_wads.try_round_u64()?,
-                    owner_unclaimed,
-                    ..ReserveLiquidity::default()
-                },
-                collateral:ReserveCollateral{
-                    mint_total_supply,
-                    ..ReserveCollateral::default()
-                },
-                config: PoolConfig { optimal_utilization_rate, min_borrow_rate, optimal_borrow_rate, max_borrow_rate, ..PoolConfig::default() },
-                lottery:Ticket{
-                    total_mining_speed:100,
-                    kink_util_rate:50,
-                    l_token_mining_index:Decimal::zero(),
-                    borrow_mining_index:Decimal::zero(),
-                    ..Ticket::default()
-                },
-                ..Pool::default()
+            owner_unclaimed_amount in 0..=u128::from(MAX_LIQUIDITY) * u128::from(WAD),
+        ) {
+            let liquidity = ReserveLiquidity {
+                owner_unclaimed: Decimal::from_scaled_val(owner_unclaimed_amount),
+                ..borrowed_liquidity(total_liquidity, Rate::from_scaled_val(borrowed_percent))?
             };
-            let (mining_ratio,borrow_ratio)=reserve.get_mine_ratio()?;
-            // println!("mint_total_supply={},total_liquidity={},borrowed_percent={},borrowed_amount_wads={},owner_unclaimed={},mining_ratio={},borrow_ratio={}",
-            //     mint_total_supply,total_liquidity,Rate::from_scaled_val(borrowed_percent),borrowed_amount_wads,owner_unclaimed,mining_ratio,borrow_ratio);
-        }
-        #[test]
-        fn refresh_index(
-               mint_total_supply in 0..=MAX_LIQUIDITY,
-            total_liquidity in 0..=MAX_LIQUIDITY,
-            borrowed_percent in 0..=WAD,
-            optimal_utilization_rate in 0..=100u8,
-            owner_unclaimed_amount in 0..=u128::MAX / u128::from(u64::MAX) / 1000 as u128 * u128::from(WAD),
-            (min_borrow_rate, optimal_borrow_rate, max_borrow_rate) in borrow_rates(),
-               cumulative_borrow_rate_wads in WAD..=WAD + WAD / 100000 ,
-        ){
+            let all_liquidity = Decimal::from(liquidity.available_amount)
+                .try_add(liquidity.borrowed_amount_wads)?;
 
-            let borrowed_amount_wads = Decimal::from(total_liquidity).try_mul(Rate::from_scaled_val(borrowed_percent))?;
-            let cumulative_borrow_rate_decimal = Decimal::from_scaled_val(u128::from(cumulative_borrow_rate_wads));
-            let owner_unclaimed = Decimal::from_scaled_val(owner_unclaimed_amount);
-            let mut reserve = Pool {
-                liquidity: ReserveLiquidity {
-                    borrowed_amount_wads,
-                    available_amount: total_liquidity - borrowed_amount_wads.try_round_u64()?,
-                    owner_unclaimed,
-                    cumulative_borrow_rate_wads:cumulative_borrow_rate_decimal,
-                    ..ReserveLiquidity::default()
-                },
-                collateral:ReserveCollateral{
-                    mint_total_supply,
-                    ..ReserveCollateral::default()
-                },
-                config: PoolConfig { optimal_utilization_rate, min_borrow_rate, optimal_borrow_rate, max_borrow_rate, ..PoolConfig::default() },
-                lottery:Ticket{
-                    total_mining_speed:100,
-                    kink_util_rate:50,
-                    l_token_mining_index:Decimal::zero(),
-                    borrow_mining_index:Decimal::zero(),
-                    ..Ticket::default()
-                },
-                ..Pool::default()
-            };
-            reserve.refresh_index(100)?;
-            // println!("mint_total_supply={},total_liquidity={},borrowed_percent={},borrowed_amount_wads={},cumulative_borrow_rate_decimal={},owner_unclaimed={},l_token_mining_index={},borrow_mining_index={}",
-            //     mint_total_supply,total_liquidity,Rate::from_scaled_val(borrowed_percent),borrowed_amount_wads,cumulative_borrow_rate_decimal,owner_unclaimed,reserve.bonus.l_token_mining_index,reserve.bonus.borrow_mining_index);
+            // the reserve owner share of the interest is never part of the supply
+            let total_supply = liquidity.total_supply()?;
+            if liquidity.owner_unclaimed > all_liquidity {
+                assert_eq!(total_supply, Decimal::zero());
+            } else {
+                assert_eq!(total_supply.try_add(liquidity.owner_unclaimed)?, all_liquidity);
+            }
         }
+
         #[test]
-        fn refresh_index_boundary(
-               mint_total_supply in 0..=MAX_LIQUIDITY,
+        fn utilization_rate(
             total_liquidity in 0..=MAX_LIQUIDITY,
             borrowed_percent in 0..=WAD,
-            optimal_utilization_rate in 0..=100u8,
-            owner_unclaimed_amount in 0..=u128::MAX / u128::from(u64::MAX) / 1000 as u128 * u128::from(WAD),
-            (min_borrow_rate, optimal_borrow_rate, max_borrow_rate) in borrow_rates(),
-
-        ){
-           let cumulative_borrow_rate_wads  = 10*WAD;
-            // let borrowed_amount_wads = Decimal::from(total_liquidity).try_mul(Rate::from_scaled_val(borrowed_percent))?;
-            let borrowed_amount_wads = Decimal::from_scaled_val(u128::from(WAD+1));
-
-            let cumulative_borrow_rate_decimal = Decimal::from_scaled_val(u128::from(cumulative_borrow_rate_wads));
-            let owner_unclaimed = Decimal::from_scaled_val(owner_unclaimed_amount);
-            let mut reserve = Pool {
-                liquidity: ReserveLiquidity {
-                    borrowed_amount_wads,
-                    available_amount: total_liquidity - borrowed_amount_wads.try_round_u64()?,
-                    owner_unclaimed,
-                    cumulative_borrow_rate_wads:cumulative_borrow_rate_decimal,
-                    ..ReserveLiquidity::default()
-                },
-                collateral:ReserveCollateral{
-                    mint_total_supply,
-                    ..ReserveCollateral::default()
-                },
-                config: PoolConfig { optimal_utilization_rate, min_borrow_rate, optimal_borrow_rate, max_borrow_rate, ..PoolConfig::default() },
-                lottery:Ticket{
-                    total_mining_speed:100,
-                    kink_util_rate:50,
-                    l_token_mining_index:Decimal::zero(),
-                    borrow_mining_index:Decimal::zero(),
-                    ..Ticket::default()
-                },
-                ..Pool::default()
+            owner_unclaimed_amount in 0..=u128::from(MAX_LIQUIDITY / 100) * u128::from(WAD),
+        ) {
+            let liquidity = ReserveLiquidity {
+                owner_unclaimed: Decimal::from_scaled_val(owner_unclaimed_amount),
+                ..borrowed_liquidity(total_liquidity, Rate::from_scaled_val(borrowed_percent))?
             };
-            reserve.refresh_index(100)?;
-            // println!("mint_total_supply={},total_liquidity={},borrowed_percent={},borrowed_amount_wads={},cumulative_borrow_rate_decimal={},owner_unclaimed={},l_token_mining_index={},borrow_mining_index={}",
-            //     mint_total_supply,total_liquidity,Rate::from_scaled_val(borrowed_percent),borrowed_amount_wads,cumulative_borrow_rate_decimal,owner_unclaimed,reserve.bonus.l_token_mining_index,reserve.bonus.borrow_mining_index);
+            let utilization_rate = liquidity.utilization_rate()?;
+            assert!(utilization_rate <= Rate::one());
+            if liquidity.borrowed_amount_wads < Decimal::one() {
+                assert_eq!(utilization_rate, Rate::zero());
+            }
         }
+
         #[test]
         fn current_borrow_rate(
-                mint_total_supply in 0..=MAX_LIQUIDITY,
             total_liquidity in 0..=MAX_LIQUIDITY,
             borrowed_percent in 0..=WAD,
             optimal_utilization_rate in 0..=100u8,
-            owner_unclaimed_amount in 0..=u128::MAX / u128::from(u64::MAX) / 1000 as u128 * u128::from(WAD),
+            owner_unclaimed_amount in 0..=u128::from(MAX_LIQUIDITY / 100) * u128::from(WAD),
             (min_borrow_rate, optimal_borrow_rate, max_borrow_rate) in borrow_rates(),
-               cumulative_borrow_rate_wads in WAD..=WAD + WAD / 100000 ,
         ) {
-            let borrowed_amount_wads = Decimal::from(total_liquidity).try_mul(Rate::from_scaled_val(borrowed_percent))?;
-            let cumulative_borrow_rate_decimal = Decimal::from_scaled_val(u128::from(cumulative_borrow_rate_wads));
-            let owner_unclaimed = Decimal::from_scaled_val(owner_unclaimed_amount);
-            let mut reserve = Pool {
+            let pool = Pool {
                 liquidity: ReserveLiquidity {
-                    borrowed_amount_wads,
-                    available_amount: total_liquidity - borrowed_amount_wads.try_round_u64()?,
-                    owner_unclaimed,
-                    cumulative_borrow_rate_wads:cumulative_borrow_rate_decimal,
-                    ..ReserveLiquidity::default()
+                    owner_unclaimed: Decimal::from_scaled_val(owner_unclaimed_amount),
+                    ..borrowed_liquidity(total_liquidity, Rate::from_scaled_val(borrowed_percent))?
                 },
-                collateral:ReserveCollateral{
-                    mint_total_supply,
-                    ..ReserveCollateral::default()
-                },
-                config: PoolConfig { optimal_utilization_rate, min_borrow_rate, optimal_borrow_rate, max_borrow_rate, ..PoolConfig::default() },
-                lottery:Ticket{
-                    total_mining_speed:100,
-                    kink_util_rate:50,
-                    l_token_mining_index:Decimal::zero(),
-                    borrow_mining_index:Decimal::zero(),
-                    ..Ticket::default()
+                config: PoolConfig {
+                    optimal_utilization_rate,
+                    min_borrow_rate,
+                    optimal_borrow_rate,
+                    max_borrow_rate,
+                    ..PoolConfig::default()
                 },
                 ..Pool::default()
             };
-            // println!("total_liquidity={},borrowed_percent={},borrowed_amount_wads={},optimal_utilization_rate={},owner_unclaimed_amount={},owner_unclaimed={},min_borrow_rate={},optimal_borrow_rate={},max_borrow_rate={}",
-            //         total_liquidity,Rate::from_scaled_val(borrowed_percent),borrowed_amount_wads,optimal_utilization_rate,owner_unclaimed_amount,owner_unclaimed,min_borrow_rate,optimal_borrow_rate,max_borrow_rate);
-            let current_borrow_rate = reserve.current_borrow_rate()?;
-            // println!("current_borrow_rate={}",current_borrow_rate);
+            let current_borrow_rate = pool.current_borrow_rate()?;
             assert!(current_borrow_rate >= Rate::from_percent(min_borrow_rate));
             assert!(current_borrow_rate <= Rate::from_percent(max_borrow_rate));
 
             let optimal_borrow_rate = Rate::from_percent(optimal_borrow_rate);
-            let current_rate = reserve.liquidity.utilization_rate()?;
-            // println!("current_rate={}",current_rate);
-            assert!(current_rate <= Rate::from_percent(100));
-            match current_rate.cmp(&Rate::from_percent(optimal_utilization_rate)) {
-                Ordering::Less => {
-                    if min_borrow_rate == reserve.config.optimal_borrow_rate {
-                        assert_eq!(current_borrow_rate, optimal_borrow_rate);
-                    } else {
-                        assert!(current_borrow_rate < optimal_borrow_rate);
-                    }
-                }
-                Ordering::Equal => assert!(current_borrow_rate == optimal_borrow_rate),
-                Ordering::Greater => {
-                    if max_borrow_rate == reserve.config.optimal_borrow_rate {
-                        assert_eq!(current_borrow_rate, optimal_borrow_rate);
-                    } else {
-                        assert!(current_borrow_rate > optimal_borrow_rate);
-                    }
-                }
+            let utilization_rate = pool.liquidity.utilization_rate()?;
+            match utilization_rate.cmp(&Rate::from_percent(optimal_utilization_rate)) {
+                Ordering::Less => assert!(current_borrow_rate <= optimal_borrow_rate),
+                Ordering::Equal => assert_eq!(current_borrow_rate, optimal_borrow_rate),
+                Ordering::Greater => assert!(current_borrow_rate >= optimal_borrow_rate),
             }
         }
 
         #[test]
-        fn collateral_exchange_rate(
-            total_liquidity in 0..=MAX_LIQUIDITY,
-            borrowed_percent in 0..=WAD,
-            collateral_multiplier in 0..=(5*WAD),
-            borrow_rate in 0..=100u8,
-            owner_unclaimed_amount in 0..= u128::MAX / u128::from(u64::MAX) / 1000u128 * u128::from(WAD),
-            cumulative_borrow_rate_wads in WAD..=WAD + WAD / 100000 ,
+        fn accrue_interest(
+            total_liquidity in u64::MAX / 6..=MAX_LIQUIDITY,
+            borrowed_percent in 0..=100u8,
+            optimal_utilization_rate in 0..=100u8,
+            (min_borrow_rate, optimal_borrow_rate, max_borrow_rate) in borrow_rates(),
+            reserve_owner_fee_wad in 0..=WAD,
+            slots_elapsed in 0..=SLOTS_PER_YEAR,
         ) {
-            let borrowed_liquidity_wads = Decimal::from(total_liquidity).try_mul(Rate::from_scaled_val(borrowed_percent))?;
-            let available_liquidity = total_liquidity - borrowed_liquidity_wads.try_round_u64()?;
-            let mint_total_supply = Decimal::from(total_liquidity).try_mul(Rate::from_scaled_val(collateral_multiplier))?.try_round_u64()?;
-             let cumulative_borrow_rate_decimal = Decimal::from_scaled_val(u128::from(cumulative_borrow_rate_wads));
-            let owner_unclaimed = Decimal::from_scaled_val(owner_unclaimed_amount);
-            let mut reserve = Pool {
-                collateral: ReserveCollateral {
-                    mint_total_supply,
-                    ..ReserveCollateral::default()
-                },
-                liquidity: ReserveLiquidity {
-                    borrowed_amount_wads: borrowed_liquidity_wads,
-                    available_amount: available_liquidity,
-                    cumulative_borrow_rate_wads:cumulative_borrow_rate_decimal,
-                    owner_unclaimed,
-                    ..ReserveLiquidity::default()
-                },
+            let mut pool = Pool {
+                liquidity: borrowed_liquidity(total_liquidity, Rate::from_percent(borrowed_percent))?,
                 config: PoolConfig {
-                    min_borrow_rate: borrow_rate,
-                    optimal_borrow_rate: borrow_rate,
-                    optimal_utilization_rate: 100,
+                    optimal_utilization_rate,
+                    min_borrow_rate,
+                    optimal_borrow_rate,
+                    max_borrow_rate,
+                    reserve_owner_fee_wad,
                     ..PoolConfig::default()
                 },
                 ..Pool::default()
             };
-            if owner_unclaimed.gt(&Decimal::from(total_liquidity)){
-                return Ok(());
-            }
-            let exchange_rate = reserve.collateral_exchange_rate()?;
-            // assert!(exchange_rate.0.to_scaled_val() <= 5u128 * WAD as u128);
+            let borrowed_amount_wads = pool.liquidity.borrowed_amount_wads;
+            let utilization_rate = pool.liquidity.utilization_rate()?;
 
-            // After interest accrual, total liquidity increases and collateral are worth more
-            reserve.accrue_interest(1)?;
-
-            let new_exchange_rate = reserve.collateral_exchange_rate()?;
-            // println!("borrow_rate={},total_liquidity={},borrowed_percent={},borrowed_liquidity_wads={},owner_unclaimed_amount={},cumulative_borrow_rate_decimal={},new_exchange_rate.0={},exchange_rate.0={}",
-            //     borrow_rate,total_liquidity,Rate::from_scaled_val(borrowed_percent),borrowed_liquidity_wads,owner_unclaimed, cumulative_borrow_rate_decimal,new_exchange_rate.0,exchange_rate.0);
-
-            if borrow_rate > 0 && total_liquidity > 0 && borrowed_percent > 0 && reserve.liquidity.total_supply()?.gt(&Decimal::zero()) {
-                assert!(new_exchange_rate.0 < exchange_rate.0);
+            pool.accrue_interest(slots_elapsed)?;
+            let interest = pool.liquidity.borrowed_amount_wads.try_sub(borrowed_amount_wads)?;
+            if utilization_rate > Rate::zero() && slots_elapsed > 0 {
+                assert!(interest > Decimal::zero());
             } else {
-                assert_eq!(new_exchange_rate.0, exchange_rate.0);
+                assert_eq!(interest, Decimal::zero());
             }
+            // the reserve owner only keeps its share of the interest
+            assert!(pool.liquidity.owner_unclaimed <= interest);
+            assert!(pool.liquidity.cumulative_borrow_rate_wads >= Decimal::one());
         }
 
         #[test]
         fn compound_interest(
-            total_liquidity in u64::MAX / 6..=MAX_LIQUIDITY,
-            borrowed_percent in 0..=100u8,
-            optimal_utilization_rate in 0..=100u8,
-            (min_borrow_rate, optimal_borrow_rate, max_borrow_rate) in borrow_rates(),
-            slots_elapsed in 0..=SLOTS_PER_YEAR,
+            slots_elapsed in 1..=SLOTS_PER_YEAR,
+            borrow_rate in 0..=36u8,
         ) {
-              let borrowed_amount_wads = Decimal::from(total_liquidity).try_mul(Rate::from_percent(borrowed_percent))?;
-            let mut reserve = Pool {
-                liquidity: ReserveLiquidity {
-                    borrowed_amount_wads,
-                    available_amount: total_liquidity - borrowed_amount_wads.try_round_u64()?,
-                    cumulative_borrow_rate_wads:Decimal::one(),
-                    ..ReserveLiquidity::default()
-                },
-                collateral:ReserveCollateral{
-                    ..ReserveCollateral::default()
-                },
-                config: PoolConfig { optimal_utilization_rate, min_borrow_rate, optimal_borrow_rate, max_borrow_rate, ..PoolConfig::default() },
-                lottery:Ticket{
-                    total_mining_speed:100,
-                    kink_util_rate:50,
-                    l_token_mining_index:Decimal::zero(),
-                    borrow_mining_index:Decimal::zero(),
-                    ..Ticket::default()
-                },
-                ..Pool::default()
+            let mut liquidity = ReserveLiquidity {
+                borrowed_amount_wads: Decimal::from(MAX_LIQUIDITY),
+                cumulative_borrow_rate_wads: Decimal::one(),
+                ..ReserveLiquidity::default()
             };
+            let borrow_rate = Rate::from_percent(borrow_rate);
 
-            // print!("total_liquidity={},borrowed_percent={},borrowed_amount_wads={},optimal_utilization_rate={},,min_borrow_rate={},optimal_borrow_rate={},max_borrow_rate={},",
-            //         total_liquidity,Rate::from_percent(borrowed_percent),borrowed_amount_wads,optimal_utilization_rate,min_borrow_rate,optimal_borrow_rate,max_borrow_rate);
-            // println!("slots_elapsed={}",slots_elapsed);
-            // Simulate running for max 1000 years, assuming that interest is
+            // Simulate running for max 10 years, assuming that interest is
             // compounded at least once a year
-            for i in 0..100 {
-                let borrow_rate = reserve.current_borrow_rate()?;
-
-                // reserve.liquidity.compound_interest(borrow_rate, slots_elapsed,0)?;
-                if i > 90{
+            for _ in 0..10 {
+                let cumulative_borrow_rate_wads = liquidity.cumulative_borrow_rate_wads;
+                liquidity.compound_interest(borrow_rate, slots_elapsed, 0)?;
+                liquidity.borrowed_amount_wads.to_scaled_val()?;
+                assert!(liquidity.cumulative_borrow_rate_wads >= cumulative_borrow_rate_wads);
+            }
+        }
 
-                    // println!("borrow_rate={}, reserve.liquidity.borrowed_amount_wads={}", borrow_rate,reserve.liquidity.borrowed_amount_wads);
-                }
+        #[test]
+        fn refresh_index_monotonic(
+            mint_total_supply in 0..=MAX_LIQUIDITY,
+            total_liquidity in 0..=MAX_LIQUIDITY,
+            borrowed_percent in 0..=WAD,
+            cumulative_borrow_rate_wads in WAD..=10 * WAD,
+            total_mining_speed in 0..=u64::from(u32::MAX),
+            kink_util_rate in 0..=MAX_KINK_UTIL_RATE,
+            mine_distribution in 0..=2u8,
+            first_slots_elapsed in 0..=SLOTS_PER_YEAR,
+            second_slots_elapsed in 0..=SLOTS_PER_YEAR,
+        ) {
+            let mine_distribution = MineDistribution::try_from(mine_distribution)?;
+            let mut pool = mine_ratio_pool(total_liquidity, borrowed_percent, kink_util_rate, mine_distribution);
+            pool.liquidity.cumulative_borrow_rate_wads = Decimal::from_scaled_val(u128::from(cumulative_borrow_rate_wads));
+            pool.collateral.mint_total_supply = mint_total_supply;
+            pool.lottery.total_mining_speed = total_mining_speed;
+
+            let mut slot = 0;
+            for slots_elapsed in [first_slots_elapsed, second_slots_elapsed] {
+                let lottery = pool.lottery;
+                slot += slots_elapsed;
+                pool.refresh_index(slot)?;
+                pool.last_update.update_slot(slot);
+                assert!(pool.lottery.l_token_mining_index >= lottery.l_token_mining_index);
+                assert!(pool.lottery.borrow_mining_index >= lottery.borrow_mining_index);
+
+                // the lend side never mines more than the mining speed
+                let lend_side_mined = pool.lottery.l_token_mining_index
+                    .try_sub(lottery.l_token_mining_index)?
+                    .try_mul(mint_total_supply)?;
+                assert!(lend_side_mined <= Decimal::from(total_mining_speed).try_mul(slots_elapsed)?);
+            }
 
-                // println!(" reserve.liquidity.borrowed_amount_wads={}", reserve.liquidity.borrowed_amount_wads);
-                reserve.liquidity.borrowed_amount_wads.to_scaled_val()?;
+            if mint_total_supply > 0 && slot > 0 {
+                assert_eq!(pool.refresh_index(slot - 1), Err(PoolingError::MathOverflow.into()));
             }
         }
+
         #[test]
-        fn compound_interest_simple(
-            slots_elapsed in 1..=SLOTS_PER_YEAR,
-            borrow_rate in 0..=36u8,
+        fn deposit_and_redeem_round_trip(
+            principal_amount in 0..=MAX_LIQUIDITY,
+            yield_amount in 0..=MAX_LIQUIDITY,
+            liquidity_amount in 0..=MAX_LIQUIDITY,
         ) {
-            let mut reserve = Pool::default();
-            reserve.liquidity.borrowed_amount_wads = Decimal::from(MAX_LIQUIDITY);
-            reserve.liquidity.cumulative_borrow_rate_wads = Decimal::one();
-            let borrow_rate = Rate::from_percent(borrow_rate);
-            // println!("slots_elapsed={},borrow_rate={}",slots_elapsed,borrow_rate);
-            // Simulate running for max 1000 years, assuming that interest is
-            // compounded at least once a year
-            for i in 0..10 {
-                reserve.liquidity.compound_interest(borrow_rate, slots_elapsed, 0)?;
-                if i % 10 == 0{
-                    // println!("borrowed_amount_wads={},cumulative_borrow_rate_wads={}",reserve.liquidity.borrowed_amount_wads,reserve.liquidity.cumulative_borrow_rate_wads);
-                }
-                reserve.liquidity.borrowed_amount_wads.to_scaled_val()?;
-                reserve.liquidity.cumulative_borrow_rate_wads.to_scaled_val()?;
-            }
+            let mut pool = Pool::default();
+            pool.deposit_liquidity(principal_amount)?;
+            pool.liquidity.deposit(yield_amount)?;
+            let prize_available = pool.prize_available()?;
+
+            // collateral is minted one to one with the principal, whatever the yield
+            let collateral_amount = pool.deposit_liquidity(liquidity_amount)?;
+            assert_eq!(collateral_amount, liquidity_amount);
+            assert_eq!(pool.prize_available()?, prize_available);
+
+            assert_eq!(pool.redeem_collateral(collateral_amount)?, liquidity_amount);
+            assert_eq!(pool.liquidity.principal_amount, principal_amount);
+            assert_eq!(pool.collateral.mint_total_supply, principal_amount);
+            assert_eq!(pool.prize_available()?, prize_available);
         }
 
         #[test]
-        fn reserve_accrue_interest(
-                total_liquidity in u64::MAX / 6..=MAX_LIQUIDITY,
-            borrowed_percent in 0..=100u8,
-            optimal_utilization_rate in 0..=100u8,
-            (min_borrow_rate, optimal_borrow_rate, max_borrow_rate) in borrow_rates(),
+        fn collateral_exchange_rate(
+            principal_amount in 0..=MAX_LIQUIDITY / 1000,
+            exchange_rate in collateral_exchange_rate_range(),
+            yield_amount in 0..=MAX_LIQUIDITY / 1000,
+            borrowed_percent in 0..=WAD,
             slots_elapsed in 0..=SLOTS_PER_YEAR,
         ) {
-            let borrowed_amount_wads = Decimal::from(total_liquidity).try_mul(Rate::from_percent(borrowed_percent))?;
-            let mut reserve = Pool {
+            let mint_total_supply = exchange_rate.liquidity_to_collateral(principal_amount)?;
+            let total_liquidity = principal_amount + yield_amount;
+            let mut pool = Pool {
                 liquidity: ReserveLiquidity {
-                    borrowed_amount_wads,
-                    available_amount: total_liquidity - borrowed_amount_wads.try_round_u64()?,
-                    cumulative_borrow_rate_wads:Decimal::one(),
-                    ..ReserveLiquidity::default()
+                    principal_amount,
+                    ..borrowed_liquidity(total_liquidity, Rate::from_scaled_val(borrowed_percent))?
                 },
-                collateral:ReserveCollateral{
+                collateral: ReserveCollateral {
+                    mint_total_supply,
                     ..ReserveCollateral::default()
                 },
-                config: PoolConfig { optimal_utilization_rate, min_borrow_rate, optimal_borrow_rate, max_borrow_rate, ..PoolConfig::default() },
-                lottery:Ticket{
-                    total_mining_speed:100,
-                    kink_util_rate:50,
-                    l_token_mining_index:Decimal::zero(),
-                    borrow_mining_index:Decimal::zero(),
-                    ..Ticket::default()
+                config: PoolConfig {
+                    optimal_utilization_rate: 80,
+                    min_borrow_rate: 0,
+                    optimal_borrow_rate: 4,
+                    max_borrow_rate: 30,
+                    ..PoolConfig::default()
                 },
                 ..Pool::default()
             };
 
-            let utilization_rate = reserve.liquidity.utilization_rate()?;
-            let borrow_rate = reserve.current_borrow_rate()?;
-             reserve.accrue_interest(slots_elapsed)?;
-            // println!("total_liquidity={},borrowed_percent={},slots_elapsed={},utilization_rate={},optimal_utilization_rate={},min_borrow_rate={},optimal_borrow_rate={},max_borrow_rate={},borrow_rate={},borrowed_amount_wads={},reserve.liquidity.borrowed_amount_wads={}",
-            //     total_liquidity,borrowed_percent,slots_elapsed,utilization_rate,optimal_utilization_rate,min_borrow_rate,optimal_borrow_rate,max_borrow_rate,borrow_rate,borrowed_amount_wads,reserve.liquidity.borrowed_amount_wads);
-            if utilization_rate > Rate::zero() && slots_elapsed > 0 {
-                assert!(reserve.liquidity.borrowed_amount_wads > borrowed_amount_wads);
+            let collateral_exchange_rate = pool.collateral_exchange_rate()?;
+            if mint_total_supply == 0 || principal_amount == 0 {
+                assert_eq!(
+                    Rate::from(collateral_exchange_rate),
+                    Rate::from_scaled_val(INITIAL_COLLATERAL_RATE)
+                );
             } else {
-                assert!(reserve.liquidity.borrowed_amount_wads == borrowed_amount_wads);
-            }
-        }
-
-        #[test]
-        fn borrow_fee_calculation(
-            borrow_fee_wad in 0..WAD, // at WAD, fee == borrow amount, which fails
-            reserve_owner_fee_wad in 0..WAD,
-            flash_loan_fee_wad in 0..WAD, // at WAD, fee == borrow amount, which fails
-            host_fee_percentage in 0..=100u8,
-            borrow_amount in 3..=u64::MAX, // start at 3 to ensure calculation success
-                                           // 0, 1, and 2 are covered in the minimum tests
-                                           // @FIXME: ^ no longer true
-        ) {
-            let fees = ReserveFees {
-                borrow_fee_wad,
-                reserve_owner_fee_wad,
-                flash_loan_fee_wad,
-                host_fee_percentage,
-            };
-            let (total_fee, host_fee) = fees.calculate_borrow_fees(Decimal::from(borrow_amount), FeeCalculation::Exclusive)?;
-
-            // The total fee can't be greater than the amount borrowed, as long
-            // as amount borrowed is greater than 2.
-            // At a borrow amount of 2, we can get a total fee of 2 if a host
-            // fee is also specified.
-            assert!(total_fee <= borrow_amount);
-
-            // the host fee can't be greater than the total fee
-            assert!(host_fee <= total_fee);
-
-            // for all fee rates greater than 0, we must have some fee
-            if borrow_fee_wad > 0 {
-                assert!(total_fee > 0);
-            }
-
-            if host_fee_percentage == 100 {
-                // if the host fee percentage is maxed at 100%, it should get all the fee
-                assert_eq!(host_fee, total_fee);
+                // outstanding collateral never redeems for more than the principal
+                assert!(collateral_exchange_rate.collateral_to_liquidity(mint_total_supply)? <= principal_amount);
+                assert_eq!(collateral_exchange_rate.liquidity_to_collateral(principal_amount)?, mint_total_supply);
             }
 
-            // if there's a host fee and some borrow fee, host fee must be greater than 0
-            if host_fee_percentage > 0 && borrow_fee_wad > 0 {
-                assert!(host_fee > 0);
-            } else {
-                assert_eq!(host_fee, 0);
-            }
-        }
-
-        #[test]
-        fn flash_loan_fee_calculation(
-            borrow_fee_wad in 0..WAD, // at WAD, fee == borrow amount, which fails
-            reserve_owner_fee_wad in 0..WAD,
-            flash_loan_fee_wad in 0..WAD, // at WAD, fee == borrow amount, which fails
-            host_fee_percentage in 0..=100u8,
-            borrow_amount in 3..=u64::MAX, // start at 3 to ensure calculation success
-                                           // 0, 1, and 2 are covered in the minimum tests
-                                           // @FIXME: ^ no longer true
-        ) {
-            let fees = ReserveFees {
-                borrow_fee_wad,
-                reserve_owner_fee_wad,
-                flash_loan_fee_wad,
-                host_fee_percentage,
-            };
-            let (total_fee, host_fee) = fees.calculate_flash_loan_fees(Decimal::from(borrow_amount))?;
-
-            // The total fee can't be greater than the amount borrowed, as long
-            // as amount borrowed is greater than 2.
-            // At a borrow amount of 2, we can get a total fee of 2 if a host
-            // fee is also specified.
-            assert!(total_fee <= borrow_amount);
-
-            // the host fee can't be greater than the total fee
-            assert!(host_fee <= total_fee);
-
-            // for all fee rates greater than 0, we must have some fee
-            if borrow_fee_wad > 0 {
-                assert!(total_fee > 0);
-            }
-
-            if host_fee_percentage == 100 {
-                // if the host fee percentage is maxed at 100%, it should get all the fee
-                assert_eq!(host_fee, total_fee);
-            }
-
-            // if there's a host fee and some borrow fee, host fee must be greater than 0
-            if host_fee_percentage > 0 && borrow_fee_wad > 0 {
-                assert!(host_fee > 0);
-            } else {
-                assert_eq!(host_fee, 0);
-            }
+            // interest accrued on borrows goes to the prize, not to the collateral
+            pool.accrue_interest(slots_elapsed)?;
+            assert_eq!(
+                Rate::from(pool.collateral_exchange_rate()?),
+                Rate::from(collateral_exchange_rate)
+            );
         }
     }
 
     #[test]
-    fn borrow_fee_calculation_min_host() {
-        let fees = ReserveFees {
-            borrow_fee_wad: 10_000_000_000_000_000, // 1%
-            reserve_owner_fee_wad: 10_000_000_000_000_000,
-            flash_loan_fee_wad: 0,
-            host_fee_percentage: 20,
+    fn pool_pack_round_trip() {
+        let pool = Pool {
+            version: PROGRAM_VERSION,
+            last_update: LastUpdate::new(12),
+            pool_manager: Pubkey::new_unique(),
+            liquidity: ReserveLiquidity {
+                mint_pubkey: Pubkey::new_unique(),
+                mint_decimals: 6,
+                supply_pubkey: Pubkey::new_unique(),
+                fee_receiver: Pubkey::new_unique(),
+                use_pyth_oracle: true,
+                pyth_oracle_pubkey: Pubkey::new_unique(),
+                available_amount: 1_000,
+                borrowed_amount_wads: Decimal::from(500u64),
+                cumulative_borrow_rate_wads: Decimal::from_percent(101),
+                market_price: Decimal::from(2u64),
+                owner_unclaimed: Decimal::from_percent(3),
+                principal_amount: 1_400,
+            },
+            collateral: ReserveCollateral {
+                mint_pubkey: Pubkey::new_unique(),
+                mint_total_supply: 1_400,
+                supply_pubkey: Pubkey::new_unique(),
+            },
+            config: PoolConfig {
+                paused: 1,
+                mine_distribution: MineDistribution::Flat,
+                exit_fee_wad: WAD / 100,
+                exit_fee_decay_slots: 1_000,
+                protocol_fee_wad: WAD / 10,
+                optimal_utilization_rate: 80,
+                min_borrow_rate: 1,
+                optimal_borrow_rate: 4,
+                max_borrow_rate: 30,
+                reserve_owner_fee_wad: WAD / 5,
+            },
+            lottery: Lottery {
+                un_coll_supply_account: Pubkey::new_unique(),
+                l_token_mining_index: Decimal::from(3u64),
+                borrow_mining_index: Decimal::from(4u64),
+                total_mining_speed: 100,
+                kink_util_rate: 50,
+            },
+            reentry_lock: true,
+            prize: Prize {
+                last_draw_slot: 8,
+                last_winner: Pubkey::new_unique(),
+                last_prize_amount: 60,
+                unclaimed_amount: 40,
+                taxed_amount: 25,
+                period_slots: 100,
+                period_start_slot: 10,
+                draw_count: 2,
+                draw_pending: true,
+                pending_commitment: [7u8; 32],
+                pending_commit_slot: 11,
+                reveal_slot: 12,
+                randomness_account: Pubkey::new_unique(),
+                tiers: vec![
+                    PrizeTier {
+                        percent: 70,
+                        winner_count: 1,
+                    },
+                    PrizeTier {
+                        percent: 30,
+                        winner_count: 3,
+                    },
+                ],
+                deferred_contributions: [5, 6, 0],
+                indexed_balance: 2_400,
+                indexed_tickets: 3,
+                awarding: true,
+                draw_seed: [9u8; 32],
+                draw_prize_amount: 50,
+                draw_picks: 2,
+                winners_awarded: 1,
+                awarded_amount: 35,
+                draw_winner: Pubkey::new_unique(),
+            },
+            ticket_balance: 1_200,
+            ticket_twab: Twab {
+                period_start_slot: 10,
+                last_slot: 12,
+                cumulative_balance: 2_400,
+                next_cumulative_balance: 600,
+            },
+            sponsored_amount: 200,
+            yield_source: YieldSource {
+                program_id: Pubkey::new_unique(),
+                reserve: Pubkey::new_unique(),
+                share_supply: Pubkey::new_unique(),
+                redeemable_amount: 300,
+            },
         };
 
-        // only 2 tokens borrowed, get error
-        let err = fees
-            .calculate_borrow_fees(Decimal::from(2u64), FeeCalculation::Exclusive)
-            .unwrap_err();
-        assert_eq!(err, PoolingError::BorrowTooSmall.into()); // minimum of 3 tokens
-
-        // only 1 token borrowed, get error
-        let err = fees
-            .calculate_borrow_fees(Decimal::one(), FeeCalculation::Exclusive)
-            .unwrap_err();
-        assert_eq!(err, PoolingError::BorrowTooSmall.into());
-
-        // 0 amount borrowed, 0 fee
-        let (total_fee, host_fee) = fees
-            .calculate_borrow_fees(Decimal::zero(), FeeCalculation::Exclusive)
-            .unwrap();
-        assert_eq!(total_fee, 0);
-        assert_eq!(host_fee, 0);
+        let mut data = [0u8; Pool::LEN];
+        Pool::pack(pool.clone(), &mut data).unwrap();
+        let unpacked = Pool::unpack(&data).unwrap();
+        assert_eq!(unpacked, pool);
+        assert_eq!(unpacked.last_update.stale, pool.last_update.stale);
     }
 
     #[test]
-    fn borrow_fee_calculation_min_no_host() {
-        let fees = ReserveFees {
-            borrow_fee_wad: 10_000_000_000_000_000, // 1%
-            reserve_owner_fee_wad: 10_000_000_000_000_000,
-            flash_loan_fee_wad: 0,
-            host_fee_percentage: 0,
-        };
+    fn old_pool_version_rejected() {
+        let mut data = [0u8; Pool::LEN];
+        Pool::pack(
+            Pool {
+                version: MIN_POOL_VERSION - 1,
+                ..Pool::default()
+            },
+            &mut data,
+        )
+        .unwrap();
+        assert_eq!(Pool::unpack(&data), Err(ProgramError::InvalidAccountData));
 
-        // only 2 tokens borrowed, ok
-        let (total_fee, host_fee) = fees
-            .calculate_borrow_fees(Decimal::from(2u64), FeeCalculation::Exclusive)
-            .unwrap();
-        assert_eq!(total_fee, 1);
-        assert_eq!(host_fee, 0);
-
-        // only 1 token borrowed, get error
-        let err = fees
-            .calculate_borrow_fees(Decimal::one(), FeeCalculation::Exclusive)
-            .unwrap_err();
-        assert_eq!(err, PoolingError::BorrowTooSmall.into()); // minimum of 2 tokens
-
-        // 0 amount borrowed, 0 fee
-        let (total_fee, host_fee) = fees
-            .calculate_borrow_fees(Decimal::zero(), FeeCalculation::Exclusive)
-            .unwrap();
-        assert_eq!(total_fee, 0);
-        assert_eq!(host_fee, 0);
+        data[0] = MIN_POOL_VERSION;
+        assert_eq!(Pool::unpack(&data).unwrap().version, MIN_POOL_VERSION);
     }
 
     #[test]
-    fn borrow_fee_calculation_host() {
-        let fees = ReserveFees {
-            borrow_fee_wad: 10_000_000_000_000_000, // 1%
-            reserve_owner_fee_wad: 10_000_000_000_000_000,
-            flash_loan_fee_wad: 0,
-            host_fee_percentage: 20,
+    fn yield_source_needs_current_pool_version() {
+        let pool = Pool {
+            version: PROGRAM_VERSION,
+            yield_source: YieldSource {
+                program_id: Pubkey::new_unique(),
+                reserve: Pubkey::new_unique(),
+                share_supply: Pubkey::new_unique(),
+                redeemable_amount: 42,
+            },
+            ..Pool::default()
         };
+        let mut data = [0u8; Pool::LEN];
+        Pool::pack(pool.clone(), &mut data).unwrap();
+        assert_eq!(Pool::unpack(&data).unwrap().yield_source, pool.yield_source);
 
-        let (total_fee, host_fee) = fees
-            .calculate_borrow_fees(Decimal::from(1000u64), FeeCalculation::Exclusive)
-            .unwrap();
-
-        assert_eq!(total_fee, 10); // 1% of 1000
-        assert_eq!(host_fee, 2); // 20% of 10
+        // the yield source lives in bytes version 1 pools left as padding
+        data[0] = 1;
+        assert_eq!(Pool::unpack(&data), Err(ProgramError::InvalidAccountData));
     }
 
     #[test]
-    fn borrow_fee_calculation_no_host() {
-        let fees = ReserveFees {
-            borrow_fee_wad: 10_000_000_000_000_000, // 1%
-            reserve_owner_fee_wad: 10_000_000_000_000_000,
-            flash_loan_fee_wad: 0,
-            host_fee_percentage: 0,
+    fn pool_pack_rejects_extra_tiers() {
+        let mut pool = Pool {
+            version: PROGRAM_VERSION,
+            ..Pool::default()
         };
+        pool.prize.tiers = vec![PrizeTier { percent: 10, winner_count: 1 }; MAX_PRIZE_TIERS + 1];
+        let mut data = [0u8; Pool::LEN];
+        assert_eq!(
+            Pool::pack(pool.clone(), &mut data),
+            Err(PoolingError::InvalidConfig.into())
+        );
 
-        let (total_fee, host_fee) = fees
-            .calculate_borrow_fees(Decimal::from(1000u64), FeeCalculation::Exclusive)
-            .unwrap();
-
-        assert_eq!(total_fee, 10); // 1% of 1000
-        assert_eq!(host_fee, 0); // 0 host fee
+        pool.prize.tiers.truncate(MAX_PRIZE_TIERS);
+        Pool::pack(pool.clone(), &mut data).unwrap();
+        assert_eq!(Pool::unpack(&data).unwrap().prize.tiers, pool.prize.tiers);
     }
 }
@@ -1,6 +1,8 @@
 use super::*;
+use crate::error::PoolingError;
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 use solana_program::{
+    entrypoint::ProgramResult,
     msg,
     program_error::ProgramError,
     program_pack::{IsInitialized, Pack, Sealed},
@@ -29,7 +31,12 @@ pub struct PoolManager {
     pub mine_mint: Pubkey,
     /// Supply address of mine token
     pub mine_supply_account: Pubkey,
-
+    /// Randomness oracle program id
+    pub randomness_program_id: Pubkey,
+    /// Kill switch pausing every operation of every pool of the pool manager
+    pub paused: bool,
+    /// Guardian authority which can pause, but never unpause, the pool manager and its pools
+    pub guardian: Pubkey,
 }
 
 impl PoolManager {
@@ -51,6 +58,52 @@ impl PoolManager {
         self.oracle_program_id = params.oracle_program_id;
         self.mine_mint = params.mine_mint;
         self.mine_supply_account = params.mine_supply_account;
+        self.randomness_program_id = params.randomness_program_id;
+        self.paused = false;
+        self.guardian = Pubkey::default();
+    }
+
+    /// Propose a new owner, who takes over once accepting the ownership.
+    /// Setting the default pubkey cancels a pending transfer.
+    pub fn set_pending_owner(&mut self, new_owner: Pubkey) {
+        self.pending_owner = new_owner;
+    }
+
+    /// Hand the ownership over to the pending owner
+    pub fn accept_owner(&mut self, pending_owner: &Pubkey) -> ProgramResult {
+        if self.pending_owner == Pubkey::default() {
+            msg!("Pool manager has no pending owner");
+            return Err(PoolingError::InvalidMarketOwner.into());
+        }
+        if &self.pending_owner != pending_owner {
+            msg!("Pool manager pending owner does not match the pending owner provided");
+            return Err(PoolingError::InvalidMarketOwner.into());
+        }
+        self.owner = self.pending_owner;
+        self.pending_owner = Pubkey::default();
+        Ok(())
+    }
+
+    /// Set the guardian. Setting the default pubkey revokes the guardian.
+    pub fn set_guardian(&mut self, guardian: Pubkey) {
+        self.guardian = guardian;
+    }
+
+    /// Check that `authority` may pause, or unpause when `unpause` is set.
+    /// The owner may do both, the guardian may only pause.
+    pub fn assert_pause_authority(&self, authority: &Pubkey, unpause: bool) -> ProgramResult {
+        if &self.owner == authority {
+            return Ok(());
+        }
+        if self.guardian == Pubkey::default() || &self.guardian != authority {
+            msg!("Pause authority is neither the pool manager owner nor its guardian");
+            return Err(PoolingError::InvalidPauseAuthority.into());
+        }
+        if unpause {
+            msg!("Pool manager guardian cannot unpause");
+            return Err(PoolingError::InvalidPauseAuthority.into());
+        }
+        Ok(())
     }
 }
 
@@ -71,7 +124,8 @@ pub struct InitPoolManagerParams {
     pub mine_mint: Pubkey,
     /// Supply address of mine token
     pub mine_supply_account: Pubkey,
-
+    /// Randomness oracle program id
+    pub randomness_program_id: Pubkey,
 }
 
 impl Sealed for PoolManager {}
@@ -84,7 +138,7 @@ impl IsInitialized for PoolManager {
 
 const POOL_MANAGER_LEN: usize = 354;
 
-// 1 + 1 + 32 + 32 + 32 + 32 + 32 + 128
+// 1 + 1 + 32 + 32 + 32 + 32 + 32 + 32 + 32 + 32 + 1 + 32 + 63
 impl Pack for PoolManager {
     const LEN: usize = POOL_MANAGER_LEN;
 
@@ -101,6 +155,9 @@ impl Pack for PoolManager {
             oracle_program_id,
             mine_mint,
             mine_supply_account,
+            randomness_program_id,
+            paused,
+            guardian,
             _padding,
         ) = mut_array_refs![
             output,
@@ -113,7 +170,10 @@ impl Pack for PoolManager {
             PUBKEY_BYTES,
             PUBKEY_BYTES,
             PUBKEY_BYTES,
-            128
+            PUBKEY_BYTES,
+            1,
+            PUBKEY_BYTES,
+            63
         ];
 
         *version = self.version.to_le_bytes();
@@ -125,6 +185,9 @@ impl Pack for PoolManager {
         pending_owner.copy_from_slice(self.pending_owner.as_ref());
         mine_mint.copy_from_slice(self.mine_mint.as_ref());
         mine_supply_account.copy_from_slice(self.mine_supply_account.as_ref());
+        randomness_program_id.copy_from_slice(self.randomness_program_id.as_ref());
+        pack_bool(self.paused, paused);
+        guardian.copy_from_slice(self.guardian.as_ref());
     }
 
     /// Unpacks a byte buffer into a [PoolManagerInfo](struct.PoolManagerInfo.html)
@@ -141,6 +204,9 @@ impl Pack for PoolManager {
             oracle_program_id,
             mine_mint,
             mine_supply_account,
+            randomness_program_id,
+            paused,
+            guardian,
             _padding,
         ) = array_refs![
             input,
@@ -153,7 +219,10 @@ impl Pack for PoolManager {
             PUBKEY_BYTES,
             PUBKEY_BYTES,
             PUBKEY_BYTES,
-            128
+            PUBKEY_BYTES,
+            1,
+            PUBKEY_BYTES,
+            63
         ];
 
         let version = u8::from_le_bytes(*version);
@@ -172,6 +241,103 @@ impl Pack for PoolManager {
             oracle_program_id: Pubkey::new_from_array(*oracle_program_id),
             mine_mint: Pubkey::new_from_array(*mine_mint),
             mine_supply_account: Pubkey::new_from_array(*mine_supply_account),
+            randomness_program_id: Pubkey::new_from_array(*randomness_program_id),
+            paused: unpack_bool(paused)?,
+            guardian: Pubkey::new_from_array(*guardian),
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pack_round_trip() {
+        let mut quote_currency = [0u8; 32];
+        quote_currency[..3].copy_from_slice(b"USD");
+        let mut pool_manager = PoolManager::new(InitPoolManagerParams {
+            bump_seed: 254,
+            owner: Pubkey::new_unique(),
+            quote_currency,
+            token_program_id: Pubkey::new_unique(),
+            oracle_program_id: Pubkey::new_unique(),
+            mine_mint: Pubkey::new_unique(),
+            mine_supply_account: Pubkey::new_unique(),
+            randomness_program_id: Pubkey::new_unique(),
+        });
+        pool_manager.set_pending_owner(Pubkey::new_unique());
+        pool_manager.set_guardian(Pubkey::new_unique());
+        pool_manager.paused = true;
+
+        let mut data = [0u8; PoolManager::LEN];
+        PoolManager::pack(pool_manager.clone(), &mut data).unwrap();
+        assert_eq!(PoolManager::unpack(&data).unwrap(), pool_manager);
+    }
+
+    #[test]
+    fn two_step_owner_transfer() {
+        let owner = Pubkey::new_unique();
+        let new_owner = Pubkey::new_unique();
+        let mut pool_manager = PoolManager {
+            owner,
+            ..PoolManager::default()
+        };
+        assert_eq!(
+            pool_manager.accept_owner(&new_owner),
+            Err(PoolingError::InvalidMarketOwner.into())
+        );
+
+        pool_manager.set_pending_owner(new_owner);
+        assert_eq!(
+            pool_manager.accept_owner(&owner),
+            Err(PoolingError::InvalidMarketOwner.into())
+        );
+        assert_eq!(pool_manager.owner, owner);
+
+        // cancelled transfers cannot be accepted
+        pool_manager.set_pending_owner(Pubkey::default());
+        assert_eq!(
+            pool_manager.accept_owner(&new_owner),
+            Err(PoolingError::InvalidMarketOwner.into())
+        );
+
+        pool_manager.set_pending_owner(new_owner);
+        pool_manager.accept_owner(&new_owner).unwrap();
+        assert_eq!(pool_manager.owner, new_owner);
+        assert_eq!(pool_manager.pending_owner, Pubkey::default());
+    }
+
+    #[test]
+    fn guardian_can_only_pause() {
+        let owner = Pubkey::new_unique();
+        let guardian = Pubkey::new_unique();
+        let mut pool_manager = PoolManager {
+            owner,
+            ..PoolManager::default()
+        };
+        assert_eq!(pool_manager.assert_pause_authority(&owner, true), Ok(()));
+        // no guardian is set, the default pubkey must not match it
+        assert_eq!(
+            pool_manager.assert_pause_authority(&Pubkey::default(), false),
+            Err(PoolingError::InvalidPauseAuthority.into())
+        );
+
+        pool_manager.set_guardian(guardian);
+        assert_eq!(pool_manager.assert_pause_authority(&guardian, false), Ok(()));
+        assert_eq!(
+            pool_manager.assert_pause_authority(&guardian, true),
+            Err(PoolingError::InvalidPauseAuthority.into())
+        );
+        assert_eq!(
+            pool_manager.assert_pause_authority(&Pubkey::new_unique(), false),
+            Err(PoolingError::InvalidPauseAuthority.into())
+        );
+
+        pool_manager.set_guardian(Pubkey::default());
+        assert_eq!(
+            pool_manager.assert_pause_authority(&guardian, false),
+            Err(PoolingError::InvalidPauseAuthority.into())
+        );
+    }
+}
@@ -0,0 +1,788 @@
+use crate::{
+    error::PoolingError,
+    math::{Decimal, Rate, TryDiv, TryMul},
+};
+use solana_program::{
+    clock::Slot,
+    entrypoint::ProgramResult,
+    hash::{hash, hashv},
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    slot_hashes::MAX_ENTRIES,
+};
+
+/// Max number of prize tiers of a pool
+pub const MAX_PRIZE_TIERS: usize = 4;
+
+/// Max number of draws a prize contribution can be spread over
+pub const MAX_CONTRIBUTION_DRAWS: usize = 4;
+
+/// Max number of picks a draw makes on top of one per winner. Once they are used up the draw
+/// completes, and the prize of the winners left unawarded is carried over to the next draw.
+pub const MAX_DRAW_REPICKS: u64 = 32;
+
+/// Prize tier, sharing a percentage of the prize among a number of winners
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PrizeTier {
+    /// Percentage of the prize awarded to this tier
+    pub percent: u8,
+    /// Number of winners sharing the tier prize
+    pub winner_count: u8,
+}
+
+impl PrizeTier {
+    /// Single tier awarding the whole prize to one winner
+    pub fn grand_prize() -> Self {
+        Self {
+            percent: 100,
+            winner_count: 1,
+        }
+    }
+}
+
+/// Prize draw state of a pool
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Prize {
+    /// Slot of the last completed draw
+    pub last_draw_slot: Slot,
+    /// Ticket account which won the grand prize of the last draw
+    pub last_winner: Pubkey,
+    /// Total amount of liquidity awarded by the last draw
+    pub last_prize_amount: u64,
+    /// Total amount of liquidity awarded to winners and not claimed yet
+    pub unclaimed_amount: u64,
+    /// Part of the prize available the protocol fee was already taken from, left over by draws
+    pub taxed_amount: u64,
+    /// Length of a prize period in slots
+    pub period_slots: u64,
+    /// Slot at which the current prize period started
+    pub period_start_slot: Slot,
+    /// Number of draws completed so far
+    pub draw_count: u64,
+    /// Whether a draw has been started and waits to be settled
+    pub draw_pending: bool,
+    /// Hash of the secret committed when the pending draw started, replaced by the secret
+    /// once revealed
+    pub pending_commitment: [u8; 32],
+    /// Slot at which the pending draw started
+    pub pending_commit_slot: Slot,
+    /// Slot at which the secret of the pending draw was revealed, 0 until then
+    pub reveal_slot: Slot,
+    /// Randomness result account requested for the next draw, default until requested
+    pub randomness_account: Pubkey,
+    /// Prize distribution, from the grand prize down
+    pub tiers: Vec<PrizeTier>,
+    /// Contributions held back for the draws following the next one, in draw order
+    pub deferred_contributions: [u64; MAX_CONTRIBUTION_DRAWS - 1],
+    /// Cumulative ticket balance of the prize period indexed for the draw so far. Each indexed
+    /// ticket holds the range of the index starting where the index stood when it was added.
+    pub indexed_balance: u128,
+    /// Number of tickets indexed for the draw
+    pub indexed_tickets: u64,
+    /// Whether the draw is seeded and awards its winners
+    pub awarding: bool,
+    /// Seed of the draw awarding its winners
+    pub draw_seed: [u8; 32],
+    /// Prize shared by the winners of the draw awarding its winners
+    pub draw_prize_amount: u64,
+    /// Number of picks made by the draw awarding its winners, including picks of tickets which
+    /// already won
+    pub draw_picks: u64,
+    /// Number of winners awarded by the draw so far
+    pub winners_awarded: u64,
+    /// Total amount of liquidity awarded by the draw so far
+    pub awarded_amount: u64,
+    /// Ticket account which won the grand prize of the draw awarding its winners
+    pub draw_winner: Pubkey,
+}
+
+pub struct InitPrizeParams {
+    pub current_slot: Slot,
+    pub period_slots: u64,
+    pub tiers: Vec<PrizeTier>,
+}
+
+impl Prize {
+    pub fn new(params: InitPrizeParams) -> Self {
+        Self {
+            period_slots: params.period_slots,
+            period_start_slot: params.current_slot,
+            tiers: params.tiers,
+            ..Self::default()
+        }
+    }
+
+    /// Check a prize distribution: at least one tier, each with winners, sharing at most 100%
+    pub fn validate_tiers(tiers: &[PrizeTier]) -> ProgramResult {
+        if tiers.is_empty() || tiers.len() > MAX_PRIZE_TIERS {
+            msg!("Prize must have between 1 and {} tiers", MAX_PRIZE_TIERS);
+            return Err(PoolingError::InvalidConfig.into());
+        }
+        if tiers.iter().any(|tier| tier.winner_count == 0) {
+            msg!("Prize tier must have at least one winner");
+            return Err(PoolingError::InvalidConfig.into());
+        }
+        let total_percent: u16 = tiers.iter().map(|tier| tier.percent as u16).sum();
+        if total_percent > 100 {
+            msg!("Prize tiers cannot share more than 100% of the prize");
+            return Err(PoolingError::InvalidConfig.into());
+        }
+        Ok(())
+    }
+
+    /// Prize of each winner of `tier`, rounded down
+    pub fn tier_winner_prize(tier: &PrizeTier, prize_amount: u64) -> Result<u64, ProgramError> {
+        Decimal::from(prize_amount)
+            .try_mul(Rate::from_percent(tier.percent))?
+            .try_div(tier.winner_count as u64)?
+            .try_floor_u64()
+    }
+
+    /// Spread a contribution evenly over the next `draws` draws. Returns the share of the next
+    /// draw, which also takes the rounding remainder, while the other shares are held back.
+    pub fn schedule_contribution(&mut self, amount: u64, draws: u8) -> Result<u64, ProgramError> {
+        if draws == 0 || draws as usize > MAX_CONTRIBUTION_DRAWS {
+            msg!("Contribution must be spread over 1 to {} draws", MAX_CONTRIBUTION_DRAWS);
+            return Err(PoolingError::InvalidAmount.into());
+        }
+        let share = amount / draws as u64;
+        for deferred in self.deferred_contributions.iter_mut().take(draws as usize - 1) {
+            *deferred = deferred
+                .checked_add(share)
+                .ok_or(PoolingError::MathOverflow)?;
+        }
+        Ok(amount - share * (draws as u64 - 1))
+    }
+
+    /// Total of the contributions held back for later draws
+    pub fn deferred_contribution_amount(&self) -> Result<u64, ProgramError> {
+        Ok(self
+            .deferred_contributions
+            .iter()
+            .try_fold(0u64, |total, deferred| total.checked_add(*deferred))
+            .ok_or(PoolingError::MathOverflow)?)
+    }
+
+    /// Slot at which the current prize period ends
+    pub fn period_end_slot(&self) -> Result<Slot, ProgramError> {
+        Ok(self
+            .period_start_slot
+            .checked_add(self.period_slots)
+            .ok_or(PoolingError::MathOverflow)?)
+    }
+
+    /// Check if the current prize period is over, so a draw can take place
+    pub fn is_period_over(&self, slot: Slot) -> Result<bool, ProgramError> {
+        Ok(slot >= self.period_end_slot()?)
+    }
+
+    /// Check if the pending draw can no longer go on: an unrevealed draw once MAX_ENTRIES slots
+    /// have passed since its commit, a revealed one once the hash of its reveal slot has left
+    /// the SlotHashes sysvar
+    pub fn is_draw_expired(&self, slot: Slot) -> Result<bool, ProgramError> {
+        let start_slot = if self.reveal_slot > 0 {
+            self.reveal_slot
+        } else {
+            self.pending_commit_slot
+        };
+        let slots_elapsed = slot
+            .checked_sub(start_slot)
+            .ok_or(PoolingError::MathOverflow)?;
+        Ok(slots_elapsed >= MAX_ENTRIES as u64)
+    }
+
+    /// Commit to the hash of a secret to start a draw. A pending draw can only be replaced
+    /// once it has expired.
+    pub fn start_draw(&mut self, commitment: [u8; 32], slot: Slot) -> ProgramResult {
+        if !self.is_period_over(slot)? {
+            msg!("Prize period is not over until slot {}", self.period_end_slot()?);
+            return Err(PoolingError::PrizePeriodNotOver.into());
+        }
+        if self.draw_pending && !self.is_draw_expired(slot)? {
+            msg!("Draw started at slot {} is still pending", self.pending_commit_slot);
+            return Err(PoolingError::DrawAlreadyPending.into());
+        }
+        if self.randomness_account != Pubkey::default() {
+            msg!("Next draw is bound to randomness result {}", self.randomness_account);
+            return Err(PoolingError::RandomnessAlreadyRequested.into());
+        }
+        if self.awarding {
+            msg!("Draw is still awarding its winners");
+            return Err(PoolingError::DrawAwardingWinners.into());
+        }
+        self.draw_pending = true;
+        self.pending_commitment = commitment;
+        self.pending_commit_slot = slot;
+        self.reveal_slot = 0;
+        Ok(())
+    }
+
+    /// Check the revealed secret against the commitment and keep it until the draw is settled
+    /// with the hash of the reveal slot, which nobody knows at reveal time
+    pub fn reveal_draw(&mut self, secret: &[u8; 32], slot: Slot) -> ProgramResult {
+        if !self.draw_pending {
+            msg!("No draw has been started");
+            return Err(PoolingError::DrawNotPending.into());
+        }
+        if self.reveal_slot > 0 {
+            msg!("Draw secret was already revealed at slot {}", self.reveal_slot);
+            return Err(PoolingError::DrawAlreadyRevealed.into());
+        }
+        if slot <= self.pending_commit_slot {
+            msg!("Draw must be revealed after slot {}", self.pending_commit_slot);
+            return Err(PoolingError::DrawRevealTooEarly.into());
+        }
+        if self.is_draw_expired(slot)? {
+            msg!("Draw started at slot {} has expired", self.pending_commit_slot);
+            return Err(PoolingError::DrawExpired.into());
+        }
+        if hash(secret).to_bytes() != self.pending_commitment {
+            msg!("Revealed secret does not match the commitment");
+            return Err(PoolingError::DrawRevealMismatch.into());
+        }
+        self.pending_commitment = *secret;
+        self.reveal_slot = slot;
+        Ok(())
+    }
+
+    /// Bind the next draw to a randomness result requested after the prize period ended. The
+    /// request cannot be replaced, so the draw cannot pick among several results.
+    pub fn request_randomness(&mut self, randomness_account: Pubkey, slot: Slot) -> ProgramResult {
+        if !self.is_period_over(slot)? {
+            msg!("Prize period is not over until slot {}", self.period_end_slot()?);
+            return Err(PoolingError::PrizePeriodNotOver.into());
+        }
+        if self.draw_pending {
+            msg!("Draw started at slot {} is still pending", self.pending_commit_slot);
+            return Err(PoolingError::DrawAlreadyPending.into());
+        }
+        if self.randomness_account != Pubkey::default() {
+            msg!("Next draw is already bound to randomness result {}", self.randomness_account);
+            return Err(PoolingError::RandomnessAlreadyRequested.into());
+        }
+        if self.awarding {
+            msg!("Draw is still awarding its winners");
+            return Err(PoolingError::DrawAwardingWinners.into());
+        }
+        self.randomness_account = randomness_account;
+        Ok(())
+    }
+
+    /// Clear the revealed draw and return its secret and reveal slot, whose hash seeds the draw
+    pub fn settle_draw(&mut self, slot: Slot) -> Result<([u8; 32], Slot), ProgramError> {
+        if !self.draw_pending || self.reveal_slot == 0 {
+            msg!("Draw secret has not been revealed");
+            return Err(PoolingError::DrawNotRevealed.into());
+        }
+        if slot <= self.reveal_slot {
+            msg!("Draw must be settled after slot {}", self.reveal_slot);
+            return Err(PoolingError::DrawSettleTooEarly.into());
+        }
+        let revealed = (self.pending_commitment, self.reveal_slot);
+        self.draw_pending = false;
+        self.pending_commitment = [0u8; 32];
+        self.reveal_slot = 0;
+        Ok(revealed)
+    }
+
+    /// Number of the draw of the current prize period, counting from 1
+    pub fn draw_number(&self) -> Result<u64, ProgramError> {
+        Ok(self
+            .draw_count
+            .checked_add(1)
+            .ok_or(PoolingError::MathOverflow)?)
+    }
+
+    /// Add `cumulative_balance` of a ticket to the draw index and return the start of the range
+    /// it holds. The index cannot grow past `total_balance`, the cumulative ticket balance of the
+    /// prize period.
+    pub fn index_ticket(
+        &mut self,
+        cumulative_balance: u128,
+        total_balance: u128,
+    ) -> Result<u128, ProgramError> {
+        if self.awarding {
+            msg!("Draw is already awarding its winners");
+            return Err(PoolingError::DrawAwardingWinners.into());
+        }
+        let offset = self.indexed_balance;
+        let indexed_balance = offset
+            .checked_add(cumulative_balance)
+            .ok_or(PoolingError::MathOverflow)?;
+        if indexed_balance > total_balance {
+            msg!("Draw index cannot exceed the {} cumulative ticket balance", total_balance);
+            return Err(PoolingError::InvalidAccountInput.into());
+        }
+        self.indexed_balance = indexed_balance;
+        self.indexed_tickets = self
+            .indexed_tickets
+            .checked_add(1)
+            .ok_or(PoolingError::MathOverflow)?;
+        Ok(offset)
+    }
+
+    /// Check that the draw index covers `total_balance`, so no ticket is left out of the draw
+    pub fn check_indexed(&self, total_balance: u128) -> ProgramResult {
+        if self.indexed_balance != total_balance {
+            msg!(
+                "Tickets indexed hold {} of the {} cumulative ticket balance of the period",
+                self.indexed_balance,
+                total_balance
+            );
+            return Err(PoolingError::DrawTicketsIncomplete.into());
+        }
+        Ok(())
+    }
+
+    /// Seed the draw and start awarding its winners, sharing `prize_amount`
+    pub fn start_awarding(&mut self, seed: [u8; 32], prize_amount: u64) -> ProgramResult {
+        if self.awarding {
+            msg!("Draw is already awarding its winners");
+            return Err(PoolingError::DrawAwardingWinners.into());
+        }
+        self.awarding = true;
+        self.draw_seed = seed;
+        self.draw_prize_amount = prize_amount;
+        self.draw_picks = 0;
+        self.winners_awarded = 0;
+        self.awarded_amount = 0;
+        self.draw_winner = Pubkey::default();
+        Ok(())
+    }
+
+    /// Point of the draw index picked next, below `total_balance`
+    pub fn pick_target(&self, total_balance: u128) -> Result<u128, ProgramError> {
+        if total_balance == 0 {
+            msg!("Nothing to pick from an empty draw index");
+            return Err(PoolingError::MathOverflow.into());
+        }
+        let random_hash = hashv(&[&self.draw_seed, &self.draw_picks.to_le_bytes()]);
+        let mut random = [0u8; 16];
+        random.copy_from_slice(&random_hash.as_ref()[..16]);
+        Ok(u128::from_le_bytes(random) % total_balance)
+    }
+
+    /// Number of winners of a draw, over every tier
+    pub fn winner_count(&self) -> u64 {
+        self.tiers.iter().map(|tier| tier.winner_count as u64).sum()
+    }
+
+    /// Max number of picks of the draw, so picks landing again and again on tickets which
+    /// already won cannot keep the draw from completing
+    pub fn max_draw_picks(&self) -> u64 {
+        self.winner_count().saturating_add(MAX_DRAW_REPICKS)
+    }
+
+    /// Check if the draw is done awarding its winners: every tier is filled, every indexed
+    /// ticket won, or the draw made its max number of picks
+    pub fn is_awarding_complete(&self) -> bool {
+        self.winners_awarded >= self.winner_count().min(self.indexed_tickets)
+            || self.draw_picks >= self.max_draw_picks()
+    }
+
+    /// Prize of the next winner, filling the tiers from the grand prize down
+    pub fn next_award_amount(&self) -> Result<u64, ProgramError> {
+        let mut winner_count: u64 = 0;
+        for tier in &self.tiers {
+            winner_count += tier.winner_count as u64;
+            if self.winners_awarded < winner_count {
+                return Self::tier_winner_prize(tier, self.draw_prize_amount);
+            }
+        }
+        msg!("Every prize tier is filled");
+        Err(PoolingError::InvalidAmount.into())
+    }
+
+    /// Pick again, the last pick landed on a ticket which already won in the draw
+    pub fn skip_pick(&mut self) -> ProgramResult {
+        self.draw_picks = self
+            .draw_picks
+            .checked_add(1)
+            .ok_or(PoolingError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Record the prize awarded to the winner of the last pick
+    pub fn record_award(&mut self, winner: Pubkey, prize_amount: u64) -> ProgramResult {
+        if self.winners_awarded == 0 {
+            self.draw_winner = winner;
+        }
+        self.winners_awarded = self
+            .winners_awarded
+            .checked_add(1)
+            .ok_or(PoolingError::MathOverflow)?;
+        self.awarded_amount = self
+            .awarded_amount
+            .checked_add(prize_amount)
+            .ok_or(PoolingError::MathOverflow)?;
+        self.skip_pick()
+    }
+
+    /// Record the draw once it is done awarding its winners
+    pub fn complete_awarding(&mut self, slot: Slot) -> ProgramResult {
+        if !self.awarding || !self.is_awarding_complete() {
+            msg!("Draw winners are not all awarded yet");
+            return Err(PoolingError::DrawNotAwarding.into());
+        }
+        self.record_draw(slot, self.draw_winner, self.awarded_amount)
+    }
+
+    /// Record the result of a completed draw and start the period containing `slot`
+    pub fn record_draw(&mut self, slot: Slot, winner: Pubkey, prize_amount: u64) -> ProgramResult {
+        if !self.is_period_over(slot)? {
+            msg!("Prize period is not over until slot {}", self.period_end_slot()?);
+            return Err(PoolingError::PrizePeriodNotOver.into());
+        }
+        let slots_elapsed = slot
+            .checked_sub(self.period_start_slot)
+            .ok_or(PoolingError::MathOverflow)?;
+        let periods_elapsed = slots_elapsed / self.period_slots;
+        self.period_start_slot = self
+            .period_start_slot
+            .checked_add(periods_elapsed * self.period_slots)
+            .ok_or(PoolingError::MathOverflow)?;
+        self.draw_count = self
+            .draw_count
+            .checked_add(1)
+            .ok_or(PoolingError::MathOverflow)?;
+        self.last_draw_slot = slot;
+        self.last_winner = winner;
+        self.last_prize_amount = prize_amount;
+        self.randomness_account = Pubkey::default();
+        self.indexed_balance = 0;
+        self.indexed_tickets = 0;
+        self.awarding = false;
+        self.draw_seed = [0u8; 32];
+        self.draw_prize_amount = 0;
+        self.draw_picks = 0;
+        self.winners_awarded = 0;
+        self.awarded_amount = 0;
+        self.draw_winner = Pubkey::default();
+        // release the contribution of the next draw
+        self.deferred_contributions.rotate_left(1);
+        self.deferred_contributions[MAX_CONTRIBUTION_DRAWS - 2] = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn record_draw_once_per_period() {
+        let mut prize = Prize::new(InitPrizeParams {
+            current_slot: 100,
+            period_slots: 50,
+            tiers: vec![PrizeTier::grand_prize()],
+        });
+        assert!(!prize.is_period_over(149).unwrap());
+        assert_eq!(
+            prize.record_draw(149, Pubkey::default(), 0),
+            Err(PoolingError::PrizePeriodNotOver.into())
+        );
+
+        prize.record_draw(160, Pubkey::default(), 10).unwrap();
+        assert_eq!(prize.period_start_slot, 150);
+        assert_eq!(prize.draw_count, 1);
+        assert_eq!(prize.last_draw_slot, 160);
+        assert_eq!(
+            prize.record_draw(199, Pubkey::default(), 0),
+            Err(PoolingError::PrizePeriodNotOver.into())
+        );
+
+        // late draws start the period containing the draw slot
+        prize.record_draw(330, Pubkey::default(), 10).unwrap();
+        assert_eq!(prize.period_start_slot, 300);
+        assert_eq!(prize.draw_count, 2);
+    }
+
+    #[test]
+    fn commit_and_reveal_draw() {
+        let secret = [7u8; 32];
+        let mut prize = Prize::new(InitPrizeParams {
+            current_slot: 0,
+            period_slots: 10,
+            tiers: vec![PrizeTier::grand_prize()],
+        });
+        assert_eq!(
+            prize.start_draw(hash(&secret).to_bytes(), 9),
+            Err(PoolingError::PrizePeriodNotOver.into())
+        );
+        assert_eq!(
+            prize.reveal_draw(&secret, 11),
+            Err(PoolingError::DrawNotPending.into())
+        );
+
+        prize.start_draw(hash(&secret).to_bytes(), 10).unwrap();
+        assert_eq!(
+            prize.start_draw([0u8; 32], 11),
+            Err(PoolingError::DrawAlreadyPending.into())
+        );
+        assert_eq!(
+            prize.reveal_draw(&secret, 10),
+            Err(PoolingError::DrawRevealTooEarly.into())
+        );
+        assert_eq!(
+            prize.reveal_draw(&[8u8; 32], 11),
+            Err(PoolingError::DrawRevealMismatch.into())
+        );
+        assert_eq!(
+            prize.settle_draw(11),
+            Err(PoolingError::DrawNotRevealed.into())
+        );
+        prize.reveal_draw(&secret, 11).unwrap();
+        assert!(prize.draw_pending);
+        assert_eq!(prize.reveal_slot, 11);
+        assert_eq!(
+            prize.reveal_draw(&secret, 12),
+            Err(PoolingError::DrawAlreadyRevealed.into())
+        );
+        assert_eq!(
+            prize.start_draw([0u8; 32], 12),
+            Err(PoolingError::DrawAlreadyPending.into())
+        );
+        assert_eq!(
+            prize.settle_draw(11),
+            Err(PoolingError::DrawSettleTooEarly.into())
+        );
+        assert_eq!(prize.settle_draw(12).unwrap(), (secret, 11));
+        assert!(!prize.draw_pending);
+        assert_eq!(prize.reveal_slot, 0);
+        assert_eq!(
+            prize.settle_draw(13),
+            Err(PoolingError::DrawNotRevealed.into())
+        );
+    }
+
+    #[test]
+    fn expired_draw_can_be_restarted() {
+        let mut prize = Prize::new(InitPrizeParams {
+            current_slot: 0,
+            period_slots: 10,
+            tiers: vec![PrizeTier::grand_prize()],
+        });
+        prize.start_draw([1u8; 32], 10).unwrap();
+        let expiry_slot = 10 + MAX_ENTRIES as u64;
+        assert!(!prize.is_draw_expired(expiry_slot - 1).unwrap());
+        assert!(prize.is_draw_expired(expiry_slot).unwrap());
+        assert_eq!(
+            prize.reveal_draw(&[0u8; 32], expiry_slot),
+            Err(PoolingError::DrawExpired.into())
+        );
+        prize.start_draw(hash(&[2u8; 32]).to_bytes(), expiry_slot).unwrap();
+        assert_eq!(prize.pending_commitment, hash(&[2u8; 32]).to_bytes());
+        assert_eq!(prize.pending_commit_slot, expiry_slot);
+
+        // a revealed draw expires once the hash of its reveal slot is gone
+        prize.reveal_draw(&[2u8; 32], expiry_slot + 100).unwrap();
+        assert!(!prize.is_draw_expired(expiry_slot + 100 + MAX_ENTRIES as u64 - 1).unwrap());
+        prize.start_draw([3u8; 32], expiry_slot + 100 + MAX_ENTRIES as u64).unwrap();
+        assert_eq!(prize.reveal_slot, 0);
+    }
+
+    #[test]
+    fn randomness_requested_once_per_draw() {
+        let randomness_account = Pubkey::new_unique();
+        let mut prize = Prize::new(InitPrizeParams {
+            current_slot: 0,
+            period_slots: 10,
+            tiers: vec![PrizeTier::grand_prize()],
+        });
+        assert_eq!(
+            prize.request_randomness(randomness_account, 9),
+            Err(PoolingError::PrizePeriodNotOver.into())
+        );
+        prize.request_randomness(randomness_account, 10).unwrap();
+        assert_eq!(prize.randomness_account, randomness_account);
+        assert_eq!(
+            prize.request_randomness(Pubkey::new_unique(), 11),
+            Err(PoolingError::RandomnessAlreadyRequested.into())
+        );
+        assert_eq!(
+            prize.start_draw([0u8; 32], 11),
+            Err(PoolingError::RandomnessAlreadyRequested.into())
+        );
+
+        prize.record_draw(12, Pubkey::default(), 0).unwrap();
+        assert_eq!(prize.randomness_account, Pubkey::default());
+        prize.start_draw([0u8; 32], 20).unwrap();
+        assert_eq!(
+            prize.request_randomness(randomness_account, 20),
+            Err(PoolingError::DrawAlreadyPending.into())
+        );
+    }
+
+    #[test]
+    fn validate_tiers() {
+        assert!(Prize::validate_tiers(&[PrizeTier::grand_prize()]).is_ok());
+        assert!(Prize::validate_tiers(&[]).is_err());
+        assert!(Prize::validate_tiers(&[PrizeTier { percent: 50, winner_count: 0 }]).is_err());
+        assert!(Prize::validate_tiers(&[
+            PrizeTier { percent: 60, winner_count: 1 },
+            PrizeTier { percent: 41, winner_count: 3 },
+        ])
+        .is_err());
+        assert!(Prize::validate_tiers(&[PrizeTier { percent: 20, winner_count: 1 }; 5]).is_err());
+    }
+
+    #[test]
+    fn draw_index_covers_period_balance() {
+        let mut prize = Prize::default();
+        assert_eq!(prize.index_ticket(30, 100).unwrap(), 0);
+        assert_eq!(
+            prize.check_indexed(100),
+            Err(PoolingError::DrawTicketsIncomplete.into())
+        );
+        assert_eq!(
+            prize.index_ticket(71, 100),
+            Err(PoolingError::InvalidAccountInput.into())
+        );
+        assert_eq!(prize.index_ticket(70, 100).unwrap(), 30);
+        assert_eq!(prize.indexed_tickets, 2);
+        prize.check_indexed(100).unwrap();
+
+        prize.start_awarding([3u8; 32], 1000).unwrap();
+        assert_eq!(
+            prize.index_ticket(0, 100),
+            Err(PoolingError::DrawAwardingWinners.into())
+        );
+    }
+
+    #[test]
+    fn award_tier_winners() {
+        let mut prize = Prize {
+            period_slots: 10,
+            tiers: vec![
+                PrizeTier { percent: 50, winner_count: 1 },
+                PrizeTier { percent: 40, winner_count: 3 },
+            ],
+            indexed_balance: 100,
+            indexed_tickets: 5,
+            ..Prize::default()
+        };
+        prize.start_awarding([3u8; 32], 1000).unwrap();
+        let grand_winner = Pubkey::new_unique();
+        assert_eq!(prize.next_award_amount().unwrap(), 500);
+        prize.record_award(grand_winner, 500).unwrap();
+
+        // a pick landing on a winner is picked again
+        let target = prize.pick_target(100).unwrap();
+        prize.skip_pick().unwrap();
+        assert_ne!(prize.pick_target(100).unwrap(), target);
+        for _ in 0..3 {
+            assert!(!prize.is_awarding_complete());
+            assert_eq!(prize.next_award_amount().unwrap(), 133);
+            prize.record_award(Pubkey::new_unique(), 133).unwrap();
+        }
+        assert!(prize.is_awarding_complete());
+        assert!(prize.next_award_amount().is_err());
+        assert_eq!(prize.draw_picks, 5);
+
+        prize.complete_awarding(10).unwrap();
+        assert_eq!(prize.last_winner, grand_winner);
+        assert_eq!(prize.last_prize_amount, 899);
+        assert_eq!(prize.draw_count, 1);
+        assert!(!prize.awarding);
+        assert_eq!(prize.indexed_balance, 0);
+        assert_eq!(prize.indexed_tickets, 0);
+    }
+
+    #[test]
+    fn award_fewer_winners_than_tickets() {
+        let mut prize = Prize {
+            tiers: vec![
+                PrizeTier { percent: 50, winner_count: 1 },
+                PrizeTier { percent: 40, winner_count: 3 },
+            ],
+            indexed_balance: 7,
+            indexed_tickets: 1,
+            ..Prize::default()
+        };
+        assert_eq!(
+            prize.complete_awarding(0),
+            Err(PoolingError::DrawNotAwarding.into())
+        );
+        prize.start_awarding([3u8; 32], 1000).unwrap();
+        assert!(!prize.is_awarding_complete());
+        prize.record_award(Pubkey::new_unique(), 500).unwrap();
+        assert!(prize.is_awarding_complete());
+    }
+
+    #[test]
+    fn skewed_draw_completes_after_max_picks() {
+        // a dust ticket holding a tiny range next to a whale ticket
+        let whale_balance = 1_000_000_000_000_000_000u128;
+        let total_balance = whale_balance + 1;
+        let mut prize = Prize {
+            period_slots: 10,
+            tiers: vec![
+                PrizeTier { percent: 50, winner_count: 1 },
+                PrizeTier { percent: 50, winner_count: 1 },
+            ],
+            indexed_balance: total_balance,
+            indexed_tickets: 2,
+            ..Prize::default()
+        };
+        prize.start_awarding([7u8; 32], 1000).unwrap();
+        let whale = Pubkey::new_unique();
+        let mut whale_won = false;
+        while !prize.is_awarding_complete() {
+            assert!(prize.pick_target(total_balance).unwrap() < whale_balance);
+            if whale_won {
+                prize.skip_pick().unwrap();
+            } else {
+                prize.record_award(whale, prize.next_award_amount().unwrap()).unwrap();
+                whale_won = true;
+            }
+        }
+        assert_eq!(prize.draw_picks, 2 + MAX_DRAW_REPICKS);
+        assert_eq!(prize.winners_awarded, 1);
+
+        // the prize of the second tier is left for the next draw
+        prize.complete_awarding(10).unwrap();
+        assert_eq!(prize.last_winner, whale);
+        assert_eq!(prize.last_prize_amount, 500);
+        assert_eq!(prize.draw_count, 1);
+        assert!(!prize.awarding);
+    }
+
+    #[test]
+    fn contribution_spread_over_draws() {
+        let mut prize = Prize::new(InitPrizeParams {
+            current_slot: 0,
+            period_slots: 10,
+            tiers: vec![PrizeTier::grand_prize()],
+        });
+        assert_eq!(prize.schedule_contribution(100, 1).unwrap(), 100);
+        assert_eq!(prize.deferred_contribution_amount().unwrap(), 0);
+        assert_eq!(prize.schedule_contribution(100, 3).unwrap(), 34);
+        assert_eq!(prize.schedule_contribution(40, 2).unwrap(), 20);
+        assert_eq!(prize.deferred_contributions, [53, 33, 0]);
+        assert!(prize.schedule_contribution(100, 0).is_err());
+        assert!(prize.schedule_contribution(100, MAX_CONTRIBUTION_DRAWS as u8 + 1).is_err());
+
+        prize.record_draw(10, Pubkey::default(), 0).unwrap();
+        assert_eq!(prize.deferred_contributions, [33, 0, 0]);
+        prize.record_draw(20, Pubkey::default(), 0).unwrap();
+        assert_eq!(prize.deferred_contribution_amount().unwrap(), 0);
+    }
+
+    proptest! {
+        #[test]
+        fn pick_target_within_index(
+            seed in prop::array::uniform32(0..=u8::MAX),
+            draw_picks in 0..=u64::MAX,
+            total_balance in 1..=u128::MAX,
+        ) {
+            let prize = Prize {
+                draw_seed: seed,
+                draw_picks,
+                ..Prize::default()
+            };
+            assert!(prize.pick_target(total_balance)? < total_balance);
+        }
+    }
+}
@@ -38,6 +38,8 @@ pub struct Ticket {
     pub deposited_value: Decimal,
     /// Total unclaimed mine for the  in context
     pub unclaimed_mine: Decimal,
+    /// Whether the deposits sponsor the prize, earning for it without taking part in draws
+    pub sponsor: bool,
 }
 
 impl Ticket {
@@ -55,6 +57,7 @@ impl Ticket {
         self.pool_manager = params.pool_manager;
         self.owner = params.owner;
         self.deposits = params.deposits;
+        self.sponsor = params.sponsor;
     }
 
     /// Accrue mine for this ticket account  from this reserve in context (only for the portion of collaterized LToken)
@@ -69,22 +72,110 @@ impl Ticket {
         Ok(())
     }
 
-    pub fn deposit(&mut self, index: usize, collateral_amount: u64) -> ProgramResult {
+    /// Take the whole tokens of the unclaimed mine, the fractional remainder stays unclaimed
+    pub fn claim_mine(&mut self) -> Result<u64, ProgramError> {
+        claim_whole_mine(&mut self.unclaimed_mine)
+    }
+
+    /// Deposit collateral, accumulating the time-weighted balance of the prize period first.
+    /// Restarts the exit fee window of the whole deposit.
+    pub fn deposit(
+        &mut self,
+        index: usize,
+        collateral_amount: u64,
+        slot: Slot,
+        prize: &Prize,
+    ) -> ProgramResult {
         let liquidity = &mut self.deposits[index];
+        liquidity.twab.accrue(liquidity.deposited_amount, slot, prize)?;
         liquidity.deposit(collateral_amount)?;
+        liquidity.deposit_slot = slot;
         Ok(())
     }
-    /// Withdraw collateral and remove it from deposits if zeroed out
-    pub fn withdraw(&mut self, withdraw_amount: u64, collateral_index: usize) -> ProgramResult {
+
+    /// Deposit collateral of a compounded prize, accumulating the time-weighted balance of the
+    /// prize period first. Keeps the exit fee window, the prize was never deposited from outside.
+    pub fn deposit_compounded_prize(
+        &mut self,
+        index: usize,
+        collateral_amount: u64,
+        slot: Slot,
+        prize: &Prize,
+    ) -> ProgramResult {
+        let liquidity = &mut self.deposits[index];
+        liquidity.twab.accrue(liquidity.deposited_amount, slot, prize)?;
+        liquidity.deposit(collateral_amount)
+    }
+    /// Withdraw collateral and remove it from deposits if nothing is left in it, accumulating the
+    /// time-weighted balance of the prize period first
+    pub fn withdraw(
+        &mut self,
+        withdraw_amount: u64,
+        collateral_index: usize,
+        slot: Slot,
+        prize: &Prize,
+    ) -> ProgramResult {
         let collateral = &mut self.deposits[collateral_index];
-        if withdraw_amount == collateral.deposited_amount {
+        collateral.twab.accrue(collateral.deposited_amount, slot, prize)?;
+        collateral.withdraw(withdraw_amount)?;
+        if collateral.is_empty(prize)? {
             self.deposits.remove(collateral_index);
-        } else {
-            collateral.withdraw(withdraw_amount)?;
         }
         Ok(())
     }
 
+    /// Credit a prize won by draw `draw_number` in the pool of the collateral at `index`
+    pub fn award_prize(&mut self, index: usize, prize_amount: u64, draw_number: u64) -> ProgramResult {
+        let collateral = &mut self.deposits[index];
+        collateral.unclaimed_prize = collateral
+            .unclaimed_prize
+            .checked_add(prize_amount)
+            .ok_or(PoolingError::MathOverflow)?;
+        collateral.won_draw = draw_number;
+        Ok(())
+    }
+
+    /// Add the collateral at `index` to the index of the next draw of `prize`, weighted by its
+    /// cumulative balance of the prize period. Returns false when there is nothing to index:
+    /// sponsor tickets, balances not held during the period and collateral already indexed.
+    pub fn index_for_draw(
+        &mut self,
+        index: usize,
+        prize: &mut Prize,
+        total_balance: u128,
+    ) -> Result<bool, ProgramError> {
+        let draw_number = prize.draw_number()?;
+        let collateral = &mut self.deposits[index];
+        if self.sponsor || collateral.indexed_draw == draw_number {
+            return Ok(false);
+        }
+        let cumulative_balance = collateral
+            .twab
+            .period_cumulative_balance(collateral.deposited_amount, prize)?;
+        if cumulative_balance == 0 {
+            return Ok(false);
+        }
+        collateral.draw_offset = prize.index_ticket(cumulative_balance, total_balance)?;
+        collateral.indexed_draw = draw_number;
+        Ok(true)
+    }
+
+    /// Take the unclaimed prize of the collateral at `index` and remove the collateral
+    /// from deposits if nothing is left in it
+    pub fn claim_prize(&mut self, index: usize, prize: &Prize) -> Result<u64, ProgramError> {
+        let collateral = &mut self.deposits[index];
+        let prize_amount = collateral.unclaimed_prize;
+        if prize_amount == 0 {
+            msg!("Ticket has no prize to claim");
+            return Err(PoolingError::NoPrizeToClaim.into());
+        }
+        collateral.unclaimed_prize = 0;
+        if collateral.is_empty(prize)? {
+            self.deposits.remove(index);
+        }
+        Ok(prize_amount)
+    }
+
 
     /// Find collateral by deposit reserve
     pub fn find_collateral_in_deposits(
@@ -140,6 +231,8 @@ pub struct InitTicketParams {
     pub owner: Pubkey,
     /// Deposited collateral for the obligation, unique by deposit reserve address
     pub deposits: Vec<TicketCollateral>,
+    /// Whether the ticket sponsors the prize instead of taking part in draws
+    pub sponsor: bool,
 }
 
 impl Sealed for Ticket {}
@@ -160,6 +253,19 @@ pub struct TicketCollateral {
     pub deposited_amount: u64,
     /// Collateral market value in quote currency
     pub market_value: Decimal,
+    /// Prize won in the reserve and not claimed yet
+    pub unclaimed_prize: u64,
+    /// Time-weighted deposited amount of the prize period, weighing the draw odds
+    pub twab: Twab,
+    /// Slot of the last deposit, starting the exit fee window
+    pub deposit_slot: Slot,
+    /// Draw the collateral was last indexed for, counting from 1
+    pub indexed_draw: u64,
+    /// Start of the range of the draw index held by the collateral, as long as its cumulative
+    /// balance of the prize period
+    pub draw_offset: u128,
+    /// Last draw the collateral won a prize in
+    pub won_draw: u64,
 }
 
 impl TicketCollateral {
@@ -170,6 +276,12 @@ impl TicketCollateral {
             deposit_reserve,
             deposited_amount: 0 as u64,
             market_value: Decimal::zero(),
+            unclaimed_prize: 0,
+            twab: Twab::default(),
+            deposit_slot: 0,
+            indexed_draw: 0,
+            draw_offset: 0,
+            won_draw: 0,
         }
     }
 
@@ -190,6 +302,32 @@ impl TicketCollateral {
             .ok_or(PoolingError::MathOverflow)?;
         Ok(())
     }
+
+    /// Range of the draw index held by the collateral for draw `draw_number`, if it is indexed
+    pub fn draw_range(
+        &self,
+        draw_number: u64,
+        prize: &Prize,
+    ) -> Result<Option<(u128, u128)>, ProgramError> {
+        if self.indexed_draw != draw_number {
+            return Ok(None);
+        }
+        let draw_end = self
+            .draw_offset
+            .checked_add(self.twab.period_cumulative_balance(self.deposited_amount, prize)?)
+            .ok_or(PoolingError::MathOverflow)?;
+        Ok(Some((self.draw_offset, draw_end)))
+    }
+
+    /// Check if nothing is left in the collateral. A balance held during the current prize
+    /// period keeps it, so the ticket still takes part in the draw of the period, and so does a
+    /// balance held after its end toward the next period.
+    pub fn is_empty(&self, prize: &Prize) -> Result<bool, ProgramError> {
+        Ok(self.deposited_amount == 0
+            && self.unclaimed_prize == 0
+            && self.twab.period_cumulative_balance(0, prize)? == 0
+            && self.twab.next_cumulative_balance == 0)
+    }
 }
 
 /// Obligation liquidity state
@@ -253,9 +391,9 @@ impl ObligationLiquidity {
     }
 }
 
-const OBLIGATION_COLLATERAL_LEN: usize = 72;
-// 32 + 8 + 16 + 16
-const OBLIGATION_LEN: usize = 827; //107+720
+const OBLIGATION_COLLATERAL_LEN: usize = 176;
+// 32 + 8 + 16 + 16 + 8 + 8 + 8 + 16 + 8 + 8 + 16 + 8 + 24
+const OBLIGATION_LEN: usize = 1932; //108+64+1760
 
 impl Pack for Ticket {
     const LEN: usize = OBLIGATION_LEN;
@@ -272,6 +410,8 @@ impl Pack for Ticket {
             deposited_value,
             deposits_len,
             unclaimed_mine,
+            sponsor,
+            _padding,
             data_flat,
         ) = mut_array_refs![
             output,
@@ -283,6 +423,8 @@ impl Pack for Ticket {
             16, // deposited_value
             1, // deposits_len
             16, // unclaimed_mine
+            1, // sponsor
+            64, // _padding
             OBLIGATION_COLLATERAL_LEN * MAX_OBLIGATION_RESERVES
         ];
 
@@ -295,6 +437,7 @@ impl Pack for Ticket {
         pack_decimal(self.deposited_value, deposited_value);
         *deposits_len = u8::try_from(self.deposits.len()).unwrap().to_le_bytes();
         pack_decimal(self.unclaimed_mine, unclaimed_mine);
+        pack_bool(self.sponsor, sponsor);
         let mut offset = 0;
         // deposits
         for collateral in &self.deposits {
@@ -304,12 +447,31 @@ impl Pack for Ticket {
                 deposit_reserve,
                 deposited_amount,
                 market_value,
-                index
-            ) = mut_array_refs![deposits_flat, PUBKEY_BYTES, 8, 16,16];
+                index,
+                unclaimed_prize,
+                twab_period_start_slot,
+                twab_last_slot,
+                twab_cumulative_balance,
+                deposit_slot,
+                indexed_draw,
+                draw_offset,
+                won_draw,
+                twab_next_cumulative_balance,
+                _padding
+            ) = mut_array_refs![deposits_flat, PUBKEY_BYTES, 8, 16, 16, 8, 8, 8, 16, 8, 8, 16, 8, 16, 8];
             deposit_reserve.copy_from_slice(collateral.deposit_reserve.as_ref());
             *deposited_amount = collateral.deposited_amount.to_le_bytes();
             pack_decimal(collateral.market_value, market_value);
             pack_decimal(collateral.index, index);
+            *unclaimed_prize = collateral.unclaimed_prize.to_le_bytes();
+            *twab_period_start_slot = collateral.twab.period_start_slot.to_le_bytes();
+            *twab_last_slot = collateral.twab.last_slot.to_le_bytes();
+            *twab_cumulative_balance = collateral.twab.cumulative_balance.to_le_bytes();
+            *deposit_slot = collateral.deposit_slot.to_le_bytes();
+            *indexed_draw = collateral.indexed_draw.to_le_bytes();
+            *draw_offset = collateral.draw_offset.to_le_bytes();
+            *won_draw = collateral.won_draw.to_le_bytes();
+            *twab_next_cumulative_balance = collateral.twab.next_cumulative_balance.to_le_bytes();
             offset += OBLIGATION_COLLATERAL_LEN;
         }
     }
@@ -327,6 +489,8 @@ impl Pack for Ticket {
             deposited_value,
             deposits_len,
             unclaimed_mine,
+            sponsor,
+            _padding,
             data_flat,
         ) = array_refs![
             input,
@@ -338,6 +502,8 @@ impl Pack for Ticket {
             16,
             1,
             16,
+            1,
+            64,
             OBLIGATION_COLLATERAL_LEN * MAX_OBLIGATION_RESERVES
         ];
 
@@ -346,6 +512,10 @@ impl Pack for Ticket {
             msg!("Ticket version does not match lending program version");
             return Err(ProgramError::InvalidAccountData);
         }
+        if version != UNINITIALIZED_VERSION && version < MIN_TICKET_VERSION {
+            msg!("Ticket version {} predates the current ticket layout", version);
+            return Err(ProgramError::InvalidAccountData);
+        }
         let deposits_len = u8::from_le_bytes(*deposits_len);
         let mut deposits = Vec::with_capacity(deposits_len as usize + 1);
         let mut offset = 0;
@@ -356,13 +526,34 @@ impl Pack for Ticket {
                 deposit_reserve,
                 deposited_amount,
                 market_value,
-                index
-            ) = array_refs![deposits_flat, PUBKEY_BYTES, 8, 16, 16];
+                index,
+                unclaimed_prize,
+                twab_period_start_slot,
+                twab_last_slot,
+                twab_cumulative_balance,
+                deposit_slot,
+                indexed_draw,
+                draw_offset,
+                won_draw,
+                twab_next_cumulative_balance,
+                _padding
+            ) = array_refs![deposits_flat, PUBKEY_BYTES, 8, 16, 16, 8, 8, 8, 16, 8, 8, 16, 8, 16, 8];
             deposits.push(TicketCollateral {
                 index: unpack_decimal(index),
                 deposit_reserve: Pubkey::new(deposit_reserve),
                 deposited_amount: u64::from_le_bytes(*deposited_amount),
                 market_value: unpack_decimal(market_value),
+                unclaimed_prize: u64::from_le_bytes(*unclaimed_prize),
+                twab: Twab {
+                    period_start_slot: u64::from_le_bytes(*twab_period_start_slot),
+                    last_slot: u64::from_le_bytes(*twab_last_slot),
+                    cumulative_balance: u128::from_le_bytes(*twab_cumulative_balance),
+                    next_cumulative_balance: u128::from_le_bytes(*twab_next_cumulative_balance),
+                },
+                deposit_slot: u64::from_le_bytes(*deposit_slot),
+                indexed_draw: u64::from_le_bytes(*indexed_draw),
+                draw_offset: u128::from_le_bytes(*draw_offset),
+                won_draw: u64::from_le_bytes(*won_draw),
             });
 
             offset += OBLIGATION_COLLATERAL_LEN;
@@ -378,6 +569,7 @@ impl Pack for Ticket {
             deposits,
             deposited_value: unpack_decimal(deposited_value),
             unclaimed_mine: unpack_decimal(unclaimed_mine),
+            sponsor: unpack_bool(sponsor)?,
         })
     }
 }
@@ -421,6 +613,168 @@ mod test {
         );
     }
 
+    #[test]
+    fn claim_prize() {
+        let pool = Pubkey::new_unique();
+        let mut ticket = Ticket {
+            deposits: vec![TicketCollateral {
+                deposited_amount: 100,
+                ..TicketCollateral::new(pool, Decimal::zero())
+            }],
+            ..Ticket::default()
+        };
+        assert_eq!(
+            ticket.claim_prize(0, &Prize::default()),
+            Err(PoolingError::NoPrizeToClaim.into())
+        );
+
+        ticket.award_prize(0, 30, 1).unwrap();
+        ticket.award_prize(0, 20, 2).unwrap();
+        // withdrawing the whole deposit keeps the unclaimed prize
+        ticket.withdraw(100, 0, 0, &Prize::default()).unwrap();
+        assert_eq!(ticket.deposits.len(), 1);
+        assert_eq!(ticket.deposits[0].unclaimed_prize, 50);
+
+        assert_eq!(ticket.claim_prize(0, &Prize::default()).unwrap(), 50);
+        assert!(ticket.deposits.is_empty());
+    }
+
+    #[test]
+    fn withdrawn_deposit_kept_until_draw() {
+        let pool = Pubkey::new_unique();
+        let prize = Prize {
+            period_slots: 100,
+            ..Prize::default()
+        };
+        let mut ticket = Ticket {
+            deposits: vec![TicketCollateral::new(pool, Decimal::zero())],
+            ..Ticket::default()
+        };
+        ticket.deposit(0, 100, 0, &prize).unwrap();
+
+        // the balance held for half of the period still takes part in its draw
+        ticket.withdraw(100, 0, 50, &prize).unwrap();
+        assert_eq!(ticket.deposits.len(), 1);
+        assert_eq!(
+            ticket.deposits[0].twab.period_cumulative_balance(0, &prize).unwrap(),
+            5_000
+        );
+
+        let next_prize = Prize {
+            period_start_slot: 100,
+            ..prize
+        };
+        assert!(ticket.deposits[0].is_empty(&next_prize).unwrap());
+    }
+
+    #[test]
+    fn compounded_prize_keeps_exit_fee_window() {
+        let pool = Pubkey::new_unique();
+        let prize = Prize {
+            period_slots: 100,
+            ..Prize::default()
+        };
+        let mut ticket = Ticket {
+            deposits: vec![TicketCollateral::new(pool, Decimal::zero())],
+            ..Ticket::default()
+        };
+        ticket.deposit(0, 100, 10, &prize).unwrap();
+        ticket.deposit_compounded_prize(0, 30, 50, &prize).unwrap();
+        assert_eq!(ticket.deposits[0].deposited_amount, 130);
+        assert_eq!(ticket.deposits[0].deposit_slot, 10);
+        // the prize still accrues the time-weighted balance from the slot it was compounded
+        assert_eq!(
+            ticket.deposits[0].twab.period_cumulative_balance(130, &prize).unwrap(),
+            100 * 40 + 130 * 50
+        );
+
+        ticket.deposit(0, 10, 60, &prize).unwrap();
+        assert_eq!(ticket.deposits[0].deposit_slot, 60);
+    }
+
+    #[test]
+    fn claim_mine_keeps_remainder() {
+        let mut ticket = Ticket {
+            unclaimed_mine: Decimal::from(7u64).try_add(Decimal::from_percent(25)).unwrap(),
+            ..Ticket::default()
+        };
+        assert_eq!(ticket.claim_mine().unwrap(), 7);
+        assert_eq!(ticket.unclaimed_mine, Decimal::from_percent(25));
+        assert_eq!(ticket.claim_mine().unwrap(), 0);
+        assert_eq!(ticket.unclaimed_mine, Decimal::from_percent(25));
+    }
+
+    #[test]
+    fn sponsor_ticket_pack_round_trip() {
+        let mut ticket = Ticket::new(InitTicketParams {
+            current_slot: 3,
+            pool_manager: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            deposits: vec![TicketCollateral::new(Pubkey::new_unique(), Decimal::one())],
+            sponsor: true,
+        });
+        ticket.deposit(0, 100, 5, &Prize::default()).unwrap();
+
+        let mut data = [0u8; Ticket::LEN];
+        Ticket::pack(ticket.clone(), &mut data).unwrap();
+        let unpacked = Ticket::unpack(&data).unwrap();
+        assert!(unpacked.sponsor);
+        assert_eq!(unpacked, ticket);
+    }
+
+    #[test]
+    fn index_deposit_for_draw() {
+        let pool = Pubkey::new_unique();
+        let mut prize = Prize {
+            period_slots: 100,
+            ..Prize::default()
+        };
+        let mut ticket = Ticket {
+            deposits: vec![TicketCollateral::new(pool, Decimal::zero())],
+            ..Ticket::default()
+        };
+        assert!(!ticket.index_for_draw(0, &mut prize, 10_000).unwrap());
+
+        ticket.deposit(0, 100, 0, &prize).unwrap();
+        assert!(ticket.index_for_draw(0, &mut prize, 10_000).unwrap());
+        assert!(!ticket.index_for_draw(0, &mut prize, 10_000).unwrap());
+        assert_eq!(prize.indexed_balance, 10_000);
+        assert_eq!(ticket.deposits[0].draw_range(1, &prize).unwrap(), Some((0, 10_000)));
+        assert_eq!(ticket.deposits[0].draw_range(2, &prize).unwrap(), None);
+
+        // deposits after the period end leave the range untouched
+        ticket.deposit(0, 50, 120, &prize).unwrap();
+        assert_eq!(ticket.deposits[0].draw_range(1, &prize).unwrap(), Some((0, 10_000)));
+
+        ticket.award_prize(0, 30, 1).unwrap();
+        let mut data = [0u8; Ticket::LEN];
+        Ticket::pack(ticket.clone(), &mut data).unwrap();
+        assert_eq!(Ticket::unpack(&data).unwrap(), ticket);
+        assert_eq!(ticket.deposits[0].won_draw, 1);
+
+        let mut sponsor = Ticket {
+            sponsor: true,
+            ..ticket
+        };
+        prize.record_draw(120, Pubkey::default(), 30).unwrap();
+        assert!(!sponsor.index_for_draw(0, &mut prize, 15_000).unwrap());
+    }
+
+    #[test]
+    fn old_ticket_version_rejected() {
+        let ticket = Ticket {
+            version: MIN_TICKET_VERSION - 1,
+            deposits: vec![TicketCollateral::new(Pubkey::new_unique(), Decimal::one())],
+            ..Ticket::default()
+        };
+        let mut data = [0u8; Ticket::LEN];
+        Ticket::pack(ticket, &mut data).unwrap();
+        assert_eq!(Ticket::unpack(&data), Err(ProgramError::InvalidAccountData));
+
+        data[0] = MIN_TICKET_VERSION;
+        assert_eq!(Ticket::unpack(&data).unwrap().deposits.len(), 1);
+    }
+
     // Creates rates (r1, r2) where 0 < r1 <= r2 <= 100*r1
     prop_compose! {
         fn cumulative_rates()(rate in 1..=u128::MAX)(
@@ -459,20 +813,14 @@ mod test {
         ) {
             let borrowed_amount_wads = Decimal::from_scaled_val(borrowed_amount);
             let repay_amount_wads = Decimal::from_scaled_val(repay_amount);
-            println!("borrowed_amount_wads=,repay_amount_wads");
-            println!("{}",borrowed_amount_wads);
-            println!("{}",repay_amount_wads);
-            let mut obligation = Ticket {
-                borrows: vec![ObligationLiquidity {
-                    borrowed_amount_wads,
-                    ..ObligationLiquidity::default()
-                }],
-                ..Ticket::default()
+            let mut liquidity = ObligationLiquidity {
+                borrowed_amount_wads,
+                ..ObligationLiquidity::default()
             };
 
-            obligation.repay(repay_amount_wads, 0)?;
-            assert!(obligation.borrows[0].borrowed_amount_wads < borrowed_amount_wads);
-            assert!(obligation.borrows[0].borrowed_amount_wads > Decimal::zero());
+            liquidity.repay(repay_amount_wads)?;
+            assert!(liquidity.borrowed_amount_wads < borrowed_amount_wads);
+            assert!(liquidity.borrowed_amount_wads > Decimal::zero());
         }
 
         #[test]
@@ -481,16 +829,13 @@ mod test {
         ) {
             let borrowed_amount_wads = Decimal::from_scaled_val(borrowed_amount);
             let repay_amount_wads = Decimal::from_scaled_val(repay_amount);
-            let mut obligation = Ticket {
-                borrows: vec![ObligationLiquidity {
-                    borrowed_amount_wads,
-                    ..ObligationLiquidity::default()
-                }],
-                ..Ticket::default()
+            let mut liquidity = ObligationLiquidity {
+                borrowed_amount_wads,
+                ..ObligationLiquidity::default()
             };
 
-            obligation.repay(repay_amount_wads, 0)?;
-            assert_eq!(obligation.borrows.len(), 0);
+            liquidity.repay(repay_amount_wads)?;
+            assert_eq!(liquidity.borrowed_amount_wads, Decimal::zero());
         }
 
         #[test]
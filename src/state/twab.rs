@@ -0,0 +1,208 @@
+use super::*;
+use crate::error::PoolingError;
+use solana_program::{clock::Slot, entrypoint::ProgramResult, program_error::ProgramError};
+use std::convert::TryFrom;
+
+/// Time-weighted balance accumulated over the current prize period. Once the period is over and
+/// waits for its draw, balance changes are accumulated for the period they happen in, which
+/// becomes the current period when the draw starts it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Twab {
+    /// Start slot of the prize period the balance is accumulated over
+    pub period_start_slot: Slot,
+    /// Slot the balance is accumulated up to
+    pub last_slot: Slot,
+    /// Sum of the balance times the slots it was held during the period
+    pub cumulative_balance: u128,
+    /// Sum of the balance times the slots it was held during the later period containing
+    /// `last_slot`, once past the end of the period
+    pub next_cumulative_balance: u128,
+}
+
+impl Twab {
+    /// Accumulate `balance`, held since the last update, up to `slot`.
+    /// Must be called before every change of the balance.
+    pub fn accrue(&mut self, balance: u64, slot: Slot, prize: &Prize) -> ProgramResult {
+        *self = self.accrued(balance, slot, prize)?;
+        Ok(())
+    }
+
+    /// Cumulative balance of the current prize period at `slot`, capped at the end of the period
+    pub fn cumulative_balance_at(
+        &self,
+        balance: u64,
+        slot: Slot,
+        prize: &Prize,
+    ) -> Result<u128, ProgramError> {
+        Ok(self.accrued(balance, slot, prize)?.cumulative_balance)
+    }
+
+    /// Cumulative balance over the whole prize period
+    pub fn period_cumulative_balance(&self, balance: u64, prize: &Prize) -> Result<u128, ProgramError> {
+        self.cumulative_balance_at(balance, prize.period_end_slot()?, prize)
+    }
+
+    /// Average balance over the whole prize period
+    pub fn average_balance(&self, balance: u64, prize: &Prize) -> Result<u64, ProgramError> {
+        let cumulative_balance = self.period_cumulative_balance(balance, prize)?;
+        u64::try_from(cumulative_balance / prize.period_slots as u128)
+            .map_err(|_| PoolingError::MathOverflow.into())
+    }
+
+    /// Balance accumulated over the current prize period and the later period containing `slot`
+    fn accrued(&self, balance: u64, slot: Slot, prize: &Prize) -> Result<Self, ProgramError> {
+        let period_start_slot = prize.period_start_slot;
+        let period_end_slot = prize.period_end_slot()?;
+        let mut twab = if self.period_start_slot == period_start_slot {
+            self.clone()
+        } else if self.last_slot >= period_start_slot
+            && later_period_start(self.period_start_slot, self.last_slot, prize.period_slots)
+                == Some(period_start_slot)
+        {
+            // last updated after the end of the previous period, within this one
+            Self {
+                period_start_slot,
+                last_slot: self.last_slot,
+                cumulative_balance: self.next_cumulative_balance,
+                next_cumulative_balance: 0,
+            }
+        } else {
+            // last updated in a previous period, the balance is held since the period start
+            Self {
+                period_start_slot,
+                last_slot: period_start_slot,
+                cumulative_balance: 0,
+                next_cumulative_balance: 0,
+            }
+        };
+
+        let slot = slot.max(twab.last_slot);
+        let slots = slot.min(period_end_slot) - twab.last_slot.min(period_end_slot);
+        twab.cumulative_balance = accumulate(twab.cumulative_balance, balance, slots)?;
+        let next_start_slot = later_period_start(period_start_slot, slot, prize.period_slots);
+        if let Some(next_start_slot) = next_start_slot {
+            let (next_cumulative_balance, next_last_slot) =
+                if later_period_start(period_start_slot, twab.last_slot, prize.period_slots)
+                    == Some(next_start_slot)
+                {
+                    (twab.next_cumulative_balance, twab.last_slot)
+                } else {
+                    (0, next_start_slot)
+                };
+            twab.next_cumulative_balance =
+                accumulate(next_cumulative_balance, balance, slot - next_last_slot)?;
+        }
+        twab.last_slot = slot;
+        Ok(twab)
+    }
+}
+
+/// Start of the later period containing `slot`, periods of `period_slots` following the one
+/// starting at `period_start_slot`, if `slot` is past the end of that one
+fn later_period_start(period_start_slot: Slot, slot: Slot, period_slots: u64) -> Option<Slot> {
+    if period_slots == 0 {
+        return None;
+    }
+    let periods_elapsed = slot.checked_sub(period_start_slot)? / period_slots;
+    if periods_elapsed == 0 {
+        return None;
+    }
+    Some(period_start_slot + periods_elapsed * period_slots)
+}
+
+/// Add `balance` held for `slots` to `cumulative_balance`
+fn accumulate(cumulative_balance: u128, balance: u64, slots: u64) -> Result<u128, ProgramError> {
+    (balance as u128)
+        .checked_mul(slots as u128)
+        .and_then(|accrued| accrued.checked_add(cumulative_balance))
+        .ok_or_else(|| PoolingError::MathOverflow.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use solana_program::pubkey::Pubkey;
+
+    fn prize(period_start_slot: Slot) -> Prize {
+        Prize {
+            period_slots: 100,
+            period_start_slot,
+            ..Prize::default()
+        }
+    }
+
+    #[test]
+    fn late_deposit_weighs_less() {
+        let prize = prize(1000);
+        let mut early = Twab::default();
+        early.accrue(0, 1000, &prize).unwrap();
+        let mut late = Twab::default();
+        late.accrue(0, 1090, &prize).unwrap();
+
+        // both hold 100 at the end of the period
+        assert_eq!(early.average_balance(100, &prize).unwrap(), 100);
+        assert_eq!(late.average_balance(100, &prize).unwrap(), 10);
+
+        // slots after the end of the period do not count
+        late.accrue(100, 1150, &prize).unwrap();
+        assert_eq!(late.cumulative_balance, 1000);
+        assert_eq!(late.average_balance(1000, &prize).unwrap(), 10);
+    }
+
+    #[test]
+    fn balance_changes_within_period() {
+        let prize = prize(1000);
+        let mut twab = Twab::default();
+        twab.accrue(0, 1010, &prize).unwrap();
+        // 50 held for 40 slots, then 20 held for the last 50 slots
+        twab.accrue(50, 1050, &prize).unwrap();
+        assert_eq!(twab.cumulative_balance, 2000);
+        assert_eq!(twab.average_balance(20, &prize).unwrap(), 30);
+    }
+
+    #[test]
+    fn new_period_starts_from_held_balance() {
+        let mut twab = Twab::default();
+        twab.accrue(0, 1050, &prize(1000)).unwrap();
+        twab.accrue(40, 1080, &prize(1000)).unwrap();
+
+        // the next period only counts the balance held since its start
+        let next = prize(1100);
+        assert_eq!(twab.cumulative_balance_at(40, 1120, &next).unwrap(), 800);
+        twab.accrue(40, 1120, &next).unwrap();
+        assert_eq!(twab.period_start_slot, 1100);
+        assert_eq!(twab.average_balance(40, &next).unwrap(), 40);
+    }
+
+    #[test]
+    fn balance_changes_before_draw_count_from_change() {
+        let prize = prize(1000);
+        let mut twab = Twab::default();
+        twab.accrue(0, 1000, &prize).unwrap();
+
+        // the period is over at 1100 and its draw only happens at 1150, 100 more deposited at 1130
+        twab.accrue(50, 1130, &prize).unwrap();
+        assert_eq!(twab.period_cumulative_balance(150, &prize).unwrap(), 5_000);
+        assert_eq!(twab.next_cumulative_balance, 1_500);
+
+        // the next period does not credit the deposit back to its start
+        let mut next = prize.clone();
+        next.record_draw(1150, Pubkey::default(), 0).unwrap();
+        assert_eq!(next.period_start_slot, 1100);
+        assert_eq!(twab.cumulative_balance_at(150, 1150, &next).unwrap(), 1_500 + 150 * 20);
+        assert_eq!(twab.average_balance(150, &next).unwrap(), 50 * 30 / 100 + 150 * 70 / 100);
+        twab.accrue(150, 1160, &next).unwrap();
+        assert_eq!(twab.period_start_slot, 1100);
+        assert_eq!(twab.cumulative_balance, 1_500 + 150 * 30);
+        assert_eq!(twab.next_cumulative_balance, 0);
+
+        // changes in a period whose draw is skipped do not count toward a later one
+        let mut skipped = Twab::default();
+        skipped.accrue(0, 1000, &prize).unwrap();
+        skipped.accrue(50, 1130, &prize).unwrap();
+        let mut after_skip = prize.clone();
+        after_skip.record_draw(1250, Pubkey::default(), 0).unwrap();
+        assert_eq!(after_skip.period_start_slot, 1200);
+        assert_eq!(skipped.cumulative_balance_at(150, 1250, &after_skip).unwrap(), 150 * 50);
+    }
+}
@@ -0,0 +1,387 @@
+//! Interface of the external lending program a pool yield source supplies its liquidity to.
+//!
+//! The lending program holds deposited liquidity in a reserve and issues shares for it, which
+//! are redeemable for a growing amount of liquidity as the reserve earns interest. In tests and
+//! with the `test-bpf` feature, `mock::process_instruction` implements the interface and can be
+//! registered under any program id in `solana-program-test`, with a reserve account filled with
+//! [`LendingReserve::new`].
+
+use bytemuck::{bytes_of, Pod, PodCastError, Zeroable};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use std::{convert::TryFrom, mem::size_of};
+
+use crate::error::PoolingError;
+
+/// Magic number identifying a lending reserve account
+pub const MAGIC: u32 = 0x5e1d50c3;
+/// First lending reserve layout version
+pub const VERSION_1: u32 = 1;
+/// Current lending reserve layout version
+pub const VERSION: u32 = VERSION_1;
+
+/// Reserve account of the lending program
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct LendingReserve {
+    /// Lending reserve magic number
+    pub magic: u32,
+    /// Layout version
+    pub ver: u32,
+    /// Mint of the liquidity lent
+    pub liquidity_mint: [u8; 32],
+    /// Token account holding the reserve liquidity
+    pub liquidity_supply: [u8; 32],
+    /// Mint of the shares issued to depositors
+    pub share_mint: [u8; 32],
+    /// Liquidity redeemable by all shares, interest included
+    pub total_liquidity: u64,
+    /// Shares issued
+    pub total_shares: u64,
+    /// Bump seed of the reserve authority
+    pub bump_seed: u8,
+    /// Unused, keeps the account size aligned
+    pub unused: [u8; 7],
+}
+
+#[cfg(target_endian = "little")]
+unsafe impl Zeroable for LendingReserve {}
+
+#[cfg(target_endian = "little")]
+unsafe impl Pod for LendingReserve {}
+
+impl LendingReserve {
+    /// Create an empty reserve, as initialized by the (mock) lending program
+    pub fn new(
+        liquidity_mint: &Pubkey,
+        liquidity_supply: &Pubkey,
+        share_mint: &Pubkey,
+        bump_seed: u8,
+    ) -> Self {
+        Self {
+            magic: MAGIC,
+            ver: VERSION,
+            liquidity_mint: liquidity_mint.to_bytes(),
+            liquidity_supply: liquidity_supply.to_bytes(),
+            share_mint: share_mint.to_bytes(),
+            total_liquidity: 0,
+            total_shares: 0,
+            bump_seed,
+            unused: [0u8; 7],
+        }
+    }
+
+    /// Serialize the reserve into account data
+    pub fn to_account_data(&self) -> Vec<u8> {
+        bytes_of(self).to_vec()
+    }
+
+    /// Liquidity redeemable for `share_amount`, rounded down
+    pub fn shares_to_liquidity(&self, share_amount: u64) -> Result<u64, ProgramError> {
+        if self.total_shares == 0 {
+            return Ok(0);
+        }
+        let liquidity_amount =
+            share_amount as u128 * self.total_liquidity as u128 / self.total_shares as u128;
+        Ok(u64::try_from(liquidity_amount).map_err(|_| PoolingError::MathOverflow)?)
+    }
+
+    /// Shares issued for `liquidity_amount`, rounded down
+    pub fn liquidity_to_shares(&self, liquidity_amount: u64) -> Result<u64, ProgramError> {
+        if self.total_shares == 0 || self.total_liquidity == 0 {
+            return Ok(liquidity_amount);
+        }
+        let share_amount =
+            liquidity_amount as u128 * self.total_shares as u128 / self.total_liquidity as u128;
+        Ok(u64::try_from(share_amount).map_err(|_| PoolingError::MathOverflow)?)
+    }
+}
+
+/// Load a lending reserve from account data
+pub fn load(data: &[u8]) -> Result<&LendingReserve, PodCastError> {
+    if data.len() < size_of::<LendingReserve>() {
+        return Err(PodCastError::SizeMismatch);
+    }
+    crate::pyth::load::<LendingReserve>(data)
+}
+
+/// Instructions of the lending program
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LendingInstruction {
+    /// Deposit liquidity into the reserve in exchange for shares
+    Supply {
+        /// Amount of liquidity to deposit
+        liquidity_amount: u64,
+    },
+    /// Burn shares in exchange for the liquidity they are worth
+    Redeem {
+        /// Amount of shares to burn
+        share_amount: u64,
+    },
+}
+
+impl LendingInstruction {
+    /// Pack the instruction into a byte buffer
+    pub fn pack(&self) -> Vec<u8> {
+        let (tag, amount) = match self {
+            Self::Supply { liquidity_amount } => (0u8, liquidity_amount),
+            Self::Redeem { share_amount } => (1u8, share_amount),
+        };
+        let mut data = Vec::with_capacity(9);
+        data.push(tag);
+        data.extend_from_slice(&amount.to_le_bytes());
+        data
+    }
+
+    /// Unpack a byte buffer into an instruction
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != 9 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let mut amount = [0u8; 8];
+        amount.copy_from_slice(&input[1..]);
+        let amount = u64::from_le_bytes(amount);
+        match input[0] {
+            0 => Ok(Self::Supply {
+                liquidity_amount: amount,
+            }),
+            1 => Ok(Self::Redeem {
+                share_amount: amount,
+            }),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// Accounts of a lending instruction. Supply moves liquidity from `source` and issues shares
+/// to `destination`, Redeem burns shares from `source` and pays liquidity to `destination`.
+pub struct LendingAccounts {
+    /// Token account the liquidity or shares are taken from
+    pub source: Pubkey,
+    /// Token account the shares or liquidity are paid to
+    pub destination: Pubkey,
+    /// Lending reserve account
+    pub reserve: Pubkey,
+    /// Token account holding the reserve liquidity
+    pub reserve_liquidity_supply: Pubkey,
+    /// Mint of the reserve shares
+    pub share_mint: Pubkey,
+    /// Authority of the reserve liquidity supply and share mint
+    pub reserve_authority: Pubkey,
+    /// Authority allowed to transfer or burn from `source`
+    pub user_transfer_authority: Pubkey,
+    /// Token program id
+    pub token_program_id: Pubkey,
+}
+
+impl LendingAccounts {
+    /// Build `instruction` for the lending program `program_id`
+    pub fn instruction(&self, program_id: &Pubkey, instruction: LendingInstruction) -> Instruction {
+        Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(self.source, false),
+                AccountMeta::new(self.destination, false),
+                AccountMeta::new(self.reserve, false),
+                AccountMeta::new(self.reserve_liquidity_supply, false),
+                AccountMeta::new(self.share_mint, false),
+                AccountMeta::new_readonly(self.reserve_authority, false),
+                AccountMeta::new_readonly(self.user_transfer_authority, true),
+                AccountMeta::new_readonly(self.token_program_id, false),
+            ],
+            data: instruction.pack(),
+        }
+    }
+}
+
+/// Derive the authority of a reserve, owning its liquidity supply and share mint
+pub fn find_reserve_authority(program_id: &Pubkey, reserve: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[reserve.as_ref()], program_id)
+}
+
+/// Mock lending program for tests
+#[cfg(any(test, feature = "test-bpf"))]
+pub mod mock {
+    use super::*;
+    use solana_program::{
+        account_info::{next_account_info, AccountInfo},
+        entrypoint::ProgramResult,
+        msg,
+        program::{invoke, invoke_signed},
+    };
+
+    /// Process a lending instruction the way the external lending program does
+    pub fn process_instruction(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        input: &[u8],
+    ) -> ProgramResult {
+        let instruction = LendingInstruction::unpack(input)?;
+        let account_info_iter = &mut accounts.iter();
+        let source_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let reserve_info = next_account_info(account_info_iter)?;
+        let reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+        let share_mint_info = next_account_info(account_info_iter)?;
+        let reserve_authority_info = next_account_info(account_info_iter)?;
+        let user_transfer_authority_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        if reserve_info.owner != program_id {
+            msg!("Lending reserve is not owned by the lending program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut reserve = *load(&reserve_info.data.borrow())
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        if reserve.liquidity_supply != reserve_liquidity_supply_info.key.to_bytes()
+            || reserve.share_mint != share_mint_info.key.to_bytes()
+        {
+            msg!("Lending reserve accounts do not match");
+            return Err(ProgramError::InvalidArgument);
+        }
+        let authority_signer_seeds = &[reserve_info.key.as_ref(), &[reserve.bump_seed]];
+
+        match instruction {
+            LendingInstruction::Supply { liquidity_amount } => {
+                let share_amount = reserve.liquidity_to_shares(liquidity_amount)?;
+                invoke(
+                    &spl_token::instruction::transfer(
+                        token_program_info.key,
+                        source_info.key,
+                        reserve_liquidity_supply_info.key,
+                        user_transfer_authority_info.key,
+                        &[],
+                        liquidity_amount,
+                    )?,
+                    &[
+                        source_info.clone(),
+                        reserve_liquidity_supply_info.clone(),
+                        user_transfer_authority_info.clone(),
+                        token_program_info.clone(),
+                    ],
+                )?;
+                invoke_signed(
+                    &spl_token::instruction::mint_to(
+                        token_program_info.key,
+                        share_mint_info.key,
+                        destination_info.key,
+                        reserve_authority_info.key,
+                        &[],
+                        share_amount,
+                    )?,
+                    &[
+                        share_mint_info.clone(),
+                        destination_info.clone(),
+                        reserve_authority_info.clone(),
+                        token_program_info.clone(),
+                    ],
+                    &[authority_signer_seeds],
+                )?;
+                reserve.total_liquidity = reserve
+                    .total_liquidity
+                    .checked_add(liquidity_amount)
+                    .ok_or(PoolingError::MathOverflow)?;
+                reserve.total_shares = reserve
+                    .total_shares
+                    .checked_add(share_amount)
+                    .ok_or(PoolingError::MathOverflow)?;
+            }
+            LendingInstruction::Redeem { share_amount } => {
+                let liquidity_amount = reserve.shares_to_liquidity(share_amount)?;
+                invoke(
+                    &spl_token::instruction::burn(
+                        token_program_info.key,
+                        source_info.key,
+                        share_mint_info.key,
+                        user_transfer_authority_info.key,
+                        &[],
+                        share_amount,
+                    )?,
+                    &[
+                        source_info.clone(),
+                        share_mint_info.clone(),
+                        user_transfer_authority_info.clone(),
+                        token_program_info.clone(),
+                    ],
+                )?;
+                invoke_signed(
+                    &spl_token::instruction::transfer(
+                        token_program_info.key,
+                        reserve_liquidity_supply_info.key,
+                        destination_info.key,
+                        reserve_authority_info.key,
+                        &[],
+                        liquidity_amount,
+                    )?,
+                    &[
+                        reserve_liquidity_supply_info.clone(),
+                        destination_info.clone(),
+                        reserve_authority_info.clone(),
+                        token_program_info.clone(),
+                    ],
+                    &[authority_signer_seeds],
+                )?;
+                reserve.total_liquidity = reserve
+                    .total_liquidity
+                    .checked_sub(liquidity_amount)
+                    .ok_or(PoolingError::MathOverflow)?;
+                reserve.total_shares = reserve
+                    .total_shares
+                    .checked_sub(share_amount)
+                    .ok_or(PoolingError::MathOverflow)?;
+            }
+        }
+        reserve_info.data.borrow_mut()[..size_of::<LendingReserve>()]
+            .copy_from_slice(bytes_of(&reserve));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mock_reserve_round_trip() {
+        let mut reserve = LendingReserve::new(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            254,
+        );
+        assert_eq!(reserve.liquidity_to_shares(100).unwrap(), 100);
+        assert_eq!(reserve.shares_to_liquidity(100).unwrap(), 0);
+
+        // interest earned by the reserve makes shares worth more liquidity
+        reserve.total_liquidity = 150;
+        reserve.total_shares = 100;
+        assert_eq!(reserve.liquidity_to_shares(30).unwrap(), 20);
+        assert_eq!(reserve.shares_to_liquidity(21).unwrap(), 31);
+
+        let data = reserve.to_account_data();
+        assert_eq!(data.len(), size_of::<LendingReserve>());
+        let loaded = load(&data).unwrap();
+        assert_eq!(loaded.magic, MAGIC);
+        assert_eq!(loaded.ver, VERSION);
+        assert_eq!(loaded.share_mint, reserve.share_mint);
+        assert_eq!(loaded.total_liquidity, 150);
+        assert_eq!(loaded.bump_seed, 254);
+        assert!(load(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn instruction_pack_round_trip() {
+        for instruction in [
+            LendingInstruction::Supply { liquidity_amount: 7 },
+            LendingInstruction::Redeem { share_amount: u64::MAX },
+        ]
+        .iter()
+        {
+            assert_eq!(LendingInstruction::unpack(&instruction.pack()).unwrap(), *instruction);
+        }
+        assert!(LendingInstruction::unpack(&[2u8; 9]).is_err());
+    }
+}
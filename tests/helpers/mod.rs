@@ -0,0 +1,185 @@
+#![allow(dead_code)]
+
+use allwin_protocol::{
+    randomness::RandomnessResult,
+    state::{
+        InitPoolManagerParams, InitTicketParams, Pool, PoolManager, Ticket, TicketCollateral,
+        PROGRAM_VERSION,
+    },
+};
+use solana_program::{
+    program_option::COption,
+    program_pack::{IsInitialized, Pack},
+    pubkey::Pubkey,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    instruction::InstructionError,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+
+pub const LAMPORTS_TO_SOL: u64 = 1_000_000_000;
+
+pub fn program_test() -> ProgramTest {
+    ProgramTest::new(
+        "allwin_protocol",
+        allwin_protocol::id(),
+        processor!(allwin_protocol::processor::process_instruction),
+    )
+}
+
+pub struct TestPoolManager {
+    pub pubkey: Pubkey,
+    pub owner: Keypair,
+    pub authority: Pubkey,
+    pub randomness_program_id: Pubkey,
+}
+
+pub fn add_pool_manager(test: &mut ProgramTest) -> TestPoolManager {
+    let pubkey = Pubkey::new_unique();
+    let owner = Keypair::new();
+    let randomness_program_id = Pubkey::new_unique();
+    let (authority, bump_seed) =
+        Pubkey::find_program_address(&[pubkey.as_ref()], &allwin_protocol::id());
+    let pool_manager = PoolManager::new(InitPoolManagerParams {
+        bump_seed,
+        owner: owner.pubkey(),
+        quote_currency: [0u8; 32],
+        token_program_id: spl_token::id(),
+        oracle_program_id: Pubkey::new_unique(),
+        mine_mint: Pubkey::new_unique(),
+        mine_supply_account: Pubkey::new_unique(),
+        randomness_program_id,
+    });
+    add_packed(test, pubkey, pool_manager, &allwin_protocol::id());
+    TestPoolManager {
+        pubkey,
+        owner,
+        authority,
+        randomness_program_id,
+    }
+}
+
+/// Add an initialized pool of `pool_manager`, `pool` filling in the rest of its state
+pub fn add_pool(test: &mut ProgramTest, pool_manager: &TestPoolManager, pubkey: Pubkey, pool: Pool) {
+    let pool = Pool {
+        version: PROGRAM_VERSION,
+        pool_manager: pool_manager.pubkey,
+        ..pool
+    };
+    add_packed(test, pubkey, pool, &allwin_protocol::id());
+}
+
+/// Add a ticket of `pool_manager` with `collateral` deposited in a pool
+pub fn add_ticket(
+    test: &mut ProgramTest,
+    pool_manager: &TestPoolManager,
+    collateral: TicketCollateral,
+) -> Pubkey {
+    let pubkey = Pubkey::new_unique();
+    let ticket = Ticket::new(InitTicketParams {
+        current_slot: 0,
+        pool_manager: pool_manager.pubkey,
+        owner: Pubkey::new_unique(),
+        deposits: vec![collateral],
+        sponsor: false,
+    });
+    add_packed(test, pubkey, ticket, &allwin_protocol::id());
+    pubkey
+}
+
+/// Add a randomness result account written by the oracle of `pool_manager`
+pub fn add_randomness_result(
+    test: &mut ProgramTest,
+    pool_manager: &TestPoolManager,
+    result: RandomnessResult,
+) -> Pubkey {
+    let pubkey = Pubkey::new_unique();
+    test.add_account(
+        pubkey,
+        Account {
+            lamports: LAMPORTS_TO_SOL,
+            data: result.to_account_data(),
+            owner: pool_manager.randomness_program_id,
+            ..Account::default()
+        },
+    );
+    pubkey
+}
+
+/// Add an SPL Token mint of `supply` whose tokens are minted by `mint_authority`
+pub fn add_mint(test: &mut ProgramTest, mint_authority: Option<Pubkey>, supply: u64) -> Pubkey {
+    let pubkey = Pubkey::new_unique();
+    let mint = spl_token::state::Mint {
+        mint_authority: mint_authority.map_or(COption::None, COption::Some),
+        supply,
+        decimals: 6,
+        is_initialized: true,
+        ..spl_token::state::Mint::default()
+    };
+    add_packed(test, pubkey, mint, &spl_token::id());
+    pubkey
+}
+
+/// Add an SPL Token account of `mint` owned by `owner` holding `amount`
+pub fn add_token_account(test: &mut ProgramTest, mint: Pubkey, owner: Pubkey, amount: u64) -> Pubkey {
+    let pubkey = Pubkey::new_unique();
+    let account = spl_token::state::Account {
+        mint,
+        owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..spl_token::state::Account::default()
+    };
+    add_packed(test, pubkey, account, &spl_token::id());
+    pubkey
+}
+
+pub async fn get_token_balance(banks_client: &mut BanksClient, pubkey: Pubkey) -> u64 {
+    get_state::<spl_token::state::Account>(banks_client, pubkey).await.amount
+}
+
+pub fn add_packed<T: Pack>(test: &mut ProgramTest, pubkey: Pubkey, state: T, owner: &Pubkey) {
+    let mut data = vec![0u8; T::LEN];
+    T::pack(state, &mut data).unwrap();
+    test.add_account(
+        pubkey,
+        Account {
+            lamports: LAMPORTS_TO_SOL,
+            data,
+            owner: *owner,
+            ..Account::default()
+        },
+    );
+}
+
+pub async fn get_state<T: Pack + IsInitialized>(banks_client: &mut BanksClient, pubkey: Pubkey) -> T {
+    let account = banks_client.get_account(pubkey).await.unwrap().unwrap();
+    T::unpack(&account.data).unwrap()
+}
+
+pub async fn process_transaction(
+    context: &mut ProgramTestContext,
+    instructions: &[solana_sdk::instruction::Instruction],
+    signers: &[&Keypair],
+) -> Result<(), TransactionError> {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+pub fn custom_error(error: allwin_protocol::error::PoolingError) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+}
@@ -0,0 +1,456 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use allwin_protocol::{
+    error::PoolingError,
+    math::Decimal,
+    randomness::{RandomnessResult, ResultStatus},
+    state::{LastUpdate, Pool, Prize, PrizeTier, ReserveLiquidity, Ticket, TicketCollateral},
+};
+use helpers::*;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    sysvar,
+};
+use solana_program_test::*;
+use solana_sdk::signature::Signer;
+
+const PERIOD_SLOTS: u64 = 100;
+const DRAW_SLOT: u64 = 150;
+
+/// Pool whose first prize period is over, refreshed at the draw slot, with a prize of 100
+/// and two tickets holding 100 and 300 of collateral over the whole period
+fn drawable_pool(randomness_account: Pubkey) -> Pool {
+    Pool {
+        last_update: LastUpdate {
+            slot: DRAW_SLOT,
+            stale: false,
+        },
+        liquidity: ReserveLiquidity {
+            available_amount: 1_100,
+            principal_amount: 1_000,
+            ..ReserveLiquidity::default()
+        },
+        prize: Prize {
+            period_slots: PERIOD_SLOTS,
+            tiers: vec![PrizeTier::grand_prize()],
+            randomness_account,
+            ..Prize::default()
+        },
+        ticket_balance: 400,
+        ..Pool::default()
+    }
+}
+
+fn add_drawable_tickets(
+    test: &mut ProgramTest,
+    pool_manager: &TestPoolManager,
+    pool_pubkey: Pubkey,
+) -> Vec<Pubkey> {
+    add_tickets(test, pool_manager, pool_pubkey, &[100, 300])
+}
+
+fn add_tickets(
+    test: &mut ProgramTest,
+    pool_manager: &TestPoolManager,
+    pool_pubkey: Pubkey,
+    deposited_amounts: &[u64],
+) -> Vec<Pubkey> {
+    deposited_amounts
+        .iter()
+        .map(|deposited_amount| {
+            add_ticket(
+                test,
+                pool_manager,
+                TicketCollateral {
+                    deposited_amount: *deposited_amount,
+                    ..TicketCollateral::new(pool_pubkey, Decimal::zero())
+                },
+            )
+        })
+        .collect()
+}
+
+fn randomness_result(pool_pubkey: &Pubkey, status: ResultStatus) -> RandomnessResult {
+    RandomnessResult {
+        status: status as u32,
+        ..RandomnessResult::new_fulfilled(pool_pubkey, 120, 121, [5u8; 32])
+    }
+}
+
+fn lottery_draw(
+    pool_pubkey: Pubkey,
+    pool_manager: &TestPoolManager,
+    randomness_pubkey: Pubkey,
+    tickets: &[Pubkey],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(pool_pubkey, false),
+        AccountMeta::new_readonly(pool_manager.pubkey, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(randomness_pubkey, false),
+    ];
+    accounts.extend(tickets.iter().map(|ticket| AccountMeta::new(*ticket, false)));
+    Instruction {
+        program_id: allwin_protocol::id(),
+        accounts,
+        data: vec![8],
+    }
+}
+
+fn pool_tickets_instruction(
+    tag: u8,
+    pool_pubkey: Pubkey,
+    pool_manager: &TestPoolManager,
+    tickets: &[Pubkey],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(pool_pubkey, false),
+        AccountMeta::new_readonly(pool_manager.pubkey, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+    ];
+    accounts.extend(tickets.iter().map(|ticket| AccountMeta::new(*ticket, false)));
+    Instruction {
+        program_id: allwin_protocol::id(),
+        accounts,
+        data: vec![tag],
+    }
+}
+
+fn index_draw_tickets(
+    pool_pubkey: Pubkey,
+    pool_manager: &TestPoolManager,
+    tickets: &[Pubkey],
+) -> Instruction {
+    pool_tickets_instruction(31, pool_pubkey, pool_manager, tickets)
+}
+
+fn award_draw_winners(
+    pool_pubkey: Pubkey,
+    pool_manager: &TestPoolManager,
+    tickets: &[Pubkey],
+) -> Instruction {
+    pool_tickets_instruction(32, pool_pubkey, pool_manager, tickets)
+}
+
+fn request_randomness(
+    pool_pubkey: Pubkey,
+    pool_manager: &TestPoolManager,
+    randomness_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: allwin_protocol::id(),
+        accounts: vec![
+            AccountMeta::new(pool_pubkey, false),
+            AccountMeta::new_readonly(pool_manager.pubkey, false),
+            AccountMeta::new_readonly(pool_manager.owner.pubkey(), true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(randomness_pubkey, false),
+        ],
+        data: vec![29],
+    }
+}
+
+#[tokio::test]
+async fn lottery_draw_uses_requested_randomness() {
+    let mut test = program_test();
+    let pool_manager = add_pool_manager(&mut test);
+    let pool_pubkey = Pubkey::new_unique();
+    let requested = add_randomness_result(
+        &mut test,
+        &pool_manager,
+        randomness_result(&pool_pubkey, ResultStatus::Fulfilled),
+    );
+    let other = add_randomness_result(
+        &mut test,
+        &pool_manager,
+        randomness_result(&pool_pubkey, ResultStatus::Fulfilled),
+    );
+    add_pool(&mut test, &pool_manager, pool_pubkey, drawable_pool(requested));
+    let tickets = add_drawable_tickets(&mut test, &pool_manager, pool_pubkey);
+
+    let mut context = test.start_with_context().await;
+    context.warp_to_slot(DRAW_SLOT).unwrap();
+
+    assert_eq!(
+        process_transaction(
+            &mut context,
+            &[lottery_draw(pool_pubkey, &pool_manager, requested, &tickets)],
+            &[],
+        )
+        .await,
+        Err(custom_error(PoolingError::DrawTicketsIncomplete))
+    );
+    process_transaction(
+        &mut context,
+        &[index_draw_tickets(pool_pubkey, &pool_manager, &tickets[..1])],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        process_transaction(
+            &mut context,
+            &[lottery_draw(pool_pubkey, &pool_manager, requested, &tickets[..1])],
+            &[],
+        )
+        .await,
+        Err(custom_error(PoolingError::DrawTicketsIncomplete))
+    );
+    // tickets already indexed are skipped
+    process_transaction(
+        &mut context,
+        &[index_draw_tickets(pool_pubkey, &pool_manager, &tickets)],
+        &[],
+    )
+    .await
+    .unwrap();
+    let pool: Pool = get_state(&mut context.banks_client, pool_pubkey).await;
+    assert_eq!(pool.prize.indexed_balance, 40_000);
+    assert_eq!(pool.prize.indexed_tickets, 2);
+
+    assert_eq!(
+        process_transaction(
+            &mut context,
+            &[lottery_draw(pool_pubkey, &pool_manager, other, &tickets)],
+            &[],
+        )
+        .await,
+        Err(custom_error(PoolingError::InvalidRandomnessResult))
+    );
+    process_transaction(
+        &mut context,
+        &[lottery_draw(pool_pubkey, &pool_manager, requested, &tickets)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let pool: Pool = get_state(&mut context.banks_client, pool_pubkey).await;
+    assert_eq!(pool.prize.draw_count, 1);
+    assert_eq!(pool.prize.period_start_slot, PERIOD_SLOTS);
+    assert_eq!(pool.prize.randomness_account, Pubkey::default());
+    assert_eq!(pool.prize.unclaimed_amount, 100);
+    assert!(!pool.prize.awarding);
+    assert_eq!(pool.prize.indexed_balance, 0);
+    assert!(tickets.contains(&pool.prize.last_winner));
+
+    let winner: Ticket = get_state(&mut context.banks_client, pool.prize.last_winner).await;
+    assert_eq!(winner.deposits[0].unclaimed_prize, 100);
+}
+
+#[tokio::test]
+async fn lottery_draw_over_more_tickets_than_fit_in_a_transaction() {
+    const TICKET_COUNT: usize = 40;
+    const BATCH_LEN: usize = 10;
+
+    let mut test = program_test();
+    let pool_manager = add_pool_manager(&mut test);
+    let pool_pubkey = Pubkey::new_unique();
+    let requested = add_randomness_result(
+        &mut test,
+        &pool_manager,
+        randomness_result(&pool_pubkey, ResultStatus::Fulfilled),
+    );
+    add_pool(&mut test, &pool_manager, pool_pubkey, drawable_pool(requested));
+    let tickets = add_tickets(&mut test, &pool_manager, pool_pubkey, &[10; TICKET_COUNT]);
+
+    let mut context = test.start_with_context().await;
+    context.warp_to_slot(DRAW_SLOT).unwrap();
+
+    for (batch, batch_tickets) in tickets.chunks(BATCH_LEN).enumerate() {
+        if batch == TICKET_COUNT / BATCH_LEN - 1 {
+            assert_eq!(
+                process_transaction(
+                    &mut context,
+                    &[lottery_draw(pool_pubkey, &pool_manager, requested, &[])],
+                    &[],
+                )
+                .await,
+                Err(custom_error(PoolingError::DrawTicketsIncomplete))
+            );
+        }
+        process_transaction(
+            &mut context,
+            &[index_draw_tickets(pool_pubkey, &pool_manager, batch_tickets)],
+            &[],
+        )
+        .await
+        .unwrap();
+    }
+
+    // seed the draw, the winner is awarded once the ticket holding the pick is provided
+    process_transaction(
+        &mut context,
+        &[lottery_draw(pool_pubkey, &pool_manager, requested, &tickets[..1])],
+        &[],
+    )
+    .await
+    .unwrap();
+    let pool: Pool = get_state(&mut context.banks_client, pool_pubkey).await;
+    let target = pool.prize.pick_target(pool.period_ticket_balance().unwrap()).unwrap();
+    let mut winner = None;
+    for ticket_pubkey in &tickets {
+        let ticket: Ticket = get_state(&mut context.banks_client, *ticket_pubkey).await;
+        let (start, end) = ticket.deposits[0]
+            .draw_range(pool.prize.draw_number().unwrap(), &pool.prize)
+            .unwrap()
+            .unwrap();
+        if start <= target && target < end {
+            winner = Some(*ticket_pubkey);
+        }
+    }
+    let winner = winner.unwrap();
+    let winner_batch = tickets
+        .chunks(BATCH_LEN)
+        .find(|batch_tickets| batch_tickets.contains(&winner))
+        .unwrap();
+    let other_batch = tickets
+        .chunks(BATCH_LEN)
+        .find(|batch_tickets| !batch_tickets.contains(&winner))
+        .unwrap();
+    if pool.prize.awarding {
+        assert_eq!(
+            process_transaction(
+                &mut context,
+                &[award_draw_winners(pool_pubkey, &pool_manager, other_batch)],
+                &[],
+            )
+            .await,
+            Err(custom_error(PoolingError::DrawWinnerNotProvided))
+        );
+        process_transaction(
+            &mut context,
+            &[award_draw_winners(pool_pubkey, &pool_manager, winner_batch)],
+            &[],
+        )
+        .await
+        .unwrap();
+    }
+
+    let pool: Pool = get_state(&mut context.banks_client, pool_pubkey).await;
+    assert_eq!(pool.prize.draw_count, 1);
+    assert!(!pool.prize.awarding);
+    assert_eq!(pool.prize.unclaimed_amount, 100);
+    assert_eq!(pool.prize.last_winner, winner);
+    let winner: Ticket = get_state(&mut context.banks_client, winner).await;
+    assert_eq!(winner.deposits[0].unclaimed_prize, 100);
+}
+
+#[tokio::test]
+async fn lottery_draw_rejected_while_draw_pending() {
+    let mut test = program_test();
+    let pool_manager = add_pool_manager(&mut test);
+    let pool_pubkey = Pubkey::new_unique();
+    let randomness = add_randomness_result(
+        &mut test,
+        &pool_manager,
+        randomness_result(&pool_pubkey, ResultStatus::Fulfilled),
+    );
+    let mut pool = drawable_pool(Pubkey::default());
+    pool.prize.draw_pending = true;
+    pool.prize.pending_commit_slot = 110;
+    add_pool(&mut test, &pool_manager, pool_pubkey, pool);
+    let tickets = add_drawable_tickets(&mut test, &pool_manager, pool_pubkey);
+
+    let mut context = test.start_with_context().await;
+    context.warp_to_slot(DRAW_SLOT).unwrap();
+    process_transaction(
+        &mut context,
+        &[index_draw_tickets(pool_pubkey, &pool_manager, &tickets)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        process_transaction(
+            &mut context,
+            &[lottery_draw(pool_pubkey, &pool_manager, randomness, &tickets)],
+            &[],
+        )
+        .await,
+        Err(custom_error(PoolingError::DrawAlreadyPending))
+    );
+}
+
+#[tokio::test]
+async fn request_randomness_binds_one_pending_result() {
+    let mut test = program_test();
+    let pool_manager = add_pool_manager(&mut test);
+    let pool_pubkey = Pubkey::new_unique();
+    let fulfilled = add_randomness_result(
+        &mut test,
+        &pool_manager,
+        randomness_result(&pool_pubkey, ResultStatus::Fulfilled),
+    );
+    let pending = add_randomness_result(
+        &mut test,
+        &pool_manager,
+        randomness_result(&pool_pubkey, ResultStatus::Pending),
+    );
+    let other_pending = add_randomness_result(
+        &mut test,
+        &pool_manager,
+        randomness_result(&pool_pubkey, ResultStatus::Pending),
+    );
+    let early_pending = add_randomness_result(
+        &mut test,
+        &pool_manager,
+        randomness_result(&pool_pubkey, ResultStatus::Pending),
+    );
+    add_pool(&mut test, &pool_manager, pool_pubkey, drawable_pool(Pubkey::default()));
+    let tickets = add_drawable_tickets(&mut test, &pool_manager, pool_pubkey);
+
+    let mut context = test.start_with_context().await;
+    context.warp_to_slot(DRAW_SLOT).unwrap();
+
+    // randomness cannot be bound before every ticket has its place in the draw
+    assert_eq!(
+        process_transaction(
+            &mut context,
+            &[request_randomness(pool_pubkey, &pool_manager, early_pending)],
+            &[&pool_manager.owner],
+        )
+        .await,
+        Err(custom_error(PoolingError::DrawTicketsIncomplete))
+    );
+    process_transaction(
+        &mut context,
+        &[index_draw_tickets(pool_pubkey, &pool_manager, &tickets)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        process_transaction(
+            &mut context,
+            &[request_randomness(pool_pubkey, &pool_manager, fulfilled)],
+            &[&pool_manager.owner],
+        )
+        .await,
+        Err(custom_error(PoolingError::InvalidRandomnessResult))
+    );
+    process_transaction(
+        &mut context,
+        &[request_randomness(pool_pubkey, &pool_manager, pending)],
+        &[&pool_manager.owner],
+    )
+    .await
+    .unwrap();
+    let pool: Pool = get_state(&mut context.banks_client, pool_pubkey).await;
+    assert_eq!(pool.prize.randomness_account, pending);
+
+    assert_eq!(
+        process_transaction(
+            &mut context,
+            &[request_randomness(pool_pubkey, &pool_manager, other_pending)],
+            &[&pool_manager.owner],
+        )
+        .await,
+        Err(custom_error(PoolingError::RandomnessAlreadyRequested))
+    );
+}
@@ -0,0 +1,193 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use allwin_protocol::{
+    error::PoolingError,
+    state::{Pool, ReserveLiquidity, YieldSource},
+    yield_source::{self, LendingReserve},
+};
+use helpers::*;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use solana_program_test::*;
+use solana_sdk::signature::Signer;
+
+/// Pool liquidity and the lending reserve its yield source supplies to
+struct TestYieldSource {
+    pool_pubkey: Pubkey,
+    liquidity_supply: Pubkey,
+    share_supply: Pubkey,
+    lending_program_id: Pubkey,
+    reserve: Pubkey,
+    reserve_liquidity_supply: Pubkey,
+    share_mint: Pubkey,
+    reserve_authority: Pubkey,
+}
+
+/// Add a pool holding 1_100 of liquidity for 1_000 of principal, supplying to a lending reserve
+/// whose 100 shares held by the pool are worth 150 of liquidity
+fn add_yield_source(test: &mut ProgramTest, pool_manager: &TestPoolManager) -> TestYieldSource {
+    let lending_program_id = Pubkey::new_unique();
+    test.add_program(
+        "allwin_lending_mock",
+        lending_program_id,
+        processor!(yield_source::mock::process_instruction),
+    );
+    let reserve = Pubkey::new_unique();
+    let (reserve_authority, bump_seed) =
+        yield_source::find_reserve_authority(&lending_program_id, &reserve);
+
+    let liquidity_mint = add_mint(test, None, 1_250);
+    let share_mint = add_mint(test, Some(reserve_authority), 100);
+    let liquidity_supply = add_token_account(test, liquidity_mint, pool_manager.authority, 1_100);
+    let share_supply = add_token_account(test, share_mint, pool_manager.authority, 100);
+    let reserve_liquidity_supply = add_token_account(test, liquidity_mint, reserve_authority, 150);
+    test.add_account(
+        reserve,
+        solana_sdk::account::Account {
+            lamports: LAMPORTS_TO_SOL,
+            data: LendingReserve {
+                total_liquidity: 150,
+                total_shares: 100,
+                ..LendingReserve::new(&liquidity_mint, &reserve_liquidity_supply, &share_mint, bump_seed)
+            }
+            .to_account_data(),
+            owner: lending_program_id,
+            ..solana_sdk::account::Account::default()
+        },
+    );
+
+    let pool_pubkey = Pubkey::new_unique();
+    add_pool(
+        test,
+        pool_manager,
+        pool_pubkey,
+        Pool {
+            liquidity: ReserveLiquidity {
+                mint_pubkey: liquidity_mint,
+                supply_pubkey: liquidity_supply,
+                available_amount: 1_100,
+                principal_amount: 1_000,
+                ..ReserveLiquidity::default()
+            },
+            yield_source: YieldSource {
+                program_id: lending_program_id,
+                reserve,
+                share_supply,
+                redeemable_amount: 150,
+            },
+            ..Pool::default()
+        },
+    );
+    TestYieldSource {
+        pool_pubkey,
+        liquidity_supply,
+        share_supply,
+        lending_program_id,
+        reserve,
+        reserve_liquidity_supply,
+        share_mint,
+        reserve_authority,
+    }
+}
+
+fn yield_source_liquidity(
+    yield_source: &TestYieldSource,
+    pool_manager: &TestPoolManager,
+    tag: u8,
+    amount: u64,
+) -> Instruction {
+    let mut data = vec![tag];
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: allwin_protocol::id(),
+        accounts: vec![
+            AccountMeta::new(yield_source.pool_pubkey, false),
+            AccountMeta::new(yield_source.liquidity_supply, false),
+            AccountMeta::new(yield_source.share_supply, false),
+            AccountMeta::new(yield_source.reserve, false),
+            AccountMeta::new(yield_source.reserve_liquidity_supply, false),
+            AccountMeta::new(yield_source.share_mint, false),
+            AccountMeta::new_readonly(yield_source.reserve_authority, false),
+            AccountMeta::new_readonly(yield_source.lending_program_id, false),
+            AccountMeta::new_readonly(pool_manager.pubkey, false),
+            AccountMeta::new_readonly(pool_manager.authority, false),
+            AccountMeta::new_readonly(pool_manager.owner.pubkey(), true),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data,
+    }
+}
+
+#[tokio::test]
+async fn supply_yield_source() {
+    let mut test = program_test();
+    let pool_manager = add_pool_manager(&mut test);
+    let yield_source = add_yield_source(&mut test, &pool_manager);
+    let mut context = test.start_with_context().await;
+
+    assert_eq!(
+        process_transaction(
+            &mut context,
+            &[yield_source_liquidity(&yield_source, &pool_manager, 26, 1_101)],
+            &[&pool_manager.owner],
+        )
+        .await,
+        Err(custom_error(PoolingError::InsufficientLiquidity))
+    );
+    process_transaction(
+        &mut context,
+        &[yield_source_liquidity(&yield_source, &pool_manager, 26, 600)],
+        &[&pool_manager.owner],
+    )
+    .await
+    .unwrap();
+
+    let pool: Pool = get_state(&mut context.banks_client, yield_source.pool_pubkey).await;
+    assert_eq!(pool.liquidity.available_amount, 500);
+    assert_eq!(pool.yield_source.redeemable_amount, 750);
+    assert_eq!(pool.prize_available().unwrap(), 250);
+    let banks_client = &mut context.banks_client;
+    assert_eq!(get_token_balance(banks_client, yield_source.liquidity_supply).await, 500);
+    assert_eq!(get_token_balance(banks_client, yield_source.reserve_liquidity_supply).await, 750);
+    assert_eq!(get_token_balance(banks_client, yield_source.share_supply).await, 500);
+}
+
+#[tokio::test]
+async fn redeem_yield_source() {
+    let mut test = program_test();
+    let pool_manager = add_pool_manager(&mut test);
+    let yield_source = add_yield_source(&mut test, &pool_manager);
+    let mut context = test.start_with_context().await;
+
+    process_transaction(
+        &mut context,
+        &[yield_source_liquidity(&yield_source, &pool_manager, 27, 41)],
+        &[&pool_manager.owner],
+    )
+    .await
+    .unwrap();
+    let pool: Pool = get_state(&mut context.banks_client, yield_source.pool_pubkey).await;
+    // 41 shares pay out 61 of liquidity rounded down, the rest stays redeemable
+    assert_eq!(pool.liquidity.available_amount, 1_161);
+    assert_eq!(pool.yield_source.redeemable_amount, 89);
+
+    process_transaction(
+        &mut context,
+        &[yield_source_liquidity(&yield_source, &pool_manager, 27, u64::MAX)],
+        &[&pool_manager.owner],
+    )
+    .await
+    .unwrap();
+    let pool: Pool = get_state(&mut context.banks_client, yield_source.pool_pubkey).await;
+    assert_eq!(pool.liquidity.available_amount, 1_250);
+    assert_eq!(pool.yield_source.redeemable_amount, 0);
+    assert_eq!(pool.prize_available().unwrap(), 250);
+    let banks_client = &mut context.banks_client;
+    assert_eq!(get_token_balance(banks_client, yield_source.liquidity_supply).await, 1_250);
+    assert_eq!(get_token_balance(banks_client, yield_source.reserve_liquidity_supply).await, 0);
+    assert_eq!(get_token_balance(banks_client, yield_source.share_supply).await, 0);
+}